@@ -0,0 +1,69 @@
+use std::collections::{HashMap, HashSet};
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+pub fn dedup_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Deduplicating 1M strings");
+    let strings: Vec<String> = (0..1_000_000u64)
+        .map(|i| format!("key-{}", cmhash::hash_word_stateless(i as usize) % 250_000))
+        .collect();
+    group.bench_function("dedup_by_hash", |b| {
+        let builder = cmhash::CMBuildHasher::new();
+        b.iter_batched(
+            || strings.clone(),
+            |mut v| {
+                cmhash::dedup_by_hash(&mut v, &builder);
+                black_box(v)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("HashSet of clones", |b| {
+        b.iter_batched(
+            || strings.clone(),
+            |mut v| {
+                let mut seen = HashSet::with_capacity_and_hasher(
+                    v.len(),
+                    cmhash::CMBuildHasher::new(),
+                );
+                v.retain(|s| seen.insert(s.clone()));
+                black_box(v)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+pub fn mph_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Looking up 50k keys");
+    let keys: Vec<String> = (0..50_000).map(|i| format!("key-{i}")).collect();
+    group.bench_function("MinimalPerfectHash::build", |b| {
+        b.iter(|| cmhash::MinimalPerfectHash::build(black_box(&keys)).unwrap())
+    });
+    let mph = cmhash::MinimalPerfectHash::build(&keys).unwrap();
+    let mut table = vec![(String::new(), 0); keys.len()];
+    for (i, key) in keys.iter().enumerate() {
+        table[mph.index(key)] = (key.clone(), i);
+    }
+    group.bench_function("MinimalPerfectHash", |b| {
+        b.iter(|| {
+            keys.iter().fold(0, |acc, key| {
+                let (stored, value) = &table[mph.index(key)];
+                acc + if stored == key { *value } else { 0 }
+            })
+        })
+    });
+    let map: HashMap<&str, usize> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.as_str(), i))
+        .collect();
+    group.bench_function("HashMap", |b| {
+        b.iter(|| keys.iter().fold(0, |acc, key| acc + map[key.as_str()]))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dedup_strings, mph_lookup);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// 64 MiB of buckets, far more than fits in cache
+const BUCKETS: usize = 1 << 22;
+const BATCH: usize = 16;
+
+/// A linearly probed table of key-value pairs, half full, with key 0 marking empty buckets
+struct Table {
+    buckets: Vec<[u64; 2]>,
+}
+
+impl Table {
+    fn new(keys: impl Iterator<Item = u64>) -> Self {
+        let mut buckets = vec![[0; 2]; BUCKETS];
+        for key in keys {
+            let mut i = cmhash::hash_batch(&[key])[0] as usize & (BUCKETS - 1);
+            while buckets[i][0] != 0 {
+                i = (i + 1) & (BUCKETS - 1);
+            }
+            buckets[i] = [key, !key];
+        }
+        Self { buckets }
+    }
+
+    fn probe(&self, key: u64, hash: u64) -> Option<u64> {
+        let mut i = hash as usize & (BUCKETS - 1);
+        loop {
+            match self.buckets[i] {
+                [0, _] => return None,
+                [k, v] if k == key => return Some(v),
+                _ => i = (i + 1) & (BUCKETS - 1),
+            }
+        }
+    }
+}
+
+pub fn batched_lookups(c: &mut Criterion) {
+    let table = Table::new(1..=(BUCKETS / 2) as u64);
+    // Random present keys, in batches of 16
+    let lookups: Vec<[u64; BATCH]> = (0..4096u64)
+        .map(|batch| {
+            core::array::from_fn(|i| {
+                let r = cmhash::hash_u64(batch * BATCH as u64 + i as u64, 1).0;
+                1 + r % (BUCKETS / 2) as u64
+            })
+        })
+        .collect();
+    let mut group = c.benchmark_group("Lookups in a 64 MiB Table");
+    group.throughput(Throughput::Elements((lookups.len() * BATCH) as u64));
+    group.bench_function("one at a time", |b| {
+        b.iter(|| {
+            lookups
+                .iter()
+                .flatten()
+                .filter_map(|&key| table.probe(key, cmhash::hash_batch(&[key])[0]))
+                .fold(0, u64::wrapping_add)
+        })
+    });
+    group.bench_function("16 at a time with prefetch", |b| {
+        b.iter(|| {
+            lookups
+                .iter()
+                .flat_map(|keys| {
+                    let hashes = cmhash::hash_batch_and_prefetch(
+                        keys,
+                        table.buckets.as_ptr().cast(),
+                        core::mem::size_of::<[u64; 2]>(),
+                        BUCKETS - 1,
+                    );
+                    core::array::from_fn::<_, BATCH, _>(|i| table.probe(keys[i], hashes[i]))
+                })
+                .flatten()
+                .fold(0, u64::wrapping_add)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, batched_lookups);
+criterion_main!(benches);
@@ -1,11 +1,12 @@
 use std::{
+    collections::{HashSet, VecDeque},
     hash::Hasher,
     sync::{Arc, Barrier},
     thread,
     time::Instant,
 };
 
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 
 #[allow(dead_code)]
 pub fn atomic_threaded(c: &mut Criterion) {
@@ -193,12 +194,162 @@ pub fn stateless_build_hasher_threaded(c: &mut Criterion) {
     }
 }
 
+#[allow(dead_code)]
+pub fn tl_exclusive(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Thread-Local Tight Loop");
+    let words: Vec<usize> = (0..1024).collect();
+    let bytes: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    group.bench_function("hash_word", |b| {
+        let hasher = cmhash::TLCoreHasher::new();
+        b.iter(|| {
+            words
+                .iter()
+                .fold(0, |acc, &w| acc ^ hasher.hash_word(black_box(w)))
+        })
+    });
+    group.bench_function("hash_word_mut", |b| {
+        let mut hasher = cmhash::TLCoreHasher::new();
+        b.iter(|| {
+            words
+                .iter()
+                .fold(0, |acc, &w| acc ^ hasher.hash_word_mut(black_box(w)))
+        })
+    });
+    group.bench_function("hash_bytes", |b| {
+        let hasher = cmhash::TLCoreHasher::new();
+        b.iter(|| hasher.hash_bytes(black_box(&bytes)))
+    });
+    group.bench_function("hash_bytes_mut", |b| {
+        let mut hasher = cmhash::TLCoreHasher::new();
+        b.iter(|| hasher.hash_bytes_mut(black_box(&bytes)))
+    });
+}
+
+#[allow(dead_code)]
+pub fn bytes_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash_bytes 4 KiB");
+    let bytes: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("TLCoreHasher", |b| {
+        let hasher = cmhash::TLCoreHasher::new();
+        b.iter(|| hasher.hash_bytes(black_box(&bytes)))
+    });
+    group.bench_function("CoreHasher", |b| {
+        let hasher = cmhash::CoreHasher::new();
+        b.iter(|| hasher.hash_bytes(black_box(&bytes)))
+    });
+}
+
+#[allow(dead_code)]
+pub fn page_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("4 KiB page");
+    let page: [u8; cmhash::PAGE_SIZE] = core::array::from_fn(|i| i as u8);
+    group.throughput(Throughput::Bytes(page.len() as u64));
+    group.bench_function("hash_bytes", |b| {
+        b.iter(|| cmhash::hash_bytes(black_box(&page), 0))
+    });
+    group.bench_function("hash_page", |b| {
+        b.iter(|| cmhash::hash_page(black_box(&page), 0))
+    });
+}
+
+type ContendedHash = fn(&cmhash::CoreHasher, &cmhash::TLCoreHasher) -> usize;
+
+#[allow(dead_code)]
+pub fn contended_tail_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Contended p99 Latency at 8 Threads");
+    let variants: [(&str, ContendedHash); 2] = [
+        ("hash_word", |shared, _| shared.hash_word(0xDEADBEEF)),
+        ("try_hash_word", |shared, local| {
+            shared.hash_word_or_else(0xDEADBEEF, |val| local.hash_word(val))
+        }),
+    ];
+    for (name, hash) in variants {
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                const THREADS: usize = 8;
+                let barrier = Arc::new(Barrier::new(THREADS + 1));
+                let hasher = Arc::new(cmhash::CoreHasher::new());
+                let threads: Vec<_> = (0..THREADS)
+                    .map(|_tid| {
+                        let barrier = Arc::clone(&barrier);
+                        let hasher = hasher.clone();
+                        thread::spawn(move || {
+                            let local = cmhash::TLCoreHasher::new();
+                            let mut latencies = Vec::with_capacity(iters as usize / THREADS + 1);
+                            barrier.wait();
+                            for _ in 0..(iters / THREADS as u64).max(1) {
+                                let start = Instant::now();
+                                black_box(hash(&hasher, &local));
+                                latencies.push(start.elapsed());
+                            }
+                            latencies
+                        })
+                    })
+                    .collect();
+                barrier.wait();
+                let mut latencies: Vec<_> = threads
+                    .into_iter()
+                    .flat_map(|thread| thread.join().unwrap())
+                    .collect();
+                latencies.sort_unstable();
+                // Reported per iteration, so criterion shows the 99th percentile of single calls
+                latencies[latencies.len() * 99 / 100] * iters as u32
+            })
+        });
+    }
+}
+
+#[allow(dead_code)]
+pub fn recent_set(c: &mut Criterion) {
+    const WINDOW: usize = 4096;
+    // A stream in which about half the keys repeat one seen within the window
+    let keys: Vec<u64> = (0..100_000u64)
+        .map(|i| {
+            let key = if i % 2 == 0 { i } else { i - (i * 7919) % 2048 };
+            cmhash::hash_u64(key, 0).0
+        })
+        .collect();
+    let mut group = c.benchmark_group("Duplicate Suppression");
+    group.throughput(Throughput::Elements(keys.len() as u64));
+    group.bench_function("RecentSet", |b| {
+        b.iter(|| {
+            let mut recent = Box::new(cmhash::RecentSet::<WINDOW>::new());
+            keys.iter().filter(|&&k| recent.check_and_insert(k)).count()
+        })
+    });
+    group.bench_function("HashSet with pruning", |b| {
+        b.iter(|| {
+            let mut seen = HashSet::with_capacity(WINDOW);
+            let mut order = VecDeque::with_capacity(WINDOW);
+            keys.iter()
+                .filter(|&&k| {
+                    if seen.contains(&k) {
+                        return true;
+                    }
+                    if order.len() == WINDOW / 2 {
+                        seen.remove(&order.pop_front().unwrap());
+                    }
+                    seen.insert(k);
+                    order.push_back(k);
+                    false
+                })
+                .count()
+        })
+    });
+}
+
 criterion_group!(
     benches,
     stateless_threaded,
     tl_threaded,
     atomic_threaded,
     tl_build_hasher_threaded,
-    stateless_build_hasher_threaded
+    stateless_build_hasher_threaded,
+    contended_tail_latency,
+    tl_exclusive,
+    bytes_throughput,
+    page_throughput,
+    recent_set
 );
 criterion_main!(benches);
@@ -0,0 +1,36 @@
+use std::hash::BuildHasher;
+
+use cmhash::CmHash;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[derive(Hash, CmHash)]
+struct Record {
+    id: u64,
+    shard: u32,
+    active: bool,
+    name: &'static str,
+}
+
+pub fn four_fields(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Hashing a 4-field struct");
+    let record = Record {
+        id: 0xDEADBEEF,
+        shard: 17,
+        active: true,
+        name: "user:42",
+    };
+    group.bench_function("derive(CmHash)", |b| {
+        b.iter(|| black_box(&record).cm_hash(0))
+    });
+    group.bench_function("derive(Hash) with CMBuildHasher", |b| {
+        let builder = cmhash::CMBuildHasher::new();
+        b.iter(|| builder.hash_one(black_box(&record)))
+    });
+    group.bench_function("derive(Hash) with Fmix64BuildHasher", |b| {
+        let builder = cmhash::Fmix64BuildHasher::default();
+        b.iter(|| builder.hash_one(black_box(&record)))
+    });
+}
+
+criterion_group!(benches, four_fields);
+criterion_main!(benches);
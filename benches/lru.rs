@@ -0,0 +1,40 @@
+use std::num::NonZeroUsize;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use lru::LruCache;
+
+pub fn get_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("LruCache get-heavy");
+    let cap = NonZeroUsize::new(4096).unwrap();
+    // Nine in ten lookups hit
+    let lookups: Vec<u64> = (0..100_000u64)
+        .map(|i| cmhash::hash_word_stateless(i as usize) as u64 % 4551)
+        .collect();
+    group.throughput(Throughput::Elements(lookups.len() as u64));
+
+    let mut cm: cmhash::CMLruCache<u64, u64> = cmhash::lru_cache_with_seed(cap, 7);
+    let mut default: LruCache<u64, u64> = LruCache::new(cap);
+    for i in 0..cap.get() as u64 {
+        cm.put(i, i);
+        default.put(i, i);
+    }
+    group.bench_function("CMBuildHasher", |b| {
+        b.iter(|| {
+            lookups
+                .iter()
+                .fold(0, |acc, k| acc + cm.get(k).copied().unwrap_or(0))
+        })
+    });
+    group.bench_function("default hasher", |b| {
+        b.iter(|| {
+            lookups
+                .iter()
+                .fold(0, |acc, k| acc + default.get(k).copied().unwrap_or(0))
+        })
+    });
+    black_box((&cm, &default));
+    group.finish();
+}
+
+criterion_group!(benches, get_heavy);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Instant;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+pub fn ordering_threaded(c: &mut Criterion) {
+    let orderings = [
+        ("SeqCst", Ordering::SeqCst),
+        ("AcqRel", Ordering::AcqRel),
+        ("Relaxed", Ordering::Relaxed),
+    ];
+    // On one thread the ordering can't change the outputs, only what they cost
+    let outputs: Vec<Vec<usize>> = orderings
+        .iter()
+        .map(|&(_, ordering)| {
+            let hasher = cmhash::CoreHasher::new();
+            (0..10_000)
+                .map(|i| hasher.hash_word_with_ordering(i, ordering))
+                .collect()
+        })
+        .collect();
+    let diverged = outputs.windows(2).any(|pair| pair[0] != pair[1]);
+    println!("CoreHasher outputs diverge between orderings on one thread: {diverged}");
+    assert!(!diverged, "the outputs must not depend on the ordering");
+
+    let mut group = c.benchmark_group("Threaded Hashing with Atomic by Ordering");
+    for (name, ordering) in orderings {
+        for threads in [1, 2, 4, 8] {
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                b.iter_custom(|iters| {
+                    let barrier = Arc::new(Barrier::new(threads + 1));
+                    let hasher = Arc::new(cmhash::CoreHasher::new());
+                    let threads: Vec<_> = (0..threads)
+                        .map(|_tid| {
+                            let barrier = Arc::clone(&barrier);
+                            let hasher = hasher.clone();
+                            thread::spawn(move || {
+                                barrier.wait();
+                                barrier.wait();
+                                for _ in 0..(iters / threads as u64) {
+                                    black_box(hasher.hash_word_with_ordering(0xDEADBEEF, ordering));
+                                }
+                            })
+                        })
+                        .collect();
+                    barrier.wait();
+                    let start = Instant::now();
+                    barrier.wait();
+                    for thread in threads {
+                        thread.join().unwrap();
+                    }
+                    start.elapsed()
+                })
+            });
+        }
+    }
+}
+
+criterion_group!(benches, ordering_threaded);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const KEYS: usize = 10_000_000;
+const BITS: u32 = 8;
+
+pub fn partition_keys(c: &mut Criterion) {
+    let keys: Vec<u64> = (0..KEYS as u64).map(|i| cmhash::hash_u64(i, 1).0).collect();
+    let mut group = c.benchmark_group("Partitioning 10M keys 256 ways");
+    group.throughput(Throughput::Elements(KEYS as u64));
+    group.sample_size(10);
+    group.bench_function("radix_partition", |b| {
+        b.iter(|| cmhash::radix_partition(black_box(&keys), BITS, 0))
+    });
+    group.bench_function("Vec of Vecs", |b| {
+        b.iter(|| {
+            let mut partitions: Vec<Vec<usize>> = vec![Vec::new(); 1 << BITS];
+            for (i, &key) in black_box(&keys).iter().enumerate() {
+                partitions[cmhash::hash_u64(key, 0).top_bits(BITS) as usize].push(i);
+            }
+            partitions
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, partition_keys);
+criterion_main!(benches);
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const KEYS: u64 = 4096;
+
+trait ConcurrentMap: Send + Sync + 'static {
+    fn insert(&self, key: u64, value: u64);
+    fn get(&self, key: u64) -> Option<u64>;
+}
+
+impl ConcurrentMap for Mutex<HashMap<u64, u64, cmhash::CMBuildHasher>> {
+    fn insert(&self, key: u64, value: u64) {
+        self.lock().unwrap().insert(key, value);
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.lock().unwrap().get(&key).copied()
+    }
+}
+
+impl ConcurrentMap for cmhash::ShardedHashMap<u64, u64, 64> {
+    fn insert(&self, key: u64, value: u64) {
+        cmhash::ShardedHashMap::insert(self, key, value);
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.get_cloned(&key)
+    }
+}
+
+/// Runs `iters` operations split across `threads`, one insert to every three lookups
+fn contend<M: ConcurrentMap>(map: Arc<M>, threads: usize, iters: u64) -> std::time::Duration {
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads as u64)
+        .map(|tid| {
+            let barrier = Arc::clone(&barrier);
+            let map = Arc::clone(&map);
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..iters / threads as u64 {
+                    let key = cmhash::hash_u64(i ^ (tid << 32), 0).0 % KEYS;
+                    if i % 4 == 0 {
+                        map.insert(key, i);
+                    } else {
+                        black_box(map.get(key));
+                    }
+                }
+            })
+        })
+        .collect();
+    let start = Instant::now();
+    barrier.wait();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+pub fn contended_maps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Contended Map Access");
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::new("Mutex<HashMap>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    let map = Mutex::new(HashMap::with_hasher(cmhash::CMBuildHasher::new()));
+                    contend(Arc::new(map), threads, iters)
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("ShardedHashMap<64>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    contend(Arc::new(cmhash::ShardedHashMap::new()), threads, iters)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, contended_maps);
+criterion_main!(benches);
@@ -0,0 +1,298 @@
+//! # cmhash-derive
+//!
+//! Provides `#[derive(CmHash)]`, re-exported by cmhash with the `derive` feature, and
+//! `#[memoize]`, re-exported with the `macros` feature
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Expr, Fields, FnArg, GenericParam,
+    Ident, Index, ItemFn, Member, Pat, PatIdent, Result, ReturnType, Type,
+};
+
+/// Derives `cmhash::CmHash`, feeding each field to the `cmhash::FieldHasher` in declaration
+/// order. Fields marked `#[cmhash(skip)]` are left out, and enums feed the index of the variant
+/// before its fields.
+#[proc_macro_derive(CmHash, attributes(cmhash))]
+pub fn derive_cm_hash(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream> {
+    let fields = Ident::new("__cmhash_fields", Span::mixed_site());
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, feeds) = destructure(&data.fields, &fields)?;
+            quote! {
+                let Self #pattern = self;
+                #(#feeds)*
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(i, variant)| {
+                    let name = &variant.ident;
+                    let discriminant = i as u64;
+                    let (pattern, feeds) = destructure(&variant.fields, &fields)?;
+                    Ok(quote! {
+                        Self::#name #pattern => {
+                            #fields.write_word(#discriminant);
+                            #(#feeds)*
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "CmHash can't be derived for unions",
+            ))
+        }
+    };
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds.push(parse_quote!(::cmhash::CmHash));
+        }
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::cmhash::CmHash for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn feed(&self, #fields: &mut ::cmhash::FieldHasher) {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns a pattern binding every field that isn't skipped, and the statements feeding them
+fn destructure(fields: &Fields, hasher: &Ident) -> Result<(TokenStream, Vec<TokenStream>)> {
+    let mut bindings = Vec::new();
+    let mut feeds = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        if is_skipped(field)? {
+            continue;
+        }
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        let binding = format_ident!("__cmhash_field_{}", i, span = Span::mixed_site());
+        let index = i as u32;
+        feeds.push(quote! {
+            #hasher.field(#index);
+            ::cmhash::CmHash::feed(#binding, #hasher);
+        });
+        bindings.push(quote!(#member: #binding));
+    }
+    Ok((quote!({ #(#bindings,)* .. }), feeds))
+}
+
+fn is_skipped(field: &syn::Field) -> Result<bool> {
+    let mut skip = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("cmhash")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown cmhash attribute, expected `skip`"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
+
+/// Memoizes a function in a bounded `cmhash::MemoCache`, keyed by the tuple of its arguments
+///
+/// `capacity = N` bounds the cache, `shared` makes one cache behind a mutex for all threads
+/// instead of one per thread, and `hasher = Type` hashes keys with a `Type::default()` builder
+/// instead of a `cmhash::CMBuildHasher`.
+#[proc_macro_attribute]
+pub fn memoize(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut options = MemoizeOptions::default();
+    let parser = syn::meta::parser(|meta| options.parse(meta));
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(item as ItemFn);
+    memoized(options, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct MemoizeOptions {
+    capacity: Option<Expr>,
+    shared: bool,
+    hasher: Option<Type>,
+}
+
+impl MemoizeOptions {
+    fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("capacity") {
+            self.capacity = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("shared") {
+            self.shared = true;
+        } else if meta.path.is_ident("hasher") {
+            self.hasher = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error(
+                "unknown memoize option, expected `capacity = N`, `shared` or `hasher = Type`",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn memoized(options: MemoizeOptions, item: ItemFn) -> Result<TokenStream> {
+    let capacity = options.capacity.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "memoize needs a bound on the cache, as `capacity = N`",
+        )
+    })?;
+    let sig = &item.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "memoize can't cache generic functions, as their cache would be shared by every \
+             instantiation",
+        ));
+    }
+    if let Some(token) = sig.asyncness {
+        return Err(Error::new_spanned(
+            token,
+            "memoize can't cache async functions",
+        ));
+    }
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    for input in &sig.inputs {
+        let FnArg::Typed(arg) = input else {
+            return Err(Error::new_spanned(
+                input,
+                "memoize can't cache methods; the cache would have to key on `self`",
+            ));
+        };
+        let Pat::Ident(PatIdent {
+            ident,
+            by_ref: None,
+            subpat: None,
+            ..
+        }) = &*arg.pat
+        else {
+            return Err(Error::new_spanned(
+                &arg.pat,
+                "memoize needs every argument bound to a plain name",
+            ));
+        };
+        if let Some(borrowed) = borrowed_part(&arg.ty) {
+            return Err(Error::new_spanned(
+                borrowed,
+                "memoize keys its cache on owned arguments; take this argument by value",
+            ));
+        }
+        names.push(ident.clone());
+        types.push((*arg.ty).clone());
+    }
+
+    let output = match &sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    let inner = Ident::new("__cmhash_memoized", Span::mixed_site());
+    let cache = Ident::new("__CMHASH_MEMO", Span::mixed_site());
+    let key = Ident::new("__cmhash_key", Span::mixed_site());
+    let value = Ident::new("__cmhash_value", Span::mixed_site());
+    let fields = (0..names.len()).map(Index::from);
+    let cache_ty = match &options.hasher {
+        Some(hasher) => quote!(::cmhash::MemoCache<(#(#types,)*), #output, #hasher>),
+        None => quote!(::cmhash::MemoCache<(#(#types,)*), #output>),
+    };
+    let new_cache = match &options.hasher {
+        Some(hasher) => {
+            quote!(::cmhash::MemoCache::with_hasher(#capacity, <#hasher as ::core::default::Default>::default()))
+        }
+        None => quote!(::cmhash::MemoCache::new(#capacity)),
+    };
+    let (lookup, store) = if options.shared {
+        (
+            quote!(#cache.lock().unwrap_or_else(::std::sync::PoisonError::into_inner).get(&#key).cloned()),
+            quote!(#cache.lock().unwrap_or_else(::std::sync::PoisonError::into_inner).insert(#key, ::core::clone::Clone::clone(&#value))),
+        )
+    } else {
+        (
+            quote!(#cache.with(|c| c.borrow().get(&#key).cloned())),
+            quote!(#cache.with(|c| c.borrow_mut().insert(#key, ::core::clone::Clone::clone(&#value)))),
+        )
+    };
+    let cache_item = if options.shared {
+        quote! {
+            static #cache: ::std::sync::LazyLock<::std::sync::Mutex<#cache_ty>> =
+                ::std::sync::LazyLock::new(|| ::std::sync::Mutex::new(#new_cache));
+        }
+    } else {
+        quote! {
+            ::std::thread_local! {
+                static #cache: ::core::cell::RefCell<#cache_ty> =
+                    ::core::cell::RefCell::new(#new_cache);
+            }
+        }
+    };
+
+    let mut inner_sig = sig.clone();
+    inner_sig.ident = inner.clone();
+    let mut outer_sig = sig.clone();
+    for (input, name) in outer_sig.inputs.iter_mut().zip(&names) {
+        if let FnArg::Typed(arg) = input {
+            *arg.pat = parse_quote!(#name);
+        }
+    }
+    let ItemFn {
+        attrs, vis, block, ..
+    } = &item;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #outer_sig {
+            #inner_sig #block
+            #cache_item
+            let #key = (#(#names,)*);
+            if let ::core::option::Option::Some(#value) = #lookup {
+                return #value;
+            }
+            // Nothing is locked or borrowed while the function runs, so it may recurse
+            let #value = #inner(#(::core::clone::Clone::clone(&#key.#fields)),*);
+            #store;
+            #value
+        }
+    })
+}
+
+/// Returns the reference or `impl Trait` in `ty`, if any, which a cache in a static can't hold
+fn borrowed_part(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(_) | Type::ImplTrait(_) => Some(ty),
+        Type::Paren(inner) => borrowed_part(&inner.elem),
+        Type::Group(inner) => borrowed_part(&inner.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().find_map(borrowed_part),
+        Type::Array(array) => borrowed_part(&array.elem),
+        Type::Slice(slice) => borrowed_part(&slice.elem),
+        _ => None,
+    }
+}
@@ -0,0 +1,63 @@
+//! A fuzz target built from this crate's `Arbitrary` impls, run over files of fuzzer input or,
+//! given none, over generated input.
+//!
+//! The body of `fuzz` drops straight into a `cargo fuzz` target:
+//!
+//! ```text
+//! cargo run --example fuzz_config --features arbitrary [FILE]...
+//! ```
+
+use core::hash::{BuildHasher, Hasher};
+
+use arbitrary::{Arbitrary, Unstructured};
+use cmhash::{CMHasher, CMHasherBuilder, DynWordHasher, HashConfig, MixerChoice};
+
+fn fuzz(data: &[u8]) -> arbitrary::Result<()> {
+    let mut u = Unstructured::new(data);
+
+    let config = HashConfig::arbitrary(&mut u)?;
+    let hasher = DynWordHasher::from_config(&config).expect("arbitrary configs always build");
+    let input: &[u8] = u.arbitrary()?;
+    let mut streamed = hasher.build_hasher();
+    streamed.write(input);
+    assert_eq!(streamed.finish(), hasher.hash(input));
+
+    let builder = CMHasherBuilder::arbitrary(&mut u)?;
+    assert_eq!(
+        builder.build_hasher().finish(),
+        builder.build_build_hasher().build_hasher().finish()
+    );
+
+    let mut original = CMHasher::<MixerChoice>::arbitrary(&mut u)?;
+    let mixer: MixerChoice = u.arbitrary()?;
+    let mut resumed = CMHasher::from_bytes_with_mixer(original.to_bytes(), mixer)
+        .expect("snapshots of arbitrary hashers always restore");
+    assert_eq!(resumed.to_bytes(), original.to_bytes());
+    let tail: &[u8] = u.arbitrary()?;
+    original.write(tail);
+    resumed.write(tail);
+    assert_eq!(original.to_bytes(), resumed.to_bytes());
+    Ok(())
+}
+
+fn main() -> std::io::Result<()> {
+    let paths: Vec<_> = std::env::args_os().skip(1).collect();
+    if paths.is_empty() {
+        let mut x = 0x5EED_u64;
+        for len in 0..1024 {
+            let data: Vec<u8> = (0..len)
+                .map(|_| {
+                    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    (cmhash::hash_u64(x, 0).0 >> 56) as u8
+                })
+                .collect();
+            let _ = fuzz(&data);
+        }
+        println!("1024 generated inputs passed");
+    } else {
+        for path in paths {
+            let _ = fuzz(&std::fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use core::fmt;
+
+use rkyv::rancor::Source;
+
+/// An archive that passed rkyv's own checks but breaks an invariant of the type it archives
+#[derive(Debug)]
+pub(crate) struct InvalidArchive(&'static str);
+
+impl fmt::Display for InvalidArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid archive: {}", self.0)
+    }
+}
+
+impl core::error::Error for InvalidArchive {}
+
+/// Fails validation with `what` unless `holds`
+pub(crate) fn check<E: Source>(holds: bool, what: &'static str) -> Result<(), E> {
+    if holds {
+        Ok(())
+    } else {
+        Err(E::new(InvalidArchive(what)))
+    }
+}
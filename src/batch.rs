@@ -0,0 +1,104 @@
+use crate::output::{hash_u64, DEFAULT_SEED};
+
+/// Hashes a batch of `B` keys, each exactly as `hash_u64(key, DEFAULT_SEED)` does
+///
+/// The keys are independent, so the rounds of different keys overlap in the pipeline instead of
+/// waiting on one another.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_batch, hash_u64, DEFAULT_SEED};
+///
+/// let hashes = hash_batch(&[1, 2, 3, 4]);
+/// assert_eq!(hashes[2], hash_u64(3, DEFAULT_SEED).0);
+/// ```
+#[inline]
+pub fn hash_batch<const B: usize>(keys: &[u64; B]) -> [u64; B] {
+    keys.map(|key| hash_u64(key, DEFAULT_SEED).0)
+}
+
+/// Hashes a batch of keys as [`hash_batch`] does, and prefetches the bucket of each into cache
+/// before returning, so that probing the buckets afterwards finds them loaded or on their way
+///
+/// The bucket of a hash is `hash as usize & mask`, and its address is `table_base` plus that
+/// index times `bucket_stride`. The mask is applied before scaling, so with `mask + 1` buckets
+/// of `bucket_stride` bytes from `table_base` every prefetched address is inside the table.
+/// Addresses are computed with wrapping arithmetic and only prefetched, never read, and a
+/// prefetch can't fault, so this is safe whatever the arguments; wrong ones only waste the
+/// prefetches.
+///
+/// Prefetching uses `prefetcht0` on `x86` and `x86_64` and `prfm pldl1keep` on `aarch64`. On
+/// other targets only the hashing is done.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_batch, hash_batch_and_prefetch};
+///
+/// let buckets = vec![0u64; 1024];
+/// let keys = [11, 22, 33, 44, 55, 66, 77, 88];
+/// let hashes = hash_batch_and_prefetch(&keys, buckets.as_ptr().cast(), 8, buckets.len() - 1);
+/// assert_eq!(hashes, hash_batch(&keys));
+/// ```
+#[cfg(any(feature = "std", feature = "prefetch"))]
+#[inline]
+pub fn hash_batch_and_prefetch<const B: usize>(
+    keys: &[u64; B],
+    table_base: *const u8,
+    bucket_stride: usize,
+    mask: usize,
+) -> [u64; B] {
+    let hashes = hash_batch(keys);
+    for &hash in &hashes {
+        prefetch(bucket_address(table_base, bucket_stride, mask, hash));
+    }
+    hashes
+}
+
+/// The address of the bucket `hash` selects in a table of `mask + 1` buckets of `bucket_stride`
+/// bytes from `table_base`
+#[cfg(any(feature = "std", feature = "prefetch"))]
+#[inline]
+pub(crate) fn bucket_address(
+    table_base: *const u8,
+    bucket_stride: usize,
+    mask: usize,
+    hash: u64,
+) -> *const u8 {
+    table_base.wrapping_add((hash as usize & mask).wrapping_mul(bucket_stride))
+}
+
+/// Hints that the cache line holding `addr` will be read soon
+#[cfg(any(feature = "std", feature = "prefetch"))]
+#[inline(always)]
+fn prefetch(addr: *const u8) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: SSE is part of the x86_64 baseline, and a prefetch doesn't access memory, so any
+    // address is allowed
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(addr.cast());
+    }
+    #[cfg(all(target_arch = "x86", target_feature = "sse"))]
+    // SAFETY: as above, with SSE enabled for the target
+    unsafe {
+        use core::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(addr.cast());
+    }
+    #[cfg(target_arch = "aarch64")]
+    // SAFETY: `prfm` is a hint that never faults, whatever the address
+    unsafe {
+        core::arch::asm!(
+            "prfm pldl1keep, [{addr}]",
+            addr = in(reg) addr,
+            options(nostack, preserves_flags, readonly)
+        );
+    }
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        all(target_arch = "x86", target_feature = "sse"),
+        target_arch = "aarch64"
+    )))]
+    let _ = addr;
+}
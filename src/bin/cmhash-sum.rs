@@ -0,0 +1,229 @@
+//! Prints or checks portable cmhash digests of files, in the manner of `sha256sum`.
+//!
+//! ```text
+//! cmhash-sum [--seed N] [--algorithm v1|stable] [FILE]...
+//! cmhash-sum [--seed N] [--algorithm v1|stable] --check SUMS
+//! ```
+//!
+//! Each digest is [`cmhash::hash_reader`] of the file's contents, printed as 16 hex
+//! digits, two spaces and the file name. With no files, or for a file named `-`, standard input
+//! is read. `--check` reads lines in that format back and reports whether each file still
+//! matches. When the lines themselves come from standard input, an entry for `-` fails, since
+//! standard input can't be hashed as well. Seeds are parsed as a [`cmhash::Seed`], so they're decimal, `0x`-prefixed hex, or
+//! exactly 16 hex digits.
+//!
+//! Exits 0 on success, 1 if a file could not be read or did not match, and 2 on bad arguments.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::ExitCode;
+
+use cmhash::{hash_reader, Seed};
+
+const USAGE: &str = "\
+Usage: cmhash-sum [OPTION]... [FILE]...
+Print or check portable cmhash digests. With no FILE, or when FILE is -, read standard input.
+
+  -s, --seed N          hash under seed N, in decimal, 0x-prefixed hex, or exactly 16 hex
+                        digits (default 0)
+  -a, --algorithm ALG   v1 or stable, which is v1 (default stable)
+  -c, --check FILE      read digests from FILE and check them
+  -h, --help            print this help and exit
+";
+
+/// Parsed command line
+struct Args {
+    seed: u64,
+    check: Option<String>,
+    files: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(msg) => {
+            eprintln!("cmhash-sum: {msg}");
+            eprintln!("Try 'cmhash-sum --help' for more information.");
+            return ExitCode::from(2);
+        }
+    };
+    let ok = match &args.check {
+        Some(sums) => check(sums, args.seed),
+        None => print_sums(&args.files, args.seed),
+    };
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Parses the arguments after the program name, returning `None` if help was asked for
+fn parse_args(mut argv: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut args = Args {
+        seed: 0,
+        check: None,
+        files: Vec::new(),
+    };
+    let mut options_done = false;
+    while let Some(arg) = argv.next() {
+        if options_done || arg == "-" || !arg.starts_with('-') {
+            args.files.push(arg);
+            continue;
+        }
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_owned(), Some(value.to_owned()))
+            }
+            _ => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| argv.next())
+                .ok_or_else(|| format!("option '{name}' requires an argument"))
+        };
+        match flag.as_str() {
+            "--" => options_done = true,
+            "-h" | "--help" => return Ok(None),
+            "-s" | "--seed" => {
+                let seed = value("--seed")?;
+                args.seed = seed
+                    .parse::<Seed>()
+                    .map_err(|e| format!("invalid seed '{seed}': {e}"))?
+                    .0;
+            }
+            "-a" | "--algorithm" => match value("--algorithm")?.as_str() {
+                "v1" | "stable" => {}
+                "v2" => return Err("algorithm 'v2' is not available in this version".into()),
+                other => return Err(format!("unknown algorithm '{other}'")),
+            },
+            "-c" | "--check" => args.check = Some(value("--check")?),
+            _ => return Err(format!("unrecognized option '{flag}'")),
+        }
+    }
+    if args.check.is_some() && !args.files.is_empty() {
+        return Err("--check takes no further files".into());
+    }
+    if args.files.is_empty() {
+        args.files.push("-".into());
+    }
+    Ok(Some(args))
+}
+
+/// Hashes the file named `name`, or standard input for `-`
+fn digest(name: &str, seed: u64) -> io::Result<u64> {
+    if name == "-" {
+        hash_reader(io::stdin().lock(), seed)
+    } else {
+        hash_reader(File::open(name)?, seed)
+    }
+}
+
+/// Prints the digest line of each file, returning whether every file could be read
+fn print_sums(files: &[String], seed: u64) -> bool {
+    let mut stdout = io::stdout().lock();
+    let mut ok = true;
+    for name in files {
+        match digest(name, seed) {
+            Ok(hash) => {
+                if writeln!(stdout, "{hash:016x}  {name}").is_err() {
+                    return false;
+                }
+            }
+            Err(e) => {
+                eprintln!("cmhash-sum: {name}: {e}");
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Checks each `<digest>  <name>` line of `sums`, returning whether every file matched
+fn check(sums: &str, seed: u64) -> bool {
+    let reader: Box<dyn BufRead> = if sums == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match File::open(sums) {
+            Ok(f) => Box::new(BufReader::new(f)),
+            Err(e) => {
+                eprintln!("cmhash-sum: {sums}: {e}");
+                return false;
+            }
+        }
+    };
+    let (mut checked, mut failed, mut unreadable, mut malformed) = (0, 0, 0, 0);
+    for (i, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("cmhash-sum: {sums}: {e}");
+                return false;
+            }
+        };
+        let Some((expected, name)) = parse_line(&line) else {
+            eprintln!(
+                "cmhash-sum: {sums}: {}: improperly formatted cmhash checksum line",
+                i + 1
+            );
+            malformed += 1;
+            continue;
+        };
+        checked += 1;
+        // Standard input is already being read for the list, and its lock isn't re-entrant
+        if sums == "-" && name == "-" {
+            eprintln!(
+                "cmhash-sum: -: standard input can't be checked while the sums are read from it"
+            );
+            println!("{name}: FAILED open or read");
+            unreadable += 1;
+            continue;
+        }
+        match digest(name, seed) {
+            Ok(hash) if hash == expected => println!("{name}: OK"),
+            Ok(_) => {
+                println!("{name}: FAILED");
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("cmhash-sum: {name}: {e}");
+                println!("{name}: FAILED open or read");
+                unreadable += 1;
+            }
+        }
+    }
+    if checked == 0 {
+        eprintln!("cmhash-sum: {sums}: no properly formatted cmhash checksum lines found");
+        return false;
+    }
+    if malformed > 0 {
+        eprintln!("cmhash-sum: WARNING: {malformed} line(s) are improperly formatted");
+    }
+    if unreadable > 0 {
+        eprintln!("cmhash-sum: WARNING: {unreadable} listed file(s) could not be read");
+    }
+    if failed > 0 {
+        eprintln!("cmhash-sum: WARNING: {failed} computed checksum(s) did NOT match");
+    }
+    failed == 0 && unreadable == 0
+}
+
+/// Splits a `<16 hex digits>  <name>` line, also accepting `sha256sum`'s binary marker ` *`
+fn parse_line(line: &str) -> Option<(u64, &str)> {
+    let (hex, rest) = line.split_at_checked(16)?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let name = rest
+        .strip_prefix("  ")
+        .or_else(|| rest.strip_prefix(" *"))?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((u64::from_str_radix(hex, 16).ok()?, name))
+}
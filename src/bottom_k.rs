@@ -0,0 +1,209 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::output::hash_bytes;
+use crate::seed::Seed;
+#[cfg(feature = "alloc")]
+use crate::sketch::{check_payload_len, SketchError, SketchHeader, SketchKind};
+
+/// A sketch keeping the `K` smallest seeded hashes of the distinct keys offered to it
+///
+/// Since hashes are uniformly distributed, the bottom `K` are a uniform sample of the distinct
+/// keys, chosen the same way on every host. That supports estimating how many distinct keys were
+/// seen and how similar two streams are, in `K` words of memory. Sketches built with the same
+/// seed can be merged.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::BottomK;
+///
+/// let mut a = BottomK::<64>::new(1);
+/// let mut b = BottomK::<64>::new(1);
+/// for i in 0..1000u32 {
+///     a.offer(&i.to_le_bytes());
+///     b.offer(&(i + 500).to_le_bytes());
+/// }
+/// let j = a.jaccard(&b);
+/// assert!(j > 0.15 && j < 0.55);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BottomK<const K: usize> {
+    seed: u64,
+    hashes: [u64; K],
+    len: usize,
+}
+
+impl<const K: usize> BottomK<K> {
+    /// Creates an empty sketch hashing keys with `seed`
+    ///
+    /// Fails to compile if `K` is zero.
+    pub fn new(seed: impl Into<Seed>) -> Self {
+        const { assert!(K > 0, "a bottom-k sketch must keep at least one hash") };
+        Self {
+            seed: seed.into().0,
+            hashes: [0; K],
+            len: 0,
+        }
+    }
+
+    /// Returns the seed keys are hashed with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the retained hashes in ascending order
+    pub fn hashes(&self) -> &[u64] {
+        &self.hashes[..self.len]
+    }
+
+    /// Returns the number of retained hashes, which is `K` once `K` distinct keys have been seen
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no keys have been offered
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Offers `key` to the sketch, returning `true` if its hash was retained
+    pub fn offer(&mut self, key: &[u8]) -> bool {
+        self.insert(hash_bytes(key, self.seed).0)
+    }
+
+    /// Returns `true` if `hash` is among the retained hashes
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        self.hashes().binary_search(&hash).is_ok()
+    }
+
+    /// Merges `other` into this sketch, leaving it as if it had been offered the keys of both
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sketches were created with different seeds.
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            self.seed, other.seed,
+            "cannot merge sketches with different seeds"
+        );
+        self.merge_unchecked(other);
+    }
+
+    /// Merges `other` into this sketch as [`Self::merge`] does, or returns
+    /// [`SketchError::HeaderMismatch`] and leaves this sketch unchanged if the sketches were
+    /// created with different seeds, as sketches read back from different sources may have been
+    #[cfg(feature = "alloc")]
+    pub fn try_merge(&mut self, other: &Self) -> Result<(), SketchError> {
+        if self.seed != other.seed {
+            return Err(SketchError::HeaderMismatch);
+        }
+        self.merge_unchecked(other);
+        Ok(())
+    }
+
+    fn merge_unchecked(&mut self, other: &Self) {
+        for &hash in other.hashes() {
+            // Both are sorted, so once one is too large the rest will be too
+            if self.len == K && hash > self.hashes[K - 1] {
+                break;
+            }
+            self.insert(hash);
+        }
+    }
+
+    /// Writes the sketch as bytes for persisting or exchange
+    ///
+    /// The bytes are a [`SketchHeader`] of kind [`SketchKind::BottomK`], holding the seed and, as
+    /// dimensions, `K` and the number of retained hashes, followed by the retained hashes in
+    /// ascending order as little-endian `u64`s.
+    #[cfg(feature = "alloc")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(crate::sketch::SKETCH_HEADER_LEN + 8 * self.len);
+        SketchHeader::new(SketchKind::BottomK, self.seed, [K as u64, self.len as u64])
+            .write(&mut bytes);
+        for hash in self.hashes() {
+            bytes.extend_from_slice(&hash.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Restores a sketch written by [`Self::to_bytes`] with the same `K`
+    ///
+    /// The header and dimensions are checked before the payload length is trusted, and the
+    /// hashes must be strictly ascending, so a corrupted sketch is rejected rather than merged
+    /// into a wrong result later.
+    #[cfg(feature = "alloc")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SketchError> {
+        let (header, payload) = SketchHeader::parse_kind(bytes, SketchKind::BottomK)?;
+        let [k, len] = header.dims();
+        if k != K as u64 || len > k {
+            return Err(SketchError::DimensionMismatch);
+        }
+        let len = len as usize;
+        check_payload_len(payload, 8 * len)?;
+        let mut sketch = Self::new(header.seed());
+        for (slot, word) in sketch.hashes.iter_mut().zip(payload.chunks_exact(8)) {
+            *slot = crate::snapshot::read_u64(word, 0);
+        }
+        sketch.len = len;
+        if sketch.hashes().windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(SketchError::Malformed);
+        }
+        Ok(sketch)
+    }
+
+    /// Estimates the number of distinct keys offered
+    ///
+    /// This is exact until `K` distinct keys have been seen, after which it is estimated from the
+    /// `K`-th smallest hash with a relative standard error of about `1 / sqrt(K - 2)`.
+    pub fn estimate_distinct(&self) -> f64 {
+        if self.len < K {
+            return self.len as f64;
+        }
+        // The K-th smallest of n uniform values is expected near K / n of the way up the range
+        let kth = (self.hashes[K - 1] as f64 + 1.0) / 18_446_744_073_709_551_616.0;
+        (K - 1) as f64 / kth
+    }
+
+    /// Estimates the Jaccard similarity of the key sets offered to `self` and `other`: the
+    /// fraction of the bottom `K` hashes of their union that both retained.
+    ///
+    /// Two empty sketches are considered identical.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sketches were created with different seeds.
+    pub fn jaccard(&self, other: &Self) -> f64 {
+        let mut union = self.clone();
+        union.merge(other);
+        if union.is_empty() {
+            return 1.0;
+        }
+        let shared = union
+            .hashes()
+            .iter()
+            .filter(|&&h| self.contains_hash(h) && other.contains_hash(h))
+            .count();
+        shared as f64 / union.len() as f64
+    }
+
+    /// Inserts `hash` if it is among the `K` smallest seen, returning whether it was
+    fn insert(&mut self, hash: u64) -> bool {
+        let Err(pos) = self.hashes().binary_search(&hash) else {
+            return false;
+        };
+        if pos == K {
+            return false;
+        }
+        let end = if self.len < K {
+            self.len += 1;
+            self.len
+        } else {
+            K
+        };
+        self.hashes.copy_within(pos..end - 1, pos + 1);
+        self.hashes[pos] = hash;
+        true
+    }
+}
@@ -0,0 +1,57 @@
+use core::hash::Hasher;
+
+use bytes::Buf;
+
+use crate::hasher::{CMHasher, Fmix64Hasher};
+use crate::mixer::{Fmix64, Mixer};
+
+impl<M: Mixer> CMHasher<M> {
+    /// Writes the remaining bytes of `buf` exactly as [`Hasher::write`] would write them as one
+    /// contiguous slice, walking its chunks without copying them together.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::hash::Hasher;
+    /// use bytes::Buf;
+    /// use cmhash::CMHasher;
+    ///
+    /// let mut chained = CMHasher::new();
+    /// chained.write_buf((&b"Hello, "[..]).chain(&b"World!"[..]));
+    /// let mut contiguous = CMHasher::new();
+    /// contiguous.write(b"Hello, World!");
+    /// assert_eq!(chained.finish(), contiguous.finish());
+    /// ```
+    pub fn write_buf(&mut self, mut buf: impl Buf) {
+        let mut stream = self.stream();
+        while buf.has_remaining() {
+            let chunk = buf.chunk();
+            let len = chunk.len();
+            stream.write(chunk);
+            buf.advance(len);
+        }
+        stream.finish();
+    }
+}
+
+/// Hashes the remaining bytes of `buf` with a [`CMHasher`] seeded with `seed` and finalizes
+/// the result, agreeing with [`hash_bytes`](crate::hash_bytes) of the same bytes.
+///
+/// # Examples
+///
+/// ```
+/// use bytes::{Buf, Bytes};
+/// use cmhash::{hash_buf, hash_bytes};
+///
+/// let header = Bytes::from_static(b"GET / HTTP/1.1\r\n");
+/// let body = Bytes::from_static(b"\r\n");
+/// assert_eq!(
+///     hash_buf(header.chain(body), 3),
+///     hash_bytes(b"GET / HTTP/1.1\r\n\r\n", 3).0
+/// );
+/// ```
+pub fn hash_buf<B: Buf>(buf: B, seed: u64) -> u64 {
+    let mut hasher = Fmix64Hasher::with_mixer(seed, Fmix64);
+    hasher.write_buf(buf);
+    hasher.finish()
+}
@@ -0,0 +1,255 @@
+use core::fmt;
+
+use crate::hasher::{CMBuildHasher, CMHasher, StatelessHasher, DEFAULT_HASHER_STATE};
+use crate::mixer::MixerChoice;
+use crate::seed::Seed;
+
+/// The multiplier used by each round of a configured [`CMHasher`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Prime {
+    /// `2^62 - 1`, the multiplier [`CMHasher`] has always used. Despite the crate's name this is
+    /// not prime; it is kept as the default so that existing hashes don't change.
+    #[default]
+    Classic,
+    /// The Mersenne prime `2^61 - 1`
+    Mersenne61,
+    /// The Mersenne prime `2^31 - 1`
+    Mersenne31,
+}
+
+impl Prime {
+    /// Returns the multiplier as a word
+    pub const fn value(self) -> u64 {
+        match self {
+            Self::Classic => crate::hasher::DEFAULT_PRIME,
+            Self::Mersenne61 => (1 << 61) - 1,
+            Self::Mersenne31 => (1 << 31) - 1,
+        }
+    }
+}
+
+/// The round function of a configured [`CMHasher`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Strategy {
+    /// Xor each word into the state and take the full product with the [`Prime`], the high half
+    /// becoming the next state. This is what [`CMHasher`] has always done.
+    #[default]
+    Multiply,
+    /// Xor each word into the state and mix it with a second lane derived from the state by
+    /// three rounds of additions, rotations and xors, for targets such as AVR and Cortex-M0
+    /// where a wide multiply is a library call.
+    ///
+    /// The [`Prime`] is ignored. The output differs from [`Strategy::Multiply`] but is as
+    /// stable: it is fixed as of this release and will not change in later ones.
+    ///
+    /// Three rounds don't reach full avalanche. Flipping one bit of a `u64` written to an
+    /// unfinalized hasher flips on average 29 of the 64 output bits rather than the ideal 32,
+    /// and no input bit is more than 5 off that ideal. Four rounds would close the gap at a third more
+    /// cost. For comparison, a single round of [`Strategy::Multiply`] flips only about one bit
+    /// there, as the low half of its product is nearly linear, so finalize the output with a
+    /// [`MixerChoice`] if that matters either way.
+    ///
+    /// To confirm that a build contains no multiply, disassemble it, e.g. with
+    /// `cargo objdump --release --target thumbv6m-none-eabi -- -d`, and check that the path from
+    /// `CMHasher::write` through the shift-add round calls neither `__aeabi_lmul` nor
+    /// `__muldi3`.
+    ShiftAdd,
+}
+
+impl Strategy {
+    /// Returns whether the round function multiplies
+    pub const fn multiplies(self) -> bool {
+        match self {
+            Self::Multiply => true,
+            Self::ShiftAdd => false,
+        }
+    }
+}
+
+/// A version of the hashing algorithm
+///
+/// Each version's output is fixed forever, so that hashes persisted by one release of this crate
+/// can be reproduced by every later one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// The original algorithm: each word is xored into the state and multiplied by the prime, the
+    /// high half of the product carried into the next word
+    #[default]
+    V1,
+}
+
+/// A combination of options that [`CMHasherBuilder`] can't build
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A [`StatelessHasher`] has no state, so it can't be seeded
+    StatelessSeed,
+    /// A [`StatelessHasher`] doesn't finalize its output
+    StatelessMixer,
+    /// A [`StatelessHasher`] only supports the default prime, round function and native byte
+    /// order
+    StatelessLayout,
+    /// An output width other than 32 or 64 bits was requested
+    UnsupportedWidth(u32),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StatelessSeed => f.write_str("a stateless hasher can't be seeded"),
+            Self::StatelessMixer => f.write_str("a stateless hasher can't use a mixer"),
+            Self::StatelessLayout => f.write_str(
+                "a stateless hasher only supports the default prime, round function and byte order",
+            ),
+            Self::UnsupportedWidth(bits) => {
+                write!(
+                    f,
+                    "unsupported output width of {bits} bits, expected 32 or 64"
+                )
+            }
+        }
+    }
+}
+
+/// A [`CMBuildHasher`] configured by [`CMHasherBuilder`]
+pub type ConfiguredBuildHasher = CMBuildHasher<MixerChoice>;
+
+/// A single entry point for configuring the hashers in this crate
+///
+/// The default configuration builds hashers identical to [`CMHasher::new`].
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use cmhash::{CMHasherBuilder, MixerChoice};
+///
+/// let builder = CMHasherBuilder::new().seed(7).mixer(MixerChoice::Fmix64).portable(true);
+/// let mut map = HashMap::with_hasher(builder.build_build_hasher());
+/// map.insert("key", 1);
+/// assert_eq!(map["key"], 1);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CMHasherBuilder {
+    seed: Option<u64>,
+    prime: Prime,
+    portable: bool,
+    mixer: MixerChoice,
+    version: Algorithm,
+    strategy: Strategy,
+}
+
+impl CMHasherBuilder {
+    /// Returns a builder with the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the initial state of the hasher
+    pub fn seed(mut self, seed: impl Into<Seed>) -> Self {
+        self.seed = Some(seed.into().0);
+        self
+    }
+
+    /// Sets the multiplier used by each round
+    pub fn prime(mut self, prime: Prime) -> Self {
+        self.prime = prime;
+        self
+    }
+
+    /// Reads input words little-endian if `portable`, so hashes match across platforms, and in
+    /// native byte order otherwise
+    pub fn portable(mut self, portable: bool) -> Self {
+        self.portable = portable;
+        self
+    }
+
+    /// Sets the finalizer applied by [`Hasher::finish`](core::hash::Hasher::finish)
+    pub fn mixer(mut self, mixer: MixerChoice) -> Self {
+        self.mixer = mixer;
+        self
+    }
+
+    /// Sets the round function
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the version of the algorithm
+    pub fn version(mut self, version: Algorithm) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Returns the configured seed, or the default state if none was set
+    pub fn get_seed(&self) -> u64 {
+        self.seed.unwrap_or(DEFAULT_HASHER_STATE)
+    }
+
+    /// Returns the configured multiplier
+    pub fn get_prime(&self) -> Prime {
+        self.prime
+    }
+
+    /// Returns whether input words are read little-endian regardless of platform
+    pub fn is_portable(&self) -> bool {
+        self.portable
+    }
+
+    /// Returns the configured finalizer
+    pub fn get_mixer(&self) -> MixerChoice {
+        self.mixer
+    }
+
+    /// Returns the configured round function
+    pub fn get_strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Returns the configured version of the algorithm
+    pub fn get_version(&self) -> Algorithm {
+        self.version
+    }
+
+    /// Builds a [`CMHasher`] with this configuration
+    pub fn build_hasher(&self) -> CMHasher<MixerChoice> {
+        CMHasher::configured(
+            self.get_seed(),
+            self.mixer,
+            self.prime.value(),
+            self.portable,
+        )
+        .with_strategy(self.strategy)
+    }
+
+    /// Builds a [`BuildHasher`](core::hash::BuildHasher) yielding hashers with this configuration
+    pub fn build_build_hasher(&self) -> ConfiguredBuildHasher {
+        CMBuildHasher::configured(
+            self.get_seed(),
+            self.mixer,
+            self.prime.value(),
+            self.portable,
+        )
+        .with_strategy(self.strategy)
+    }
+
+    /// Builds a [`StatelessHasher`], which supports none of the options besides the version
+    pub fn build_stateless(&self) -> Result<StatelessHasher, ConfigError> {
+        if self.seed.is_some() {
+            return Err(ConfigError::StatelessSeed);
+        }
+        if self.mixer != MixerChoice::None {
+            return Err(ConfigError::StatelessMixer);
+        }
+        if self.prime != Prime::Classic || self.strategy != Strategy::Multiply || self.portable {
+            return Err(ConfigError::StatelessLayout);
+        }
+        Ok(StatelessHasher::new())
+    }
+}
@@ -0,0 +1,44 @@
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+use lru::LruCache;
+
+use crate::hasher::CMBuildHasher;
+use crate::seed::Seed;
+
+/// An [`LruCache`] whose keys are hashed with [`CMBuildHasher`]
+///
+/// [`CMHasher`](crate::CMHasher) is at its best on word-sized keys such as integers and ids. It
+/// keeps little of a key whose [`Hash`] impl makes several writes, as `str` and `String` do, so
+/// key those caches by a precomputed hash or use an [`LruCache`] with a
+/// [`KeyedBuildHasher`](crate::KeyedBuildHasher) instead.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use cmhash::{lru_cache, CMLruCache};
+///
+/// let mut cache: CMLruCache<u64, &str> = lru_cache(NonZeroUsize::new(2).unwrap());
+/// cache.put(1, "one");
+/// cache.put(2, "two");
+/// cache.put(3, "three");
+/// assert_eq!(cache.get(&1), None);
+/// assert_eq!(cache.get(&3), Some(&"three"));
+/// ```
+pub type CMLruCache<K, V> = LruCache<K, V, CMBuildHasher>;
+
+/// Creates a [`CMLruCache`] holding at most `cap` entries, hashing with a
+/// [`CMBuildHasher::unique`] state as [`LruCache::with_hasher`] would with
+/// [`CMBuildHasher::default`]
+pub fn lru_cache<K: Hash + Eq, V>(cap: NonZeroUsize) -> CMLruCache<K, V> {
+    LruCache::with_hasher(cap, CMBuildHasher::default())
+}
+
+/// Creates a [`CMLruCache`] holding at most `cap` entries, hashing with the state `seed`
+pub fn lru_cache_with_seed<K: Hash + Eq, V>(
+    cap: NonZeroUsize,
+    seed: impl Into<Seed>,
+) -> CMLruCache<K, V> {
+    LruCache::with_hasher(cap, CMBuildHasher::with_state(seed))
+}
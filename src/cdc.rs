@@ -0,0 +1,123 @@
+use core::ops::Range;
+
+use crate::seed::Seed;
+use crate::sequence::HashSequence;
+
+/// Content-defined chunking with a gear rolling hash, for splitting data into chunks whose
+/// boundaries follow the content, so that an insertion or deletion only changes the chunks
+/// around it instead of shifting every chunk after it.
+///
+/// Each byte shifts the rolling hash left by one and adds the byte's entry from a table of 256
+/// words, which are the first items of the [`HashSequence`] for the seed. Once a chunk is `min`
+/// bytes long, it ends at each further byte with probability `1 / (avg - min)`, wherever the
+/// hash falls below `u64::MAX / (avg - min)`, so chunks average about `avg` bytes; it is cut at
+/// `max` bytes regardless.
+///
+/// Boundaries only depend on the bytes since the previous boundary, so feeding the same data
+/// through [`Self::next_boundary`] in differently sized pieces finds the same boundaries.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::GearChunker;
+///
+/// let data: Vec<u8> = (0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+/// let chunker = GearChunker::new(1024, 4096, 16384, 0);
+/// let chunks: Vec<_> = chunker.chunks(&data).collect();
+/// assert_eq!(chunks.first().unwrap().start, 0);
+/// assert_eq!(chunks.last().unwrap().end, data.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct GearChunker {
+    gear: [u64; 256],
+    min: usize,
+    max: usize,
+    threshold: u64,
+    hash: u64,
+    len: usize,
+}
+
+impl GearChunker {
+    /// Creates a [`GearChunker`] cutting chunks of `min` to `max` bytes, averaging about `avg`,
+    /// with its gear table derived from `seed`
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 < min < avg <= max`.
+    pub fn new(min: usize, avg: usize, max: usize, seed: impl Into<Seed>) -> Self {
+        assert!(
+            0 < min && min < avg && avg <= max,
+            "chunk sizes must satisfy 0 < min < avg <= max"
+        );
+        let mut table = HashSequence::new(seed);
+        Self {
+            gear: core::array::from_fn(|_| table.next().expect("the sequence never ends")),
+            min,
+            max,
+            threshold: u64::MAX / (avg - min) as u64,
+            hash: 0,
+            len: 0,
+        }
+    }
+
+    /// Feeds `bytes` into the current chunk, returning the offset into `bytes` just past the
+    /// first boundary found in it.
+    ///
+    /// The bytes after a returned boundary have not been consumed: pass them in again to
+    /// continue with the next chunk. If no boundary is found, all of `bytes` belong to the
+    /// current chunk, which continues into the next call.
+    pub fn next_boundary(&mut self, bytes: &[u8]) -> Option<usize> {
+        for (i, &byte) in bytes.iter().enumerate() {
+            self.hash = (self.hash << 1).wrapping_add(self.gear[byte as usize]);
+            self.len += 1;
+            if self.len >= self.max || (self.len >= self.min && self.hash < self.threshold) {
+                self.reset();
+                return Some(i + 1);
+            }
+        }
+        None
+    }
+
+    /// Discards the current chunk, so the next byte fed starts a new one
+    pub fn reset(&mut self) {
+        self.hash = 0;
+        self.len = 0;
+    }
+
+    /// Returns the ranges of the chunks of `data`, starting from a fresh chunk and ending with
+    /// whatever follows the last boundary
+    pub fn chunks<'a>(&self, data: &'a [u8]) -> GearChunks<'a> {
+        let mut chunker = self.clone();
+        chunker.reset();
+        GearChunks {
+            chunker,
+            data,
+            pos: 0,
+        }
+    }
+}
+
+/// An iterator over the chunk ranges of a slice, returned by [`GearChunker::chunks`]
+#[derive(Debug, Clone)]
+pub struct GearChunks<'a> {
+    chunker: GearChunker,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Iterator for GearChunks<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let start = self.pos;
+        if start == self.data.len() {
+            return None;
+        }
+        let end = match self.chunker.next_boundary(&self.data[start..]) {
+            Some(len) => start + len,
+            None => self.data.len(),
+        };
+        self.pos = end;
+        Some(start..end)
+    }
+}
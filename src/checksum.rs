@@ -0,0 +1,181 @@
+use core::hash::Hasher;
+
+use crate::hasher::{CMHasher, StatelessHasher};
+use crate::keyed::KeyedHasher;
+use crate::mixer::Mixer;
+use crate::small::{CMHasher16, CMHasher32};
+use crate::sponge::Sponge;
+
+/// Something bytes can be fed to for a checksum, in the style of the `digest` crate's
+/// `Update` and `FixedOutput` traits rather than [`Hasher`].
+///
+/// Unlike [`Hasher::finish`], [`Self::finalize`] consumes the checksum, so a finished one can't
+/// be fed by mistake, and [`Self::reset`] makes it reusable without constructing it again. The
+/// output type is whatever width the checksum naturally has: 16 bits for [`CMHasher16`], 32 for
+/// [`CMHasher32`], 64 for the [`Hasher`]s and 128 for a [`Sponge`].
+///
+/// Each [`Self::update`] appends to the input, so the checksum is the same however the input is
+/// split. For the [`Hasher`]s in this crate it equals one [`Hasher::write`] of the whole input
+/// followed by [`Hasher::finish`]; for the narrow hashers, their `hash_bytes` of it.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{Checksum, CMHasher, CMHasher32, KeyedHasher};
+///
+/// fn checksum_of<C: Checksum>(mut checksum: C, parts: &[&[u8]]) -> C::Output {
+///     for part in parts {
+///         checksum.update(part);
+///     }
+///     checksum.finalize()
+/// }
+///
+/// let plain = checksum_of(CMHasher::new(), &[b"Hello, ", b"World!"]);
+/// assert_eq!(plain, checksum_of(CMHasher::new(), &[b"Hello, World!"]));
+/// let keyed = checksum_of(KeyedHasher::new(&[7; 32]), &[b"Hello, ", b"World!"]);
+/// assert_ne!(plain, keyed);
+/// let narrow: u32 = checksum_of(CMHasher32::new(), &[b"Hello, ", b"World!"]);
+/// assert_eq!(narrow, CMHasher32::new().hash_bytes(b"Hello, World!"));
+/// ```
+///
+/// A finalized checksum is gone:
+///
+/// ```compile_fail
+/// use cmhash::{Checksum, CMHasher};
+///
+/// let mut checksum = CMHasher::new();
+/// checksum.update(b"first");
+/// let first = checksum.finalize();
+/// checksum.update(b"second");
+/// ```
+pub trait Checksum {
+    /// The finished checksum
+    type Output;
+
+    /// Feeds `bytes` into the checksum, after everything fed before
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Finishes the checksum
+    fn finalize(self) -> Self::Output;
+
+    /// Discards everything fed so far, returning to the state the checksum was created in
+    fn reset(&mut self);
+}
+
+impl<M: Mixer> Checksum for CMHasher<M> {
+    type Output = u64;
+
+    /// Appends to a write that stays open until the next [`Hasher`] call, so bytes fed in any
+    /// number of updates hash as one [`Hasher::write`]
+    fn update(&mut self, bytes: &[u8]) {
+        self.append(bytes);
+    }
+
+    /// Completes the write, as an empty one if nothing was fed, and finishes the hasher
+    fn finalize(mut self) -> u64 {
+        self.append(&[]);
+        self.finish()
+    }
+
+    /// Returns to the state the hasher was created with, or restored from a snapshot with
+    fn reset(&mut self) {
+        CMHasher::reset(self);
+    }
+}
+
+impl Checksum for StatelessHasher {
+    type Output = u64;
+
+    /// Appends to a write that stays open until the next [`Hasher`] call, so bytes fed in any
+    /// number of updates hash as one [`Hasher::write`]
+    fn update(&mut self, bytes: &[u8]) {
+        self.append(bytes);
+    }
+
+    /// Completes the write, as an empty one if nothing was fed, and finishes the hasher
+    fn finalize(mut self) -> u64 {
+        self.append(&[]);
+        self.finish()
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Checksum for KeyedHasher {
+    type Output = u64;
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+    }
+
+    fn finalize(self) -> u64 {
+        self.finish()
+    }
+
+    fn reset(&mut self) {
+        KeyedHasher::reset(self);
+    }
+}
+
+impl Checksum for CMHasher16 {
+    type Output = u16;
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.append(bytes);
+    }
+
+    fn finalize(self) -> u16 {
+        self.finish_append()
+    }
+
+    /// Returns to the state the hasher was created with
+    fn reset(&mut self) {
+        CMHasher16::reset(self);
+    }
+}
+
+impl Checksum for CMHasher32 {
+    type Output = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.append(bytes);
+    }
+
+    fn finalize(self) -> u32 {
+        self.finish_append()
+    }
+
+    /// Returns to the state the hasher was created with
+    fn reset(&mut self) {
+        CMHasher32::reset(self);
+    }
+}
+
+/// The first 16 bytes squeezed after absorbing everything fed as one message, read
+/// little-endian
+impl Checksum for Sponge {
+    type Output = u128;
+
+    /// Appends to a message that stays open until the next absorb or squeeze, so bytes fed in
+    /// any number of updates are absorbed as one [`Sponge::absorb_bytes`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sponge has already been squeezed.
+    fn update(&mut self, bytes: &[u8]) {
+        self.append(bytes);
+    }
+
+    fn finalize(mut self) -> u128 {
+        self.append(&[]);
+        let mut out = [0; 16];
+        self.squeeze_bytes(&mut out);
+        u128::from_le_bytes(out)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
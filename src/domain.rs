@@ -0,0 +1,174 @@
+use core::hash::{BuildHasher, Hasher};
+
+use crate::hasher::{fmix64, for_each_word, CMHasher, DEFAULT_HASHER_STATE, DEFAULT_PRIME};
+use crate::mixer::Fmix64;
+use crate::output::hash_combine;
+use crate::seed::Seed;
+
+/// Derives the seed for `domain` nested within the domain seeded with `parent`.
+///
+/// The tag's little-endian words and then its length are each folded in with [`hash_combine`],
+/// so the seed is the same on every platform and depends on every bit of both the parent seed
+/// and the tag.
+fn domain_seed(parent: u64, domain: &str) -> u64 {
+    let bytes = domain.as_bytes();
+    let mut state = fmix64(parent);
+    for_each_word::<8>([bytes], |w| {
+        state = hash_combine(state, u64::from_le_bytes(w))
+    });
+    hash_combine(state, bytes.len() as u64)
+}
+
+fn portable_hasher(seed: u64) -> CMHasher<Fmix64> {
+    CMHasher::configured(seed, Fmix64, DEFAULT_PRIME, true)
+}
+
+/// A [`Hasher`] whose hash function is determined by a domain tag
+///
+/// Each domain derives its own seed by hashing the tag, so hashers for different domains behave
+/// as independent functions: keys that collide or cluster in one domain are spread out in
+/// another. Input is read little-endian and the output is finalized with [`Fmix64`], so a given
+/// domain produces the same hashes on every platform and in every version of this crate.
+///
+/// # Examples
+///
+/// ```
+/// use core::hash::Hasher;
+/// use cmhash::DomainHasher;
+///
+/// let mut cache = DomainHasher::new("cache");
+/// cache.write(b"user:42");
+/// let mut routing = DomainHasher::new("routing");
+/// routing.write(b"user:42");
+/// assert_ne!(cache.finish(), routing.finish());
+/// ```
+#[derive(Debug)]
+pub struct DomainHasher {
+    seed: u64,
+    inner: CMHasher<Fmix64>,
+}
+
+impl DomainHasher {
+    /// Creates a [`DomainHasher`] for `domain`
+    pub fn new(domain: &str) -> Self {
+        Self::with_seed(domain_seed(DEFAULT_HASHER_STATE, domain))
+    }
+
+    /// Creates a [`DomainHasher`] for `domain` under a root seed of `seed` rather than the
+    /// default one, so that separate deployments can keep their domains apart
+    pub fn new_seeded(domain: &str, seed: impl Into<Seed>) -> Self {
+        Self::with_seed(domain_seed(seed.into().0, domain))
+    }
+
+    fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            inner: portable_hasher(seed),
+        }
+    }
+
+    /// Returns the seed derived from the domain
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Creates a fresh [`DomainHasher`] for the domain `subdomain` nested within this one
+    ///
+    /// The result depends on the whole path of domains, so `"a"` then `"b"` differs from `"b"`
+    /// then `"a"` and from the single domain `"ab"`.
+    pub fn derive_subdomain(&self, subdomain: &str) -> Self {
+        Self::with_seed(domain_seed(self.seed, subdomain))
+    }
+}
+
+macro_rules! forward_writes {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(&mut self, i: $ty) {
+                self.inner.$method(i)
+            }
+        )*
+    };
+}
+
+/// Every write goes to the portable inner hasher through the same method, so integers, and the
+/// length prefixes of slices and strings, are read little-endian too
+impl Hasher for DomainHasher {
+    fn finish(&self) -> u64 {
+        self.inner.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.inner.write(bytes)
+    }
+
+    forward_writes! {
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_usize(usize),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+        write_isize(isize),
+    }
+}
+
+/// A [`BuildHasher`] that yields a [`DomainHasher`]
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use cmhash::DomainBuildHasher;
+///
+/// let mut labels = HashMap::with_hasher(DomainBuildHasher::new("metrics"));
+/// labels.insert("region", "eu");
+/// assert_eq!(labels["region"], "eu");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DomainBuildHasher {
+    seed: u64,
+}
+
+impl DomainBuildHasher {
+    /// Creates a [`DomainBuildHasher`] for `domain`
+    pub fn new(domain: &str) -> Self {
+        Self {
+            seed: domain_seed(DEFAULT_HASHER_STATE, domain),
+        }
+    }
+
+    /// Creates a [`DomainBuildHasher`] for `domain` under a root seed of `seed`, as
+    /// [`DomainHasher::new_seeded`] does
+    pub fn new_seeded(domain: &str, seed: impl Into<Seed>) -> Self {
+        Self {
+            seed: domain_seed(seed.into().0, domain),
+        }
+    }
+
+    /// Returns the seed derived from the domain
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns a [`DomainBuildHasher`] for the domain `subdomain` nested within this one, as
+    /// [`DomainHasher::derive_subdomain`] does
+    pub fn derive_subdomain(&self, subdomain: &str) -> Self {
+        Self {
+            seed: domain_seed(self.seed, subdomain),
+        }
+    }
+}
+
+impl BuildHasher for DomainBuildHasher {
+    type Hasher = DomainHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        DomainHasher::with_seed(self.seed)
+    }
+}
@@ -0,0 +1,306 @@
+use alloc::boxed::Box;
+use core::fmt;
+use core::hash::{BuildHasher, Hasher};
+
+use crate::builder::{Algorithm, CMHasherBuilder, ConfigError};
+use crate::checksum::Checksum;
+use crate::hasher::StatelessBuildHasher;
+use crate::keyed::KeyedBuildHasher;
+use crate::mixer::MixerChoice;
+use crate::output::HashOutput;
+
+/// A hash algorithm and its parameters, as read from configuration by
+/// [`DynWordHasher::from_config`]
+///
+/// `bits` is the width of the output, either 64 or 32. 32-bit outputs are folded down with
+/// [`HashOutput::fold32`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum HashConfig {
+    /// A [`StatelessHasher`](crate::StatelessHasher)
+    ///
+    /// As a [`BuildHasher`], keys hashed by several writes, such as `str`, pile into a few
+    /// buckets as they do with a [`StatelessBuildHasher`]; hash byte strings with
+    /// [`DynWordHasher::hash`] instead.
+    Stateless {
+        /// The version of the algorithm
+        version: Algorithm,
+        /// The width of the output
+        bits: u32,
+    },
+    /// A [`CMHasher`](crate::CMHasher) seeded with `seed` and finalized with `mixer`
+    Seeded {
+        /// The version of the algorithm
+        version: Algorithm,
+        /// The initial state of the hasher
+        seed: u64,
+        /// The finalizer
+        mixer: MixerChoice,
+        /// The width of the output
+        bits: u32,
+    },
+    /// A [`KeyedHasher`](crate::KeyedHasher) under a 256-bit key
+    Keyed {
+        /// The key
+        key: [u8; 32],
+        /// The width of the output
+        bits: u32,
+    },
+}
+
+impl HashConfig {
+    /// Returns the width of the output
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::Stateless { bits, .. } | Self::Seeded { bits, .. } | Self::Keyed { bits, .. } => {
+                *bits
+            }
+        }
+    }
+}
+
+/// Any of the variants, with `bits` always 32 or 64, so every value builds
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for HashConfig {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let bits = *u.choose(&[32, 64])?;
+        Ok(match u.choose_index(3)? {
+            0 => Self::Stateless {
+                version: u.arbitrary()?,
+                bits,
+            },
+            1 => Self::Seeded {
+                version: u.arbitrary()?,
+                seed: u.arbitrary()?,
+                mixer: u.arbitrary()?,
+                bits,
+            },
+            _ => Self::Keyed {
+                key: u.arbitrary()?,
+                bits,
+            },
+        })
+    }
+}
+
+/// A [`BuildHasher`] with its hashers erased, so that one hash costs one virtual call
+trait ErasedBuildHasher: Send + Sync {
+    fn hash(&self, bytes: &[u8]) -> u64;
+
+    fn build(&self) -> Box<dyn ErasedHasher + Send>;
+}
+
+impl<B> ErasedBuildHasher for B
+where
+    B: BuildHasher + Send + Sync,
+    B::Hasher: Checksum<Output = u64> + Send + 'static,
+{
+    fn hash(&self, bytes: &[u8]) -> u64 {
+        let mut h = self.build_hasher();
+        Checksum::update(&mut h, bytes);
+        h.finalize()
+    }
+
+    fn build(&self) -> Box<dyn ErasedHasher + Send> {
+        Box::new(self.build_hasher())
+    }
+}
+
+/// A hasher whose [`Hasher`] methods are called through the vtable as they are, and whose
+/// [`Checksum`] methods are erased into these
+trait ErasedHasher: Hasher {
+    fn feed(&mut self, bytes: &[u8]);
+
+    fn restart(&mut self);
+}
+
+impl<C: Checksum<Output = u64> + Hasher> ErasedHasher for C {
+    fn feed(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn restart(&mut self) {
+        self.reset();
+    }
+}
+
+const fn narrow(hash: u64, bits: u32) -> u64 {
+    if bits == 32 {
+        HashOutput(hash).fold32() as u64
+    } else {
+        hash
+    }
+}
+
+/// A hasher whose algorithm is chosen at runtime from a [`HashConfig`]
+///
+/// Each of the configurable algorithms sits behind one trait object, so structs holding a
+/// [`DynWordHasher`] need no type parameter for it, and hashing with [`Self::hash`] costs a
+/// single virtual call on top of the algorithm itself. Outputs are identical to those of the
+/// concrete hasher each configuration names, narrowed to 32 bits if configured.
+///
+/// A [`DynWordHasher`] is [`Send`] and [`Sync`]; the hashers it builds with [`Self::hasher`] are
+/// [`Send`] only, as the underlying hashers are.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{DynWordHasher, HashConfig, MixerChoice, Algorithm};
+///
+/// let config = HashConfig::Seeded {
+///     version: Algorithm::V1,
+///     seed: 7,
+///     mixer: MixerChoice::Fmix64,
+///     bits: 32,
+/// };
+/// let hasher = DynWordHasher::from_config(&config).unwrap();
+/// assert!(hasher.hash(b"key") <= u64::from(u32::MAX));
+/// ```
+pub struct DynWordHasher {
+    config: HashConfig,
+    inner: Box<dyn ErasedBuildHasher>,
+}
+
+impl DynWordHasher {
+    /// Creates the hasher described by `config`
+    ///
+    /// Returns [`ConfigError::UnsupportedWidth`] unless `bits` is 32 or 64.
+    pub fn from_config(config: &HashConfig) -> Result<Self, ConfigError> {
+        if !matches!(config.bits(), 32 | 64) {
+            return Err(ConfigError::UnsupportedWidth(config.bits()));
+        }
+        let inner: Box<dyn ErasedBuildHasher> = match *config {
+            HashConfig::Stateless { version, .. } => {
+                CMHasherBuilder::new().version(version).build_stateless()?;
+                Box::new(StatelessBuildHasher)
+            }
+            HashConfig::Seeded {
+                version,
+                seed,
+                mixer,
+                ..
+            } => Box::new(
+                CMHasherBuilder::new()
+                    .version(version)
+                    .seed(seed)
+                    .mixer(mixer)
+                    .build_build_hasher(),
+            ),
+            HashConfig::Keyed { ref key, .. } => Box::new(KeyedBuildHasher::new(key)),
+        };
+        Ok(Self {
+            config: config.clone(),
+            inner,
+        })
+    }
+
+    /// Returns the configuration the hasher was created from
+    pub fn config(&self) -> &HashConfig {
+        &self.config
+    }
+
+    /// Hashes `bytes`
+    pub fn hash(&self, bytes: &[u8]) -> u64 {
+        narrow(self.inner.hash(bytes), self.config.bits())
+    }
+
+    /// Returns a fresh hasher for feeding input in pieces
+    ///
+    /// Its [`Checksum::update`]s append to the input, so [`Checksum::finalize`] equals
+    /// [`Self::hash`] of everything fed, however it was split. Its [`Hasher`] methods are those of
+    /// the concrete hasher, each write hashed as that hasher hashes it.
+    pub fn hasher(&self) -> DynHasher {
+        DynHasher {
+            bits: self.config.bits(),
+            inner: self.inner.build(),
+        }
+    }
+}
+
+impl fmt::Debug for DynWordHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynWordHasher")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BuildHasher for DynWordHasher {
+    type Hasher = DynHasher;
+
+    fn build_hasher(&self) -> DynHasher {
+        self.hasher()
+    }
+}
+
+/// A hasher built by a [`DynWordHasher`]
+///
+/// Every [`Hasher`] method, the integer writes included, is forwarded to the concrete hasher,
+/// so as a [`BuildHasher`] a [`DynWordHasher`] hashes keys exactly as that hasher's own
+/// [`BuildHasher`] does.
+pub struct DynHasher {
+    bits: u32,
+    inner: Box<dyn ErasedHasher + Send>,
+}
+
+impl fmt::Debug for DynHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynHasher")
+            .field("bits", &self.bits)
+            .finish_non_exhaustive()
+    }
+}
+
+macro_rules! forward_writes {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(&mut self, i: $ty) {
+                self.inner.$method(i);
+            }
+        )*
+    };
+}
+
+impl Hasher for DynHasher {
+    fn finish(&self) -> u64 {
+        narrow(self.inner.finish(), self.bits)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.inner.write(bytes);
+    }
+
+    forward_writes! {
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_usize(usize),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+        write_isize(isize),
+    }
+}
+
+impl Checksum for DynHasher {
+    type Output = u64;
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.inner.feed(bytes);
+    }
+
+    /// Completes the input, as an empty one if nothing was fed, and finishes the hasher
+    fn finalize(mut self) -> u64 {
+        self.inner.feed(&[]);
+        self.finish()
+    }
+
+    fn reset(&mut self) {
+        self.inner.restart();
+    }
+}
@@ -0,0 +1,102 @@
+//! Directory arithmetic for extendible hashing.
+//!
+//! An extendible hash table keeps a directory of `2^global_depth` slots, each pointing at a
+//! bucket. Slots are indexed by the top `global_depth` bits of a key's hash, as
+//! [`dir_index`](crate::extendible::dir_index) computes. A bucket of local depth `d` holds the
+//! keys whose hashes share their top `d` bits, its
+//! [`bucket_prefix`](crate::extendible::bucket_prefix), and is pointed at by the
+//! `2^(global_depth - d)` consecutive slots that start with those bits.
+//!
+//! When a bucket overflows it splits in two of depth `d + 1`, the next bit of the hash deciding
+//! which half a key moves to, and [`split_targets`](crate::extendible::split_targets) gives the
+//! slots each half takes over. A bucket whose local depth already equals the global depth is
+//! pointed at by a single slot and can't split until the directory doubles, as
+//! [`needs_directory_double`](crate::extendible::needs_directory_double) reports. Since slots
+//! are indexed by the top bits, doubling the directory makes new slot `i` a copy of old slot
+//! `i / 2`, and no key changes bucket.
+//!
+//! Indexing by the top bits rather than the bottom ones keeps a bucket's slots contiguous and
+//! uses the best-mixed bits of hashes from this crate.
+//!
+//! # Examples
+//!
+//! ```
+//! use cmhash::extendible::{bucket_prefix, dir_index, needs_directory_double, split_targets};
+//!
+//! // A directory of 4 slots, where slots 0 and 1 share a bucket of depth 1
+//! let hash = 0x3FFF_FFFF_FFFF_FFFF;
+//! assert_eq!(dir_index(hash, 2), 0);
+//! assert_eq!(bucket_prefix(hash, 1), 0);
+//! assert!(!needs_directory_double(1, 2));
+//!
+//! // Splitting it leaves slot 0 to one half and gives slot 1 to the other
+//! let (low, high) = split_targets(1, 1, 2);
+//! assert_eq!((low, high), (0, 1));
+//! assert_eq!(dir_index(hash, 2), low);
+//! ```
+
+use crate::output::HashOutput;
+
+/// Returns the directory slot of `hash` in a directory of `2^global_depth` slots: the top
+/// `global_depth` bits of the hash
+///
+/// A depth of 0 is a directory of one slot, which every hash maps to.
+///
+/// # Panics
+///
+/// Panics if `global_depth` is over 64, or a directory that deep can't be indexed by `usize`.
+pub fn dir_index(hash: u64, global_depth: u8) -> usize {
+    assert!(
+        u32::from(global_depth) <= usize::BITS,
+        "a directory of 2^{global_depth} slots can't be indexed"
+    );
+    bucket_prefix(hash, global_depth) as usize
+}
+
+/// Returns the prefix identifying the bucket of local depth `local_depth` that `hash` belongs
+/// in: the top `local_depth` bits of the hash
+///
+/// # Panics
+///
+/// Panics if `local_depth` is over 64.
+pub fn bucket_prefix(hash: u64, local_depth: u8) -> u64 {
+    HashOutput(hash).top_bits(u32::from(local_depth))
+}
+
+/// Returns the first directory slot of each half when the bucket at slot `old_index`, of local
+/// depth `local_depth`, splits into two of depth `local_depth + 1` under a directory of depth
+/// `global_depth`
+///
+/// Each half takes over `2^(global_depth - local_depth - 1)` consecutive slots from the one
+/// returned for it. Keys whose next bit, bit `local_depth` from the top, is clear move to the
+/// first half and those whose bit is set to the second, so a key's new bucket is the half its
+/// [`dir_index`] falls in. `old_index` may be any of the slots pointing at the bucket.
+///
+/// # Panics
+///
+/// Panics if `local_depth` isn't below `global_depth`, in which case the directory must double
+/// first, if `global_depth` is too deep to index as [`dir_index`] panics, or if `old_index` is
+/// outside the directory.
+pub fn split_targets(old_index: usize, local_depth: u8, global_depth: u8) -> (usize, usize) {
+    assert!(
+        local_depth < global_depth,
+        "a bucket of depth {local_depth} can't split in a directory of depth {global_depth}"
+    );
+    assert!(
+        u32::from(global_depth) <= usize::BITS,
+        "a directory of 2^{global_depth} slots can't be indexed"
+    );
+    assert!(
+        (old_index as u128) < 1 << global_depth,
+        "slot {old_index} is outside a directory of depth {global_depth}"
+    );
+    let half = 1usize << (global_depth - local_depth - 1);
+    let low = old_index & !(half << 1).wrapping_sub(1);
+    (low, low + half)
+}
+
+/// Returns `true` if splitting a bucket of local depth `local_depth` needs the directory of
+/// depth `global_depth` to double first, which is when the bucket has only one slot
+pub fn needs_directory_double(local_depth: u8, global_depth: u8) -> bool {
+    local_depth >= global_depth
+}
@@ -0,0 +1,82 @@
+use core::num::NonZeroUsize;
+
+use crate::output::{hash_bytes, hash_combine, hash_to_bucket};
+use crate::seed::Seed;
+
+// Xored into the seed to key the sign hash differently from the index hash
+const SIGN_TWEAK: u64 = 0x5851_F42D_4C95_7F2D;
+
+/// The hashing trick: maps tokens to signed positions in a fixed-size feature vector
+///
+/// Each token's index in `0..dims` and its sign are taken from two hashes of it keyed
+/// differently, so which tokens share an index tells nothing about their signs, and collisions
+/// cancel out on average rather than piling up. The mapping depends only on the token, `dims`
+/// and the seed, so it is the same on every platform and in every version of this crate, and
+/// models trained on its output keep working.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use cmhash::FeatureHasher;
+///
+/// let hasher = FeatureHasher::new(NonZeroUsize::new(16).unwrap(), 7);
+/// let mut features = [0.0f32; 16];
+/// hasher.accumulate(["spam", "spam"], &mut features);
+/// let (index, sign) = hasher.index_and_sign(b"spam");
+/// assert_eq!(features[index], 2.0 * sign as f32);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureHasher {
+    dims: NonZeroUsize,
+    seed: u64,
+}
+
+impl FeatureHasher {
+    /// Creates a [`FeatureHasher`] mapping tokens into `dims` dimensions under `seed`
+    pub fn new(dims: NonZeroUsize, seed: impl Into<Seed>) -> Self {
+        Self {
+            dims,
+            seed: seed.into().0,
+        }
+    }
+
+    /// Returns the number of dimensions tokens are mapped into
+    pub fn dims(&self) -> NonZeroUsize {
+        self.dims
+    }
+
+    /// Returns the seed
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the index in `0..dims` and the sign, `1` or `-1`, of `token`
+    pub fn index_and_sign(&self, token: &[u8]) -> (usize, i8) {
+        let hash = hash_bytes(token, self.seed).0;
+        let index = hash_to_bucket(hash_combine(self.seed, hash), self.dims.get());
+        let sign = if hash_combine(self.seed ^ SIGN_TWEAK, hash) >> 63 == 0 {
+            1
+        } else {
+            -1
+        };
+        (index, sign)
+    }
+
+    /// Adds the signed count of each of `tokens` into `out`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` doesn't have exactly `dims` elements.
+    pub fn accumulate<T: AsRef<[u8]>>(&self, tokens: impl IntoIterator<Item = T>, out: &mut [f32]) {
+        assert_eq!(
+            out.len(),
+            self.dims.get(),
+            "the output must have one element per dimension"
+        );
+        for token in tokens {
+            let (index, sign) = self.index_and_sign(token.as_ref());
+            out[index] += sign as f32;
+        }
+    }
+}
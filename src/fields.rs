@@ -0,0 +1,332 @@
+use crate::hasher::{fmix64, DEFAULT_HASHER_STATE};
+use crate::seed::Seed;
+use crate::word;
+
+// An odd constant near 2^64 / phi, scaled by the field index before it is xored into the state
+const FIELD_CONSTANT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A value that can be hashed by feeding its fields to a [`FieldHasher`] as words
+///
+/// Unlike [`Hash`](core::hash::Hash), which reaches the hasher as byte slices, integers are fed
+/// as single words and strings are length-prefixed, so composite keys hash in a few rounds and
+/// can't collide by shifting bytes between fields. Usually implemented with
+/// `#[derive(CmHash)]` (requires the `derive` feature).
+///
+/// The hash of a given value and seed is the same on every platform.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{CmHash, FieldHasher};
+///
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// impl CmHash for Point {
+///     fn feed(&self, fields: &mut FieldHasher) {
+///         fields.field(0);
+///         self.x.feed(fields);
+///         fields.field(1);
+///         self.y.feed(fields);
+///     }
+/// }
+///
+/// assert_ne!(Point { x: 1, y: 2 }.cm_hash(0), Point { x: 2, y: 1 }.cm_hash(0));
+/// ```
+pub trait CmHash {
+    /// Feeds this value to `fields`
+    fn feed(&self, fields: &mut FieldHasher);
+
+    /// Hashes this value under `seed`
+    fn cm_hash(&self, seed: u64) -> u64 {
+        let mut fields = FieldHasher::new(seed);
+        self.feed(&mut fields);
+        fields.finish()
+    }
+}
+
+/// The state [`CmHash`] implementations feed their fields to
+///
+/// Each word goes through one round of the algorithm, and the result is finalized with fmix64.
+#[derive(Debug, Clone)]
+pub struct FieldHasher {
+    state: u64,
+    acc: u64,
+}
+
+impl FieldHasher {
+    /// Creates a [`FieldHasher`] seeded with `seed`
+    #[inline]
+    pub fn new(seed: impl Into<Seed>) -> Self {
+        Self {
+            state: seed.into().0 ^ DEFAULT_HASHER_STATE,
+            acc: 0,
+        }
+    }
+
+    /// Marks the start of the field at `index`, so that a value hashes differently depending on
+    /// which field it is in
+    #[inline]
+    pub fn field(&mut self, index: u32) {
+        self.state ^= (index as u64 + 1).wrapping_mul(FIELD_CONSTANT);
+    }
+
+    /// Feeds a single word
+    #[inline]
+    pub fn write_word(&mut self, val: u64) {
+        let (hash, state) = word::round(self.state, val);
+        self.acc ^= hash;
+        self.state = state;
+    }
+
+    /// Feeds `bytes` as its length followed by its little-endian words, so that no sequence of
+    /// writes is a prefix of another
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_word(bytes.len() as u64);
+        let chunks = bytes.array_chunks::<8>();
+        // Assembled a byte at a time, which for short strings beats copying into a padded word
+        let last = chunks
+            .remainder()
+            .iter()
+            .rev()
+            .fold(0, |word, &b| word << 8 | b as u64);
+        for chunk in chunks {
+            self.write_word(u64::from_le_bytes(*chunk));
+        }
+        self.write_word(last);
+    }
+
+    /// Returns the hash of everything fed so far
+    #[inline]
+    pub fn finish(&self) -> u64 {
+        fmix64(self.acc ^ self.state)
+    }
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl CmHash for $t {
+                #[inline]
+                fn feed(&self, fields: &mut FieldHasher) {
+                    fields.write_word(*self as u64);
+                }
+            }
+        )*
+    };
+}
+
+// Signed integers are sign-extended, so equal values of different widths feed the same word
+impl_word!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, bool, char);
+
+impl CmHash for u128 {
+    fn feed(&self, fields: &mut FieldHasher) {
+        fields.write_word(*self as u64);
+        fields.write_word((*self >> 64) as u64);
+    }
+}
+
+impl CmHash for i128 {
+    fn feed(&self, fields: &mut FieldHasher) {
+        (*self as u128).feed(fields)
+    }
+}
+
+impl CmHash for () {
+    fn feed(&self, _: &mut FieldHasher) {}
+}
+
+impl CmHash for str {
+    #[inline]
+    fn feed(&self, fields: &mut FieldHasher) {
+        fields.write_bytes(self.as_bytes())
+    }
+}
+
+// Feeds every element as its own word, so byte buffers are better fed with `write_bytes`
+impl<T: CmHash> CmHash for [T] {
+    fn feed(&self, fields: &mut FieldHasher) {
+        fields.write_word(self.len() as u64);
+        for item in self {
+            item.feed(fields);
+        }
+    }
+}
+
+impl<T: CmHash, const N: usize> CmHash for [T; N] {
+    fn feed(&self, fields: &mut FieldHasher) {
+        for item in self {
+            item.feed(fields);
+        }
+    }
+}
+
+impl<T: CmHash> CmHash for Option<T> {
+    fn feed(&self, fields: &mut FieldHasher) {
+        match self {
+            None => fields.write_word(0),
+            Some(value) => {
+                fields.write_word(1);
+                value.feed(fields);
+            }
+        }
+    }
+}
+
+impl<T: CmHash + ?Sized> CmHash for &T {
+    fn feed(&self, fields: &mut FieldHasher) {
+        (**self).feed(fields)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl CmHash for alloc::string::String {
+    fn feed(&self, fields: &mut FieldHasher) {
+        self.as_str().feed(fields)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: CmHash> CmHash for alloc::vec::Vec<T> {
+    fn feed(&self, fields: &mut FieldHasher) {
+        self.as_slice().feed(fields)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: CmHash + ?Sized> CmHash for alloc::boxed::Box<T> {
+    fn feed(&self, fields: &mut FieldHasher) {
+        (**self).feed(fields)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: CmHash),+> CmHash for ($($name,)+) {
+            fn feed(&self, fields: &mut FieldHasher) {
+                $(
+                    fields.field($index);
+                    self.$index.feed(fields);
+                )+
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Hashes a composite key from a list of expressions, combining the hash of each in order with
+/// [`hash_combine`](crate::hash_combine).
+///
+/// Integers are hashed with [`hash_u64`](crate::hash_u64) (signed ones sign-extended), anything
+/// else that is `AsRef<[u8]>`, such as strings and byte slices, with
+/// [`hash_bytes`](crate::hash_bytes), and any other [`Hash`](core::hash::Hash) value with
+/// [`hash_value`](crate::hash_value), all seeded with the seed. The seed defaults to
+/// [`DEFAULT_SEED`](crate::DEFAULT_SEED) and can be given with a leading `seed = ...`. The
+/// expressions are only borrowed, and integer literals need a suffix.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes, hash_combine, hash_fields, hash_u64, hash_value};
+///
+/// let (tenant_id, table_name, row_key) = (7u32, "users", (1u8, 'a'));
+/// let key = hash_fields!(seed = 9, tenant_id, table_name, row_key);
+///
+/// let mut manual = 9;
+/// manual = hash_combine(manual, hash_u64(tenant_id as u64, 9).0);
+/// manual = hash_combine(manual, hash_bytes(table_name.as_bytes(), 9).0);
+/// manual = hash_combine(manual, hash_value(&row_key, 9).0);
+/// assert_eq!(key, manual);
+/// ```
+#[macro_export]
+macro_rules! hash_fields {
+    (seed = $seed:expr, $($field:expr),+ $(,)?) => {{
+        #[allow(unused_imports)]
+        use $crate::fields::__private::{ViaBytes as _, ViaHash as _, ViaWord as _};
+        let seed: u64 = $seed;
+        let acc = seed;
+        $(
+            let acc = $crate::hash_combine(
+                acc,
+                (&&&$crate::fields::__private::Field(&$field)).field_hash(seed),
+            );
+        )+
+        acc
+    }};
+    ($($field:expr),+ $(,)?) => {
+        $crate::hash_fields!(seed = $crate::DEFAULT_SEED, $($field),+)
+    };
+}
+
+// Picks the hashing path for each `hash_fields!` argument by autoref: method lookup tries
+// `&&Field` (integers) before `&Field` (bytes) before `Field` (anything `Hash`)
+#[doc(hidden)]
+pub mod __private {
+    use core::hash::Hash;
+
+    use crate::output::{hash_bytes, hash_u64, hash_value};
+
+    #[derive(Debug)]
+    pub struct Field<'a, T: ?Sized>(pub &'a T);
+
+    pub trait Word: Copy {
+        fn to_word(self) -> u64;
+    }
+
+    macro_rules! impl_word {
+        ($($t:ty),*) => {
+            $(
+                impl Word for $t {
+                    #[inline]
+                    fn to_word(self) -> u64 {
+                        self as u64
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_word!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+    pub trait ViaWord {
+        fn field_hash(&self, seed: u64) -> u64;
+    }
+
+    impl<T: Word> ViaWord for &&Field<'_, T> {
+        #[inline]
+        fn field_hash(&self, seed: u64) -> u64 {
+            hash_u64(self.0.to_word(), seed).0
+        }
+    }
+
+    pub trait ViaBytes {
+        fn field_hash(&self, seed: u64) -> u64;
+    }
+
+    impl<T: AsRef<[u8]> + ?Sized> ViaBytes for &Field<'_, T> {
+        #[inline]
+        fn field_hash(&self, seed: u64) -> u64 {
+            hash_bytes(self.0.as_ref(), seed).0
+        }
+    }
+
+    pub trait ViaHash {
+        fn field_hash(&self, seed: u64) -> u64;
+    }
+
+    impl<T: Hash + ?Sized> ViaHash for Field<'_, T> {
+        #[inline]
+        fn field_hash(&self, seed: u64) -> u64 {
+            hash_value(self.0, seed).0
+        }
+    }
+}
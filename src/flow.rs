@@ -0,0 +1,103 @@
+use core::net::IpAddr;
+
+use crate::output::hash_combine;
+
+/// Hashes a flow's 5-tuple under `seed` so that both directions of the flow hash alike, for
+/// sending every packet of a connection to the same worker
+///
+/// The two endpoints, each an address and port, are put in order before hashing, so swapping
+/// source and destination gives the same hash. Addresses are hashed as 128-bit IPv6 addresses,
+/// IPv4 ones in their IPv4-mapped form `::ffff:a.b.c.d`, so an IPv4 flow hashes like the same
+/// flow seen through a dual-stack socket. The endpoints are folded in as the high and low words
+/// of each address in turn, then a word packing both ports and the protocol, with
+/// [`hash_combine`].
+///
+/// The output is stable: it is the same on every platform and won't change in future releases,
+/// so a load balancer's configuration reproduces the same assignment.
+///
+/// # Examples
+///
+/// ```
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use cmhash::flow_hash;
+///
+/// let client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10));
+/// let server = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7));
+/// let outbound = flow_hash(client, server, 50_000, 443, 6, 7);
+/// let inbound = flow_hash(server, client, 443, 50_000, 6, 7);
+/// assert_eq!(outbound, inbound);
+/// ```
+pub fn flow_hash(
+    src_ip: IpAddr,
+    dst_ip: IpAddr,
+    src_port: u16,
+    dst_port: u16,
+    proto: u8,
+    seed: u64,
+) -> u64 {
+    let (src, dst) = (endpoint(src_ip, src_port), endpoint(dst_ip, dst_port));
+    let (first, second) = if src <= dst { (src, dst) } else { (dst, src) };
+    hash_endpoints(first, second, proto, true, seed)
+}
+
+/// Hashes a flow's 5-tuple under `seed` keeping its direction, so that the two directions of a
+/// flow hash differently
+///
+/// This is [`flow_hash`] without putting the endpoints in order, and is stable in the same way.
+/// It never agrees with [`flow_hash`] on the same flow other than by chance.
+///
+/// # Examples
+///
+/// ```
+/// use std::net::{IpAddr, Ipv6Addr};
+/// use cmhash::flow_hash_directed;
+///
+/// let a = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+/// let b = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2));
+/// assert_ne!(
+///     flow_hash_directed(a, b, 5353, 53, 17, 0),
+///     flow_hash_directed(b, a, 53, 5353, 17, 0),
+/// );
+/// ```
+pub fn flow_hash_directed(
+    src_ip: IpAddr,
+    dst_ip: IpAddr,
+    src_port: u16,
+    dst_port: u16,
+    proto: u8,
+    seed: u64,
+) -> u64 {
+    let (src, dst) = (endpoint(src_ip, src_port), endpoint(dst_ip, dst_port));
+    hash_endpoints(src, dst, proto, false, seed)
+}
+
+/// An address as a 128-bit IPv6 address, IPv4 ones mapped, and a port, ordered by address first
+fn endpoint(ip: IpAddr, port: u16) -> (u128, u16) {
+    let ip = match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    };
+    (ip.to_bits(), port)
+}
+
+fn hash_endpoints(
+    (first_ip, first_port): (u128, u16),
+    (second_ip, second_port): (u128, u16),
+    proto: u8,
+    symmetric: bool,
+    seed: u64,
+) -> u64 {
+    let ports = u64::from(first_port) << 32
+        | u64::from(second_port) << 16
+        | u64::from(proto) << 8
+        | u64::from(symmetric);
+    [
+        (first_ip >> 64) as u64,
+        first_ip as u64,
+        (second_ip >> 64) as u64,
+        second_ip as u64,
+        ports,
+    ]
+    .into_iter()
+    .fold(seed, hash_combine)
+}
@@ -0,0 +1,121 @@
+//! Framing messages with a trailing checksum to detect corruption in transit.
+//!
+//! A frame is the payload followed by [`TRAILER_LEN`](crate::frame::TRAILER_LEN) bytes: the
+//! little-endian checksum of the payload under a seed both ends agree on. Each little-endian
+//! word of the payload is xored into the state and passed through the MurmurHash3 finalizer that
+//! [`Fmix64`](crate::Fmix64) applies, then the payload length is folded in the same way, so
+//! frames verify the same on every platform. As the finalizer is a bijection, corruption confined
+//! to one word of the payload, such as any single bit flip, always changes the checksum.
+//! [`CMHasher`](crate::CMHasher) makes no such guarantee, which is why it isn't used here.
+//!
+//! This detects accidental corruption, such as bit flips and truncation on a lossy link. It is
+//! **not** a MAC: anyone can forge a frame that verifies.
+//!
+//! # Examples
+//!
+//! ```
+//! use cmhash::frame::{append_checksum, verify, ChecksumError, TRAILER_LEN};
+//!
+//! let mut buf = [0u8; 64];
+//! buf[..5].copy_from_slice(b"hello");
+//! let len = append_checksum(&mut buf, 5, 7);
+//! assert_eq!(len, 5 + TRAILER_LEN);
+//! assert_eq!(verify(&buf[..len], 7), Ok(&b"hello"[..]));
+//!
+//! buf[1] ^= 0x20;
+//! assert_eq!(verify(&buf[..len], 7), Err(ChecksumError::Mismatch));
+//! ```
+
+use core::fmt;
+
+use crate::hasher::{fmix64, for_each_word, DEFAULT_HASHER_STATE};
+
+/// The length of the checksum trailer
+pub const TRAILER_LEN: usize = 8;
+
+/// Why a frame couldn't be checked or written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumError {
+    /// The frame is shorter than the trailer
+    Truncated,
+    /// The trailer doesn't match the payload
+    Mismatch,
+    /// The output buffer can't hold the frame, which needs this many bytes
+    BufferTooSmall(usize),
+}
+
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => f.write_str("frame is shorter than its checksum"),
+            Self::Mismatch => f.write_str("checksum does not match the payload"),
+            Self::BufferTooSmall(needed) => {
+                write!(
+                    f,
+                    "buffer is too small for the frame, which needs {needed} bytes"
+                )
+            }
+        }
+    }
+}
+
+/// Returns the checksum trailer for `payload` under `seed`
+pub fn checksum(payload: &[u8], seed: u64) -> [u8; TRAILER_LEN] {
+    let mut state = seed ^ DEFAULT_HASHER_STATE;
+    for_each_word::<8>([payload], |w| state = fmix64(state ^ u64::from_le_bytes(w)));
+    fmix64(state ^ payload.len() as u64).to_le_bytes()
+}
+
+/// Writes the checksum of the first `payload_len` bytes of `buf` after them, returning the
+/// length of the frame
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than `payload_len + TRAILER_LEN`.
+pub fn append_checksum(buf: &mut [u8], payload_len: usize, seed: u64) -> usize {
+    let len = payload_len + TRAILER_LEN;
+    assert!(
+        buf.len() >= len,
+        "a {payload_len} byte payload needs a {len} byte buffer, but it is {} bytes",
+        buf.len()
+    );
+    let trailer = checksum(&buf[..payload_len], seed);
+    buf[payload_len..len].copy_from_slice(&trailer);
+    len
+}
+
+/// Writes `payload` followed by its checksum to the start of `out`, returning the length of the
+/// frame
+///
+/// Returns [`ChecksumError::BufferTooSmall`] if `out` can't hold the frame, leaving it
+/// untouched.
+pub fn frame_into(payload: &[u8], out: &mut [u8], seed: u64) -> Result<usize, ChecksumError> {
+    let len = payload.len() + TRAILER_LEN;
+    if out.len() < len {
+        return Err(ChecksumError::BufferTooSmall(len));
+    }
+    out[..payload.len()].copy_from_slice(payload);
+    Ok(append_checksum(out, payload.len(), seed))
+}
+
+/// Returns `payload` followed by its checksum
+#[cfg(feature = "alloc")]
+pub fn frame(payload: &[u8], seed: u64) -> alloc::vec::Vec<u8> {
+    let mut out = alloc::vec![0; payload.len() + TRAILER_LEN];
+    frame_into(payload, &mut out, seed).expect("the buffer fits the frame");
+    out
+}
+
+/// Checks the trailer of the frame `buf`, returning the payload if it matches
+pub fn verify(buf: &[u8], seed: u64) -> Result<&[u8], ChecksumError> {
+    let payload_len = buf
+        .len()
+        .checked_sub(TRAILER_LEN)
+        .ok_or(ChecksumError::Truncated)?;
+    let (payload, trailer) = buf.split_at(payload_len);
+    if checksum(payload, seed)[..] == *trailer {
+        Ok(payload)
+    } else {
+        Err(ChecksumError::Mismatch)
+    }
+}
@@ -0,0 +1,178 @@
+use core::hash::Hasher;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::hasher::{CMHasher, Fmix64Hasher, DEFAULT_PRIME};
+use crate::mixer::Fmix64;
+use crate::output::DEFAULT_SEED;
+
+/// Size of the buffer used when streaming from a reader
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Files at least this large are memory mapped when the `mmap` feature is enabled
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// Hashes everything read from `reader` until end of file.
+///
+/// Words are read little-endian, so the digest is the same on every platform. On little-endian
+/// targets it is identical to calling [`hash_bytes`](crate::hash_bytes) with `seed` on the same
+/// bytes held in memory, regardless of how the reader splits them up. Everywhere, it equals a
+/// [`CMHasherBuilder`](crate::CMHasherBuilder) with `.portable(true)` and
+/// [`MixerChoice::Fmix64`](crate::MixerChoice::Fmix64) given the same bytes in one write.
+///
+/// # Examples
+///
+/// ```
+/// use core::hash::Hasher;
+/// use cmhash::{hash_reader, CMHasherBuilder, MixerChoice};
+///
+/// let data = b"streamed bytes";
+/// let mut h = CMHasherBuilder::new().seed(7).mixer(MixerChoice::Fmix64).portable(true).build_hasher();
+/// h.write(data);
+/// assert_eq!(hash_reader(&data[..], 7).unwrap(), h.finish());
+/// ```
+pub fn hash_reader<R: Read>(reader: R, seed: u64) -> io::Result<u64> {
+    hash_reader_with_buffer(reader, seed, &mut vec![0u8; READ_BUFFER_SIZE])
+}
+
+/// The hasher [`hash_reader`] and [`hash_file`] feed: Fmix64-finalized, reading words
+/// little-endian
+fn portable_hasher(seed: u64) -> Fmix64Hasher {
+    CMHasher::configured(seed, Fmix64, DEFAULT_PRIME, true)
+}
+
+fn hash_reader_with_buffer<R: Read>(reader: R, seed: u64, buf: &mut [u8]) -> io::Result<u64> {
+    hash_into(reader, portable_hasher(seed), buf)
+}
+
+/// Feeds everything read from `reader` to `hasher` as one write, returning its digest
+fn hash_into<R: Read>(mut reader: R, hasher: Fmix64Hasher, buf: &mut [u8]) -> io::Result<u64> {
+    let mut stream = hasher.stream();
+    loop {
+        match reader.read(buf) {
+            Ok(0) => break,
+            Ok(n) => stream.write(&buf[..n]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    stream.finish();
+    Ok(hasher.finish())
+}
+
+/// Fingerprints the contents of the file at `path`, equal to [`hash_reader`] with
+/// [`DEFAULT_SEED`] over the file's bytes, and so the same on every platform.
+///
+/// Files are read through a buffer, or, with the `mmap` feature enabled, large files are memory
+/// mapped instead. Both produce the same digest.
+///
+/// Hashing is best-effort if the file is modified while it is being read: the digest may reflect
+/// any mix of the old and new contents. With the `mmap` feature, truncating a mapped file while
+/// it is being hashed can additionally crash the process on some platforms, so only enable it
+/// when files being fingerprinted are not truncated concurrently.
+pub fn hash_file(path: impl AsRef<Path>) -> io::Result<u64> {
+    hash_path(path.as_ref(), &mut vec![0u8; READ_BUFFER_SIZE])
+}
+
+fn hash_path(path: &Path, buf: &mut [u8]) -> io::Result<u64> {
+    let file = File::open(path)?;
+    #[cfg(feature = "mmap")]
+    if file.metadata()?.len() >= MMAP_THRESHOLD {
+        // SAFETY: the mapping is only read, and the documentation of `hash_file` covers
+        // concurrent truncation of the underlying file
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        let hasher = portable_hasher(DEFAULT_SEED);
+        let mut stream = hasher.stream();
+        stream.write(&map);
+        stream.finish();
+        return Ok(hasher.finish());
+    }
+    hash_reader_with_buffer(file, DEFAULT_SEED, buf)
+}
+
+/// Options for [`hash_files_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashFilesOptions {
+    /// The size of the read buffer each worker uses
+    pub buffer_size: usize,
+    /// The maximum number of files hashed at once
+    pub concurrency: NonZeroUsize,
+}
+
+impl Default for HashFilesOptions {
+    /// A 64 KiB buffer and one worker per available core
+    fn default() -> Self {
+        Self {
+            buffer_size: READ_BUFFER_SIZE,
+            concurrency: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+}
+
+/// Fingerprints many files concurrently with [`hash_file`], using the default
+/// [`HashFilesOptions`].
+///
+/// The result for `paths[i]` is at index `i` of the returned [`Vec`], and a file that can't be
+/// read only produces an error in its own slot.
+pub fn hash_files<P: AsRef<Path> + Sync>(paths: &[P]) -> Vec<io::Result<u64>> {
+    hash_files_with(paths, &HashFilesOptions::default())
+}
+
+/// Fingerprints many files concurrently with [`hash_file`], with at most
+/// `options.concurrency` files being read at once.
+///
+/// The workers are scoped to this call: they have all finished by the time it returns or
+/// unwinds, so no threads are left behind.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_files_with, HashFilesOptions};
+/// use std::num::NonZeroUsize;
+///
+/// let options = HashFilesOptions {
+///     concurrency: NonZeroUsize::new(2).unwrap(),
+///     ..Default::default()
+/// };
+/// let results = hash_files_with(&["/this/path/does/not/exist"], &options);
+/// assert!(results[0].is_err());
+/// ```
+pub fn hash_files_with<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    options: &HashFilesOptions,
+) -> Vec<io::Result<u64>> {
+    let buffer_size = options.buffer_size.max(1);
+    let workers = options.concurrency.get().min(paths.len());
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<io::Result<u64>>> = paths.iter().map(|_| None).collect();
+    std::thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut buf = vec![0u8; buffer_size];
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            break done;
+                        };
+                        done.push((i, hash_path(path.as_ref(), &mut buf)));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (i, result) in handle.join().unwrap() {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| r.expect("every path is claimed by a worker"))
+        .collect()
+}
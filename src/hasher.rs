@@ -1,15 +1,66 @@
 use core::cell::Cell;
 use core::hash::{BuildHasher, Hasher};
+use core::sync::atomic::Ordering;
 
-const DEFAULT_HASHER_STATE: u64 = 0xAAAA_AAAA_AAAA_AAAA;
+#[cfg(not(feature = "portable-atomic"))]
+use core::sync::atomic::AtomicUsize;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic::AtomicUsize;
+
+use crate::builder::Strategy;
+use crate::mixer::{Fmix64, Mixer, NoMix};
+use crate::raw;
+use crate::seed::Seed;
+use crate::snapshot::{self, StateError};
+
+pub(crate) const DEFAULT_HASHER_STATE: u64 = 0xAAAA_AAAA_AAAA_AAAA;
+
+/// The multiplier [`CMHasher`] uses unless configured otherwise
+pub(crate) const DEFAULT_PRIME: u64 = (2 << 61) - 1;
+
+// The first 64 fractional bits of pi, so that a zero state and input don't stay zero
+const SHIFT_ADD_KEY: u64 = 0x243F_6A88_85A3_08D3;
+
+/// A round of [`Strategy::ShiftAdd`]: xors `val` into `state`, then runs three SipHash-style
+/// add-rotate-xor rounds over it and a second lane derived from `state`, returning the hash and
+/// the next state
+///
+/// It uses only additions, rotations and xors on `u64`, which targets without a hardware
+/// multiplier can do in a handful of instructions each.
+pub(crate) const fn shift_add_round(state: u64, val: u64) -> (u64, u64) {
+    let mut a = val ^ state;
+    let mut b = state.rotate_left(32) ^ SHIFT_ADD_KEY;
+    let mut i = 0;
+    while i < 3 {
+        a = a.wrapping_add(b);
+        b = b.rotate_left(13) ^ a;
+        a = a.rotate_left(32);
+        a = a.wrapping_add(b);
+        b = b.rotate_left(17) ^ a;
+        i += 1;
+    }
+    (a, b)
+}
 
 ///An implementation of Fast Mersenne Hashing that is compatible with [`Hasher`]
-#[derive(Debug, Default)]
-pub struct CMHasher {
+///
+/// The [`Mixer`] `M` finalizes the output of [`Hasher::finish`]. The default, [`NoMix`], leaves it
+/// untouched.
+#[derive(Debug)]
+pub struct CMHasher<M = NoMix> {
     state: Cell<u64>,
     data: Cell<u64>,
+    initial: u64,
+    mixer: M,
+    prime: u64,
+    portable: bool,
+    strategy: Strategy,
+    open: Cell<Option<OpenWrite>>,
 }
 
+/// A [`CMHasher`] whose output is finalized with [`Fmix64`]
+pub type Fmix64Hasher = CMHasher<Fmix64>;
+
 impl CMHasher {
     /// Creates a new [`CMHasher`].
     pub fn new() -> Self {
@@ -17,52 +68,332 @@ impl CMHasher {
     }
 
     /// Creates a new [`CMHasher`] with the specified state
-    pub fn with_state(state: u64) -> Self {
+    pub fn with_state(state: impl Into<Seed>) -> Self {
+        Self::with_mixer(state, NoMix)
+    }
+}
+
+impl<M: Mixer> CMHasher<M> {
+    /// Creates a new [`CMHasher`] with the specified state whose output is finalized by `mixer`
+    pub fn with_mixer(state: impl Into<Seed>, mixer: M) -> Self {
+        Self::configured(state.into().0, mixer, DEFAULT_PRIME, false)
+    }
+
+    /// Creates a hasher multiplying by `prime`, reading words little-endian if `portable` and in
+    /// native order otherwise
+    pub(crate) fn configured(state: u64, mixer: M, prime: u64, portable: bool) -> Self {
         Self {
             state: Cell::new(state),
             data: Cell::new(0),
+            initial: state,
+            mixer,
+            prime,
+            portable,
+            strategy: Strategy::Multiply,
+            open: Cell::new(None),
+        }
+    }
+
+    /// Switches the hasher to the round function of `strategy`
+    pub(crate) fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Returns the hasher to the state it was created or restored with, discarding everything
+    /// written since
+    pub(crate) fn reset(&mut self) {
+        self.state.set(self.initial);
+        self.data.set(0);
+        self.open.set(None);
+    }
+
+    /// Appends `bytes` to a write left open across calls, which the hasher's next write,
+    /// [`Hasher::finish`] or snapshot completes exactly as one [`Hasher::write`] of everything
+    /// appended
+    pub(crate) fn append(&mut self, bytes: &[u8]) {
+        let mut open = self.open.take().unwrap_or(OpenWrite::new(self.state.get()));
+        open.words
+            .push(bytes, |word| open.data ^= self.hash(self.load(word)));
+        self.open.set(Some(open));
+    }
+
+    /// Completes the write left open by [`Self::append`], if there is one
+    fn close(&self) {
+        if let Some(open) = self.open.take() {
+            let last = self.hash(self.load(open.words.finish()));
+            self.data.set(open.data ^ last);
+        }
+    }
+
+    /// Writes a sequence of byte slices exactly as [`Hasher::write`] would write their
+    /// concatenation, without joining them into one buffer.
+    #[cfg(feature = "std")]
+    pub fn write_vectored(&mut self, parts: &[std::io::IoSlice<'_>]) {
+        let mut stream = self.stream();
+        for part in parts {
+            stream.write(part);
+        }
+        stream.finish();
+    }
+
+    /// Starts a write whose bytes arrive in several parts
+    #[cfg(any(feature = "std", feature = "bytes", feature = "unicode"))]
+    pub(crate) fn stream(&self) -> StreamingWrite<'_, M> {
+        self.close();
+        StreamingWrite {
+            hasher: self,
+            data: self.state.get(),
+            words: WordBuffer::new(),
         }
     }
 
     fn hash(&self, val: u64) -> u64 {
         let state = self.state.get();
-        let input = val ^ state;
-        let (hash, state) = input.widening_mul((2 << 61) - 1);
+        let (hash, state) = match self.strategy {
+            Strategy::Multiply => raw::round_u64(state, val, self.prime),
+            Strategy::ShiftAdd => shift_add_round(state, val),
+        };
         self.state.set(state);
         hash
     }
+
+    fn load(&self, word: [u8; 8]) -> u64 {
+        if self.portable {
+            u64::from_le_bytes(word)
+        } else {
+            u64::from_ne_bytes(word)
+        }
+    }
+
+    /// Writes an integer's bytes, little-endian when the hasher is portable so that integer keys
+    /// hash the same on every platform
+    fn write_int<const N: usize>(&mut self, le: [u8; N], ne: [u8; N]) {
+        self.write(if self.portable { &le } else { &ne });
+    }
+}
+
+/// The size of a [`CMHasher`] snapshot: version, state, data, prime and flags
+const CM_SNAPSHOT_SIZE: usize = 26;
+
+/// The snapshot flag recording that input words are read little-endian
+const PORTABLE_FLAG: u8 = 1;
+
+/// The snapshot flag recording that the hasher uses [`Strategy::ShiftAdd`]
+const SHIFT_ADD_FLAG: u8 = 2;
+
+impl CMHasher {
+    /// The size of the snapshot written by [`CMHasher::to_bytes`]
+    pub const SNAPSHOT_SIZE: usize = CM_SNAPSHOT_SIZE;
+
+    /// Restores a hasher from a snapshot written by [`Self::to_bytes`]
+    pub fn from_bytes(bytes: [u8; CM_SNAPSHOT_SIZE]) -> Result<Self, StateError> {
+        Self::from_bytes_with_mixer(bytes, NoMix)
+    }
 }
 
-impl Hasher for CMHasher {
+impl<M: Mixer> CMHasher<M> {
+    /// Writes the hasher as a snapshot
+    ///
+    /// The layout is a version byte, then the state, the pending output and the multiplier, each
+    /// as a little-endian `u64`, then a flags byte whose lowest bit records whether input words
+    /// are read little-endian and whose next bit records whether the hasher uses
+    /// [`Strategy::ShiftAdd`]. The mixer is part of the hasher's type rather than the snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::hash::Hasher;
+    /// use cmhash::CMHasher;
+    ///
+    /// let mut hasher = CMHasher::new();
+    /// hasher.write(b"Hello, ");
+    /// let mut resumed = CMHasher::from_bytes(hasher.to_bytes()).unwrap();
+    /// hasher.write(b"World!");
+    /// resumed.write(b"World!");
+    /// assert_eq!(resumed.finish(), hasher.finish());
+    /// ```
+    pub fn to_bytes(&self) -> [u8; CM_SNAPSHOT_SIZE] {
+        self.close();
+        let mut bytes = [0; CM_SNAPSHOT_SIZE];
+        bytes[0] = snapshot::SNAPSHOT_VERSION;
+        bytes[1..9].copy_from_slice(&self.state.get().to_le_bytes());
+        bytes[9..17].copy_from_slice(&self.data.get().to_le_bytes());
+        bytes[17..25].copy_from_slice(&self.prime.to_le_bytes());
+        if self.portable {
+            bytes[25] |= PORTABLE_FLAG;
+        }
+        if self.strategy == Strategy::ShiftAdd {
+            bytes[25] |= SHIFT_ADD_FLAG;
+        }
+        bytes
+    }
+
+    /// Restores a hasher from a snapshot written by [`Self::to_bytes`], finalizing its output
+    /// with `mixer`
+    pub fn from_bytes_with_mixer(
+        bytes: [u8; CM_SNAPSHOT_SIZE],
+        mixer: M,
+    ) -> Result<Self, StateError> {
+        snapshot::check_version(&bytes)?;
+        let flags = bytes[25];
+        if flags & !(PORTABLE_FLAG | SHIFT_ADD_FLAG) != 0 {
+            return Err(StateError::UnknownFlags(flags));
+        }
+        let hasher = Self::configured(
+            snapshot::read_u64(&bytes, 1),
+            mixer,
+            snapshot::read_u64(&bytes, 17),
+            flags & PORTABLE_FLAG != 0,
+        );
+        let hasher = if flags & SHIFT_ADD_FLAG != 0 {
+            hasher.with_strategy(Strategy::ShiftAdd)
+        } else {
+            hasher
+        };
+        hasher.data.set(snapshot::read_u64(&bytes, 9));
+        Ok(hasher)
+    }
+}
+
+impl<M: Default> Default for CMHasher<M> {
+    fn default() -> Self {
+        Self {
+            state: Cell::new(0),
+            data: Cell::new(0),
+            initial: 0,
+            mixer: M::default(),
+            prime: DEFAULT_PRIME,
+            portable: false,
+            strategy: Strategy::Multiply,
+            open: Cell::new(None),
+        }
+    }
+}
+
+impl<M: Mixer> Hasher for CMHasher<M> {
     fn finish(&self) -> u64 {
-        self.data.replace(0)
+        self.close();
+        self.mixer.mix(self.data.replace(0))
     }
 
     fn write(&mut self, bytes: &[u8]) {
+        self.close();
         let chunks = bytes.array_chunks::<8>();
         let rem = {
             let mut r = chunks.remainder().iter();
-            u64::from_ne_bytes([0u8; 8].map(|_| *r.next().unwrap_or(&0)))
+            self.load([0u8; 8].map(|_| *r.next().unwrap_or(&0)))
         };
         self.data.set(
             chunks
-                .map(|c| u64::from_ne_bytes(*c))
+                .map(|c| self.load(*c))
                 .chain(core::iter::once(rem))
                 .fold(self.state.get(), |val, next| val ^ self.hash(next)),
         );
     }
 
+    fn write_u16(&mut self, i: u16) {
+        self.write_int(i.to_le_bytes(), i.to_ne_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_int(i.to_le_bytes(), i.to_ne_bytes());
+    }
+
     fn write_u64(&mut self, i: u64) {
+        self.close();
         self.data.set(self.hash(i));
     }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write_int(i.to_le_bytes(), i.to_ne_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_int(i.to_le_bytes(), i.to_ne_bytes());
+    }
+}
+
+impl<M: Mixer> Extend<u8> for CMHasher<M> {
+    /// Hashes the bytes produced by `iter` exactly as if they were passed to [`Hasher::write`] as one slice
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        self.close();
+        self.data.set(
+            Words::new(iter.into_iter()).fold(self.state.get(), |val, next| {
+                val ^ self.hash(self.load(next))
+            }),
+        );
+    }
+}
+
+impl<'a, M: Mixer> Extend<&'a u8> for CMHasher<M> {
+    fn extend<I: IntoIterator<Item = &'a u8>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+/// The finished [`CMHasher`] digest of the bytes collected from an iterator
+///
+/// # Examples
+///
+/// ```
+/// use core::hash::Hasher;
+/// use cmhash::{CMHasher, DigestOf};
+///
+/// let digest: DigestOf = b"Hello, World!".iter().collect();
+/// let mut hasher = CMHasher::new();
+/// hasher.write(b"Hello, World!");
+/// assert_eq!(digest.0, hasher.finish());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DigestOf(pub u64);
+
+impl FromIterator<u8> for DigestOf {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut h = CMHasher::new();
+        h.extend(iter);
+        Self(h.finish())
+    }
+}
+
+impl<'a> FromIterator<&'a u8> for DigestOf {
+    fn from_iter<I: IntoIterator<Item = &'a u8>>(iter: I) -> Self {
+        iter.into_iter().copied().collect()
+    }
+}
+
+impl From<DigestOf> for u64 {
+    fn from(digest: DigestOf) -> Self {
+        digest.0
+    }
 }
 
 /// A [`BuildHasher`] that yields a [`CMHasher`]
-#[derive(Debug)]
-pub struct CMBuildHasher {
+///
+/// # Table keys
+///
+/// Unfinalized, as with the default [`NoMix`], the low bits of a hash depend only on the low
+/// bits of the key's words, and those bits pick the bucket in `std` and hashbrown maps.
+/// Sequential integers fill such a table without a collision, but keys whose first bytes agree,
+/// such as strings with a common prefix, share a bucket. Finalize with [`Fmix64`] for those.
+///
+/// Each write replaces the data of the one before, with only the state carried over, so a key
+/// whose [`Hash`](core::hash::Hash) impl writes more than once is hashed by little more than its
+/// last write. `str` and `String` end with a one-byte terminator and hash to a handful of
+/// values whatever the mixer, so write their bytes once through a wrapper instead. The probe
+/// lengths behind both notes are checked in `tests/probe_lengths.rs`.
+#[derive(Debug, Clone)]
+pub struct CMBuildHasher<M = NoMix> {
     state: u64,
+    mixer: M,
+    prime: u64,
+    portable: bool,
+    strategy: Strategy,
 }
 
+/// A [`CMBuildHasher`] whose hashers are finalized with [`Fmix64`]
+pub type Fmix64BuildHasher = CMBuildHasher<Fmix64>;
+
 impl CMBuildHasher {
     /// Returns a [`CMBuildHasher`] with the default state
     pub fn new() -> Self {
@@ -70,49 +401,247 @@ impl CMBuildHasher {
     }
 
     /// Returns a [`CMBuildHasher`] with the provided state
-    pub fn with_state(state: u64) -> Self {
-        Self { state }
+    pub fn with_state(state: impl Into<Seed>) -> Self {
+        Self::with_mixer(state, NoMix)
+    }
+}
+
+impl<M: Mixer + Clone> CMBuildHasher<M> {
+    /// Returns a [`CMBuildHasher`] with the provided state whose hashers are finalized by `mixer`
+    pub fn with_mixer(state: impl Into<Seed>, mixer: M) -> Self {
+        Self::configured(state.into().0, mixer, DEFAULT_PRIME, false)
+    }
+
+    /// Returns a [`CMBuildHasher`] whose hashers are configured as by [`CMHasher::configured`]
+    pub(crate) fn configured(state: u64, mixer: M, prime: u64, portable: bool) -> Self {
+        Self {
+            state,
+            mixer,
+            prime,
+            portable,
+            strategy: Strategy::Multiply,
+        }
+    }
+
+    /// Switches the hashers built to the round function of `strategy`
+    pub(crate) fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+}
+
+/// Any state, mixer, [`Prime`](crate::Prime), byte order and [`Strategy`]; the multiplier is
+/// always one of the primes [`CMHasherBuilder`](crate::CMHasherBuilder) offers
+#[cfg(feature = "arbitrary")]
+impl<'a, M: Mixer + Clone + arbitrary::Arbitrary<'a>> arbitrary::Arbitrary<'a>
+    for CMBuildHasher<M>
+{
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let state = u.arbitrary()?;
+        let mixer = u.arbitrary()?;
+        let prime = u.arbitrary::<crate::Prime>()?.value();
+        let portable = u.arbitrary()?;
+        Ok(Self::configured(state, mixer, prime, portable).with_strategy(u.arbitrary()?))
+    }
+}
+
+/// A hasher built by an arbitrary [`CMBuildHasher`] that has already absorbed arbitrary bytes,
+/// so fuzzers can start from the middle of a stream as a restored snapshot would
+#[cfg(feature = "arbitrary")]
+impl<'a, M: Mixer + Clone + arbitrary::Arbitrary<'a>> arbitrary::Arbitrary<'a> for CMHasher<M> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut hasher = CMBuildHasher::<M>::arbitrary(u)?.build_hasher();
+        hasher.write(u.arbitrary()?);
+        Ok(hasher)
     }
 }
 
-impl BuildHasher for CMBuildHasher {
-    type Hasher = CMHasher;
+impl<M: Mixer + Clone> BuildHasher for CMBuildHasher<M> {
+    type Hasher = CMHasher<M>;
 
     fn build_hasher(&self) -> Self::Hasher {
-        CMHasher::with_state(self.state)
+        CMHasher::configured(self.state, self.mixer.clone(), self.prime, self.portable)
+            .with_strategy(self.strategy)
+    }
+}
+
+impl<M: Mixer + Default> CMBuildHasher<M> {
+    /// Returns a [`CMBuildHasher`] with the default state, hashing identically in every instance
+    /// and every process.
+    ///
+    /// This is what [`Default`] returned before it switched to [`Self::unique`].
+    pub fn deterministic() -> Self {
+        Self {
+            state: DEFAULT_HASHER_STATE,
+            mixer: M::default(),
+            prime: DEFAULT_PRIME,
+            portable: false,
+            strategy: Strategy::Multiply,
+        }
+    }
+
+    /// Returns a [`CMBuildHasher`] whose state differs from that of every other builder
+    /// returned by this function in the process.
+    ///
+    /// The state is the next value of a process-global counter passed through
+    /// [`hash_word_stateless`](crate::hash_word_stateless), so tables built with different
+    /// builders don't share collision patterns. This needs no source of entropy and works
+    /// without `std`, but the states are unique, not secret: they are the same in every run, and
+    /// anyone who can guess how many builders were created before can reproduce them. Clones
+    /// keep the state of the builder they were cloned from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::hash::BuildHasher;
+    /// use cmhash::CMBuildHasher;
+    ///
+    /// let a: CMBuildHasher = CMBuildHasher::unique();
+    /// let b: CMBuildHasher = CMBuildHasher::unique();
+    /// assert_ne!(a.hash_one(42u64), b.hash_one(42u64));
+    /// assert_eq!(a.hash_one(42u64), a.clone().hash_one(42u64));
+    /// ```
+    pub fn unique() -> Self {
+        let n = UNIQUE_BUILDERS.fetch_add(1, Ordering::Relaxed);
+        Self {
+            state: crate::hash_word_stateless(n) as u64,
+            ..Self::deterministic()
+        }
+    }
+}
+
+/// The number of builders [`CMBuildHasher::unique`] has returned
+static UNIQUE_BUILDERS: AtomicUsize = AtomicUsize::new(0);
+
+impl<M: Mixer + Default> Default for CMBuildHasher<M> {
+    /// Returns [`CMBuildHasher::unique`]
+    fn default() -> Self {
+        Self::unique()
+    }
+}
+
+/// A [`CMHasher`] whose state is fixed at compile time
+///
+/// This hashes exactly as `CMHasher::with_state(SEED)` does, but needs no value to construct:
+/// [`Default`] is enough, so the seed can't be mixed up with another subsystem's at runtime. The
+/// hasher itself still carries the state of a [`CMHasher`]; it is [`BuildHasherSeeded`] that
+/// takes no space.
+#[derive(Debug)]
+pub struct CMHasherSeeded<const SEED: u64>(CMHasher);
+
+impl<const SEED: u64> CMHasherSeeded<SEED> {
+    /// Creates a [`CMHasherSeeded`]
+    pub fn new() -> Self {
+        Self(CMHasher::with_state(SEED))
+    }
+
+    /// Returns the seed
+    pub const fn seed() -> u64 {
+        SEED
     }
 }
 
-impl Default for CMBuildHasher {
+impl<const SEED: u64> Default for CMHasherSeeded<SEED> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl<const SEED: u64> Hasher for CMHasherSeeded<SEED> {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i)
+    }
+}
+
+/// A zero-sized [`BuildHasher`] yielding [`CMHasherSeeded`]s, which hashes exactly as
+/// `CMBuildHasher::with_state(SEED)` does
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use cmhash::BuildHasherSeeded;
+///
+/// type SessionMap = HashMap<u64, &'static str, BuildHasherSeeded<0xC0FFEE>>;
+///
+/// let mut sessions = SessionMap::default();
+/// sessions.insert(42, "alice");
+/// assert_eq!(sessions[&42], "alice");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BuildHasherSeeded<const SEED: u64>;
+
+impl<const SEED: u64> BuildHasherSeeded<SEED> {
+    /// Returns the seed
+    pub const fn seed() -> u64 {
+        SEED
+    }
+}
+
+impl<const SEED: u64> BuildHasher for BuildHasherSeeded<SEED> {
+    type Hasher = CMHasherSeeded<SEED>;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        CMHasherSeeded::new()
+    }
+}
+
 /// A [`Hasher`] that does not have a persistent internal state for fully deterministic hashing
 #[derive(Debug, Default)]
 pub struct StatelessHasher {
     data: Cell<u64>,
+    open: Cell<Option<OpenWrite>>,
 }
 
 impl StatelessHasher {
     ///Creates a new [`StatelessHasher`]
     pub fn new() -> Self {
-        Self { data: Cell::new(0) }
+        Self {
+            data: Cell::new(0),
+            open: Cell::new(None),
+        }
+    }
+
+    /// Appends `bytes` to a write left open across calls, which the hasher's next write or
+    /// [`Hasher::finish`] completes exactly as one [`Hasher::write`] of everything appended
+    pub(crate) fn append(&mut self, bytes: &[u8]) {
+        let mut open = self.open.take().unwrap_or(OpenWrite::new(0));
+        open.words.push(bytes, |word| {
+            open.data ^= self.hash(u64::from_ne_bytes(word))
+        });
+        self.open.set(Some(open));
+    }
+
+    /// Completes the write left open by [`Self::append`], if there is one
+    fn close(&self) {
+        if let Some(open) = self.open.take() {
+            let last = self.hash(u64::from_ne_bytes(open.words.finish()));
+            self.data.set(open.data ^ last);
+        }
     }
 
     fn hash(&self, val: u64) -> u64 {
-        let (hash, state) = val.widening_mul((2 << 61) - 1);
+        let (hash, state) = raw::round_u64(0, val, DEFAULT_PRIME);
         hash ^ state
     }
 }
 
 impl Hasher for StatelessHasher {
     fn finish(&self) -> u64 {
+        self.close();
         self.data.replace(0)
     }
 
     fn write(&mut self, bytes: &[u8]) {
+        self.close();
         let chunks = bytes.array_chunks::<8>();
         let rem = {
             let mut r = chunks.remainder().iter();
@@ -127,12 +656,35 @@ impl Hasher for StatelessHasher {
     }
 
     fn write_u64(&mut self, i: u64) {
+        self.close();
         self.data.set(self.hash(i));
     }
 }
 
+impl Extend<u8> for StatelessHasher {
+    /// Hashes the bytes produced by `iter` exactly as if they were passed to [`Hasher::write`] as one slice
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        self.close();
+        self.data.set(
+            Words::new(iter.into_iter())
+                .fold(0, |val, next| val ^ self.hash(u64::from_ne_bytes(next))),
+        );
+    }
+}
+
+impl<'a> Extend<&'a u8> for StatelessHasher {
+    fn extend<I: IntoIterator<Item = &'a u8>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
 /// A [`BuildHasher`] that yields a [`StatelessHasher`]
+///
+/// Its hashes share the weaknesses of an unfinalized [`CMBuildHasher`] as table keys: strings
+/// with a common prefix, and keys hashed by several writes such as `str`, pile into a few
+/// buckets.
 #[derive(Debug)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct StatelessBuildHasher;
 
 impl BuildHasher for StatelessBuildHasher {
@@ -142,3 +694,146 @@ impl BuildHasher for StatelessBuildHasher {
         StatelessHasher::new()
     }
 }
+
+/// Packs a stream of bytes into words the same way [`Hasher::write`] splits a slice, including
+/// the zero-padded final word, so bytes arriving in fragments produce the same words
+pub(crate) struct Words<I> {
+    bytes: I,
+    done: bool,
+}
+
+impl<I: Iterator<Item = u8>> Words<I> {
+    pub(crate) fn new(bytes: I) -> Self {
+        Self { bytes, done: false }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Words<I> {
+    type Item = [u8; 8];
+
+    fn next(&mut self) -> Option<[u8; 8]> {
+        if self.done {
+            return None;
+        }
+        let mut word = [0u8; 8];
+        for slot in word.iter_mut() {
+            match self.bytes.next() {
+                Some(b) => *slot = b,
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        Some(word)
+    }
+}
+
+/// Splits bytes arriving in several parts into words exactly as a single contiguous slice would be
+/// split, carrying partial words across the boundaries between parts
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WordBuffer<const N: usize> {
+    buf: [u8; N],
+    fill: usize,
+}
+
+impl<const N: usize> WordBuffer<N> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            fill: 0,
+        }
+    }
+
+    /// Calls `f` with every word completed by `part`, buffering any trailing partial word
+    pub(crate) fn push(&mut self, mut part: &[u8], mut f: impl FnMut([u8; N])) {
+        if self.fill > 0 {
+            let take = (N - self.fill).min(part.len());
+            self.buf[self.fill..self.fill + take].copy_from_slice(&part[..take]);
+            self.fill += take;
+            part = &part[take..];
+            if self.fill < N {
+                return;
+            }
+            f(self.buf);
+        }
+        let chunks = part.array_chunks::<N>();
+        let rem = chunks.remainder();
+        chunks.for_each(|c| f(*c));
+        self.buf[..rem.len()].copy_from_slice(rem);
+        self.fill = rem.len();
+    }
+
+    /// Returns the final word, zero-padded, which is always emitted even when it holds no bytes
+    pub(crate) fn finish(mut self) -> [u8; N] {
+        self.buf[self.fill..].fill(0);
+        self.buf
+    }
+}
+
+/// Calls `f` with each word of the concatenation of `parts`, split and zero-padded exactly as a
+/// single contiguous slice would be
+pub(crate) fn for_each_word<'a, const N: usize>(
+    parts: impl IntoIterator<Item = &'a [u8]>,
+    mut f: impl FnMut([u8; N]),
+) {
+    let mut words = WordBuffer::new();
+    for part in parts {
+        words.push(part, &mut f);
+    }
+    f(words.finish());
+}
+
+/// A write whose bytes arrive over several calls: the output of its completed words, and the
+/// bytes of the word in progress
+#[derive(Debug, Clone, Copy)]
+struct OpenWrite {
+    data: u64,
+    words: WordBuffer<8>,
+}
+
+impl OpenWrite {
+    fn new(data: u64) -> Self {
+        Self {
+            data,
+            words: WordBuffer::new(),
+        }
+    }
+}
+
+/// A single logical [`Hasher::write`] to a [`CMHasher`] whose bytes arrive in several parts
+#[cfg(any(feature = "std", feature = "bytes", feature = "unicode"))]
+pub(crate) struct StreamingWrite<'a, M> {
+    hasher: &'a CMHasher<M>,
+    data: u64,
+    words: WordBuffer<8>,
+}
+
+#[cfg(any(feature = "std", feature = "bytes", feature = "unicode"))]
+impl<M: Mixer> StreamingWrite<'_, M> {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        let Self {
+            hasher,
+            data,
+            words,
+        } = self;
+        words.push(bytes, |word| *data ^= hasher.hash(hasher.load(word)));
+    }
+
+    /// Completes the write, leaving the hasher exactly as one write of all the parts would
+    pub(crate) fn finish(self) {
+        let last = self.hasher.hash(self.hasher.load(self.words.finish()));
+        self.hasher.data.set(self.data ^ last);
+    }
+}
+
+/// The 64-bit finalizer from MurmurHash3, used to spread the entropy of a finished hash across
+/// every output bit before it is reduced to a range
+#[inline]
+pub(crate) const fn fmix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    h ^ (h >> 33)
+}
@@ -0,0 +1,144 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::num::NonZeroUsize;
+
+use crate::output::{hash_bytes, hash_u64};
+
+/// How a set of keys spreads across buckets, as computed by [`bucket_histogram`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl Histogram {
+    fn new(n_buckets: NonZeroUsize, buckets: impl Iterator<Item = usize>) -> Self {
+        let mut counts = vec![0; n_buckets.get()];
+        let mut total = 0;
+        for bucket in buckets {
+            counts[bucket] += 1;
+            total += 1;
+        }
+        Self { counts, total }
+    }
+
+    /// Wraps loads that were already counted
+    pub(crate) fn from_counts(counts: Vec<u64>) -> Self {
+        let total = counts.iter().sum();
+        Self { counts, total }
+    }
+
+    /// Returns the number of keys in each bucket
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the total number of keys
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the number of keys in the lightest bucket
+    pub fn min_load(&self) -> u64 {
+        self.counts.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Returns the number of keys in the heaviest bucket
+    pub fn max_load(&self) -> u64 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Returns the mean number of keys per bucket
+    pub fn mean_load(&self) -> f64 {
+        self.total as f64 / self.counts.len() as f64
+    }
+
+    /// Returns the fraction of all keys that landed in the heaviest bucket, or `0.0` if there
+    /// are no keys
+    pub fn max_share(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.max_load() as f64 / self.total as f64
+    }
+
+    /// Returns the variance of the bucket loads
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean_load();
+        self.counts
+            .iter()
+            .map(|&c| (c as f64 - mean) * (c as f64 - mean))
+            .sum::<f64>()
+            / self.counts.len() as f64
+    }
+
+    /// Returns the standard deviation of the bucket loads
+    #[cfg(feature = "std")]
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the chi-square statistic of the loads against a uniform distribution
+    ///
+    /// For uniformly distributed keys this is close to the number of buckets minus one; values
+    /// much larger indicate skew. It is `0.0` if there are no keys.
+    pub fn chi_square(&self) -> f64 {
+        let mean = self.mean_load();
+        if mean == 0.0 {
+            return 0.0;
+        }
+        self.counts
+            .iter()
+            .map(|&c| (c as f64 - mean) * (c as f64 - mean) / mean)
+            .sum()
+    }
+
+    /// Returns the indices of the `n` heaviest buckets, heaviest first, with ties broken by index
+    pub fn heaviest(&self, n: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.counts.len()).collect();
+        indices.sort_by_key(|&i| (core::cmp::Reverse(self.counts[i]), i));
+        indices.truncate(n);
+        indices
+    }
+}
+
+/// Counts how `keys` spread across `n_buckets` buckets when hashed with `seed`.
+///
+/// Each key goes to the bucket [`hash_bytes`] with `seed` and [`HashOutput::bucket`] assign it,
+/// so the histogram shows exactly how a shard count would be loaded.
+///
+/// [`HashOutput::bucket`]: crate::HashOutput::bucket
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use cmhash::bucket_histogram;
+///
+/// let keys = (0..10_000).map(|i| format!("user:{i}"));
+/// let histogram = bucket_histogram(keys, NonZeroUsize::new(16).unwrap(), 7);
+/// assert_eq!(histogram.total(), 10_000);
+/// assert!(histogram.max_load() < 700);
+/// ```
+pub fn bucket_histogram<I>(keys: I, n_buckets: NonZeroUsize, seed: u64) -> Histogram
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let buckets = keys
+        .into_iter()
+        .map(|key| hash_bytes(key.as_ref(), seed).bucket(n_buckets.get()));
+    Histogram::new(n_buckets, buckets)
+}
+
+/// Counts how the word sized `keys` spread across `n_buckets` buckets when hashed with `seed`,
+/// using [`hash_u64`] in place of [`hash_bytes`].
+pub fn bucket_histogram_words<I>(keys: I, n_buckets: NonZeroUsize, seed: u64) -> Histogram
+where
+    I: IntoIterator<Item = u64>,
+{
+    let buckets = keys
+        .into_iter()
+        .map(|key| hash_u64(key, seed).bucket(n_buckets.get()));
+    Histogram::new(n_buckets, buckets)
+}
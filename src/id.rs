@@ -0,0 +1,164 @@
+use core::fmt;
+
+use crate::output::hash_bytes;
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Why a string couldn't be parsed as a [`ShortId`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseIdError {
+    /// The string isn't the fixed length of the encoding
+    Length,
+    /// The byte at this index isn't a digit of the encoding
+    Digit(usize),
+    /// The digits encode a number larger than `u64::MAX`
+    Overflow,
+}
+
+impl fmt::Display for ParseIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length => f.write_str("identifier has the wrong length"),
+            Self::Digit(i) => write!(f, "invalid digit at index {i}"),
+            Self::Overflow => f.write_str("identifier is out of range"),
+        }
+    }
+}
+
+/// A compact identifier derived from a hash, returned by [`short_id`].
+///
+/// It formats into a caller-provided buffer, without allocating, in any of three fixed-length
+/// encodings, each zero-padded so that every identifier in an encoding has the same length:
+///
+/// | Encoding | Length | Alphabet |
+/// |---|---|---|
+/// | Hex | 16 | `0-9a-f` |
+/// | Crockford base32 | 13 | `0-9A-Z` without `I`, `L`, `O` and `U` |
+/// | Base62 | 11 | `0-9A-Za-z` |
+///
+/// Parsing accepts exactly what formatting produces, except that hex and base32 are
+/// case-insensitive, and base32 reads `I` and `L` as `1` and `O` as `0`, as Crockford's
+/// encoding specifies, so that identifiers copied by hand still parse. [`Display`](fmt::Display)
+/// writes the base32 form.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{short_id, ShortId};
+///
+/// let id = short_id(b"report-2024.pdf", 0);
+/// let mut buf = [0; ShortId::BASE32_LEN];
+/// let text = id.to_base32(&mut buf);
+/// assert_eq!(ShortId::parse_base32(text), Ok(id));
+/// assert_eq!(ShortId::parse_base32(&text.to_lowercase()), Ok(id));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ShortId(pub u64);
+
+/// Returns the [`ShortId`] of `bytes`, the [`hash_bytes`] of them under `seed`
+pub fn short_id(bytes: &[u8], seed: u64) -> ShortId {
+    ShortId(hash_bytes(bytes, seed).0)
+}
+
+impl ShortId {
+    /// The length of the hex encoding
+    pub const HEX_LEN: usize = 16;
+    /// The length of the Crockford base32 encoding
+    pub const BASE32_LEN: usize = 13;
+    /// The length of the base62 encoding
+    pub const BASE62_LEN: usize = 11;
+
+    /// Writes the identifier into `buf` as lowercase hex, returning it as a string
+    pub fn to_hex(self, buf: &mut [u8; Self::HEX_LEN]) -> &str {
+        encode(self.0, HEX, buf)
+    }
+
+    /// Writes the identifier into `buf` in Crockford base32, returning it as a string
+    pub fn to_base32(self, buf: &mut [u8; Self::BASE32_LEN]) -> &str {
+        encode(self.0, CROCKFORD, buf)
+    }
+
+    /// Writes the identifier into `buf` in base62, returning it as a string
+    pub fn to_base62(self, buf: &mut [u8; Self::BASE62_LEN]) -> &str {
+        encode(self.0, BASE62, buf)
+    }
+
+    /// Parses an identifier written by [`Self::to_hex`], in either case
+    pub fn parse_hex(s: &str) -> Result<Self, ParseIdError> {
+        decode(s, Self::HEX_LEN, 16, |b| match b {
+            b'0'..=b'9' => Some(b - b'0'),
+            b'a'..=b'f' => Some(b - b'a' + 10),
+            b'A'..=b'F' => Some(b - b'A' + 10),
+            _ => None,
+        })
+    }
+
+    /// Parses an identifier written by [`Self::to_base32`], in either case and with `I` and `L`
+    /// read as `1` and `O` as `0`
+    pub fn parse_base32(s: &str) -> Result<Self, ParseIdError> {
+        decode(s, Self::BASE32_LEN, 32, |b| match b.to_ascii_uppercase() {
+            b'O' => Some(0),
+            b'I' | b'L' => Some(1),
+            b'U' => None,
+            upper => CROCKFORD
+                .iter()
+                .position(|&digit| digit == upper)
+                .map(|d| d as u8),
+        })
+    }
+
+    /// Parses an identifier written by [`Self::to_base62`]
+    pub fn parse_base62(s: &str) -> Result<Self, ParseIdError> {
+        decode(s, Self::BASE62_LEN, 62, |b| match b {
+            b'0'..=b'9' => Some(b - b'0'),
+            b'A'..=b'Z' => Some(b - b'A' + 10),
+            b'a'..=b'z' => Some(b - b'a' + 36),
+            _ => None,
+        })
+    }
+}
+
+impl From<ShortId> for u64 {
+    fn from(id: ShortId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for ShortId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_base32(&mut [0; Self::BASE32_LEN]))
+    }
+}
+
+/// Writes `value` into `buf` in the base of `alphabet`, most significant digit first
+fn encode<'a>(mut value: u64, alphabet: &[u8], buf: &'a mut [u8]) -> &'a str {
+    let base = alphabet.len() as u64;
+    for digit in buf.iter_mut().rev() {
+        *digit = alphabet[(value % base) as usize];
+        value /= base;
+    }
+    core::str::from_utf8(buf).expect("every alphabet is ASCII")
+}
+
+/// Parses exactly `len` digits of `base`, most significant first
+fn decode(
+    s: &str,
+    len: usize,
+    base: u64,
+    digit: impl Fn(u8) -> Option<u8>,
+) -> Result<ShortId, ParseIdError> {
+    if s.len() != len {
+        return Err(ParseIdError::Length);
+    }
+    s.bytes()
+        .enumerate()
+        .try_fold(ShortId(0), |ShortId(acc), (i, b)| {
+            let d = digit(b).ok_or(ParseIdError::Digit(i))?;
+            acc.checked_mul(base)
+                .and_then(|acc| acc.checked_add(d as u64))
+                .map(ShortId)
+                .ok_or(ParseIdError::Overflow)
+        })
+}
@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+use serde_json::{Number, Value};
+
+use crate::output::{hash_bytes, hash_combine};
+
+// Mixed in first for each kind of value, so that values of different kinds never share a
+// construction
+const NULL: u64 = 1;
+const FALSE: u64 = 2;
+const TRUE: u64 = 3;
+const UNSIGNED: u64 = 4;
+const NEGATIVE: u64 = 5;
+const FLOAT: u64 = 6;
+const STRING: u64 = 7;
+const ARRAY: u64 = 8;
+const OBJECT: u64 = 9;
+
+/// Hashes a JSON document canonically under `seed`, so that documents with the same content
+/// hash equal however their objects' keys are ordered and however they were formatted.
+///
+/// With `tag(t)` standing for `hash_combine(seed, t)`, each value hashes as:
+///
+/// - `null`, `false` and `true`: `tag(1)`, `tag(2)` and `tag(3)`.
+/// - Numbers: integers `n >= 0` as `hash_combine(tag(4), n)`, negative integers as
+///   `hash_combine(tag(5), n as u64)`, and other numbers as `hash_combine(tag(6), bits)` with
+///   `bits` those of the `f64`. A float with no fractional part that fits in an `i64` or a `u64`
+///   counts as that integer, so `1.0`, `1` and `1e0` hash equal, as do `-0.0` and `0`.
+/// - Strings: `hash_combine(tag(7), hash_combine(hash_bytes(s, seed), len))`, which mixes in
+///   the length so that no string is a prefix-padded form of another.
+/// - Arrays: starting from `tag(8)`, each element's hash is folded in with [`hash_combine`] in
+///   order, then the length.
+/// - Objects: starting from `tag(9)`, each entry in order of its key's bytes folds in the hash
+///   of its key as a string and then that of its value, then the number of entries is folded
+///   in.
+///
+/// Nested values are walked with an explicit stack rather than by recursion, so however deeply
+/// a document nests, hashing it can't overflow the call stack.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_json;
+/// use serde_json::json;
+///
+/// let a = json!({ "name": "cmhash", "tags": ["hash", "no_std"], "stars": 3 });
+/// let b = json!({ "stars": 3.0, "tags": ["hash", "no_std"], "name": "cmhash" });
+/// assert_eq!(hash_json(&a, 0), hash_json(&b, 0));
+/// assert_ne!(hash_json(&json!(1), 0), hash_json(&json!("1"), 0));
+/// ```
+pub fn hash_json(value: &Value, seed: u64) -> u64 {
+    let mut stack: Vec<Frame<'_>> = Vec::new();
+    let mut finished = match enter(value, seed) {
+        Ok(hash) => return hash,
+        Err(frame) => {
+            stack.push(frame);
+            None
+        }
+    };
+    loop {
+        let frame = stack
+            .last_mut()
+            .expect("the stack holds the unfinished containers");
+        if let Some(hash) = finished.take() {
+            frame.acc = hash_combine(frame.acc, hash);
+        }
+        match frame.next_child(seed) {
+            Some(child) => match enter(child, seed) {
+                Ok(hash) => finished = Some(hash),
+                Err(frame) => stack.push(frame),
+            },
+            None => {
+                let hash = hash_combine(frame.acc, frame.len);
+                stack.pop();
+                if stack.is_empty() {
+                    return hash;
+                }
+                finished = Some(hash);
+            }
+        }
+    }
+}
+
+/// An array or object whose children are being hashed
+struct Frame<'a> {
+    acc: u64,
+    len: u64,
+    children: Children<'a>,
+}
+
+enum Children<'a> {
+    Array(core::slice::Iter<'a, Value>),
+    Object(alloc::vec::IntoIter<(&'a str, &'a Value)>),
+}
+
+impl<'a> Frame<'a> {
+    /// Returns the next child to hash, first folding in its key if it is an object entry
+    fn next_child(&mut self, seed: u64) -> Option<&'a Value> {
+        match &mut self.children {
+            Children::Array(items) => items.next(),
+            Children::Object(entries) => {
+                let (key, value) = entries.next()?;
+                self.acc = hash_combine(self.acc, hash_string(key, seed));
+                Some(value)
+            }
+        }
+    }
+}
+
+/// Hashes a scalar, or starts the frame for a container
+fn enter(value: &Value, seed: u64) -> Result<u64, Frame<'_>> {
+    let tag = |t| hash_combine(seed, t);
+    match value {
+        Value::Null => Ok(tag(NULL)),
+        Value::Bool(false) => Ok(tag(FALSE)),
+        Value::Bool(true) => Ok(tag(TRUE)),
+        Value::Number(n) => {
+            let (t, word) = canonical_number(n);
+            Ok(hash_combine(tag(t), word))
+        }
+        Value::String(s) => Ok(hash_string(s, seed)),
+        Value::Array(items) => Err(Frame {
+            acc: tag(ARRAY),
+            len: items.len() as u64,
+            children: Children::Array(items.iter()),
+        }),
+        Value::Object(map) => {
+            let mut entries: Vec<(&str, &Value)> =
+                map.iter().map(|(k, v)| (k.as_str(), v)).collect();
+            entries.sort_unstable_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            Err(Frame {
+                acc: tag(OBJECT),
+                len: entries.len() as u64,
+                children: Children::Object(entries.into_iter()),
+            })
+        }
+    }
+}
+
+fn hash_string(s: &str, seed: u64) -> u64 {
+    let content = hash_combine(hash_bytes(s.as_bytes(), seed).0, s.len() as u64);
+    hash_combine(hash_combine(seed, STRING), content)
+}
+
+/// Returns the tag and word a number is hashed as
+fn canonical_number(n: &Number) -> (u64, u64) {
+    if let Some(u) = n.as_u64() {
+        return (UNSIGNED, u);
+    }
+    if let Some(i) = n.as_i64() {
+        return (NEGATIVE, i as u64);
+    }
+    let f = n.as_f64().expect("a JSON number is an integer or a float");
+    // Both bounds are powers of two, so they are exact, and a cast back that gives the same
+    // float means there was no fractional part
+    if (0.0..18_446_744_073_709_551_616.0).contains(&f) && f as u64 as f64 == f {
+        return (UNSIGNED, f as u64);
+    }
+    if (-9_223_372_036_854_775_808.0..0.0).contains(&f) && f as i64 as f64 == f {
+        return (NEGATIVE, f as i64 as u64);
+    }
+    (FLOAT, f.to_bits())
+}
@@ -0,0 +1,158 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hasher};
+
+use crate::hasher::{fmix64, WordBuffer, DEFAULT_HASHER_STATE};
+use crate::word;
+
+/// Expands a 256-bit key into the four per-position constants used by [`KeyedHasher`].
+///
+/// The key's four little-endian words are absorbed through the round function, the result is
+/// finalized into a seed, and constant `i` is the finalized first round of `i` under that seed,
+/// so every constant depends on every bit of the key.
+fn key_schedule(key: &[u8; 32]) -> [u64; 4] {
+    let mut state = DEFAULT_HASHER_STATE;
+    let mut digest = 0;
+    for chunk in key.array_chunks::<8>() {
+        let (hash, next) = word::round(state, u64::from_le_bytes(*chunk));
+        digest ^= hash;
+        state = next;
+    }
+    seed_schedule(fmix64(digest ^ state))
+}
+
+/// The four per-position constants for a single-word seed: constant `i` is the finalized first
+/// round of `i` under `seed`
+fn seed_schedule(seed: u64) -> [u64; 4] {
+    [0, 1, 2, 3].map(|i| fmix64(word::round(seed, i).0))
+}
+
+/// A [`Hasher`] keyed with 256 bits, for deployments that want genuinely different hash
+/// functions rather than a different single-word seed.
+///
+/// The key is expanded into four word constants, and each input word is xored with the constant
+/// for its position (cycling through the four) before being fed through the round function.
+/// Writes are buffered to whole words, so splitting a write into several smaller ones does not
+/// change the result, and [`finish`](Hasher::finish) may be called repeatedly.
+///
+/// This is **not** a MAC: it makes collisions harder to predict for someone who doesn't know the
+/// key, but offers no cryptographic guarantees.
+///
+/// # Examples
+///
+/// ```
+/// use core::hash::Hasher;
+/// use cmhash::KeyedHasher;
+///
+/// let key = [7u8; 32];
+/// let mut a = KeyedHasher::new(&key);
+/// a.write(b"Hello, World!");
+/// let mut b = KeyedHasher::new(&key);
+/// b.write(b"Hello, ");
+/// b.write(b"World!");
+/// assert_eq!(a.finish(), b.finish());
+/// ```
+#[derive(Clone)]
+pub struct KeyedHasher {
+    keys: [u64; 4],
+    state: u64,
+    acc: u64,
+    words: u64,
+    len: u64,
+    pending: WordBuffer<8>,
+}
+
+impl KeyedHasher {
+    /// Creates a new [`KeyedHasher`] from a 256-bit key
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self::with_schedule(key_schedule(key))
+    }
+
+    /// Creates a [`KeyedHasher`] from a single-word seed, skipping the absorption of a full key
+    pub(crate) fn from_seed(seed: u64) -> Self {
+        Self::with_schedule(seed_schedule(seed))
+    }
+
+    /// Discards everything written, keeping the key
+    pub(crate) fn reset(&mut self) {
+        *self = Self::with_schedule(self.keys);
+    }
+
+    fn with_schedule(keys: [u64; 4]) -> Self {
+        Self {
+            keys,
+            state: DEFAULT_HASHER_STATE,
+            acc: 0,
+            words: 0,
+            len: 0,
+            pending: WordBuffer::new(),
+        }
+    }
+}
+
+/// Shows only how many bytes were written: the key schedule and the state derived from it would
+/// reveal the key
+impl fmt::Debug for KeyedHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedHasher")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hasher for KeyedHasher {
+    fn finish(&self) -> u64 {
+        let last = u64::from_ne_bytes(self.pending.finish());
+        let (hash, state) = word::round(self.state, last ^ self.keys[(self.words % 4) as usize]);
+        let (len_hash, state) = word::round(state, self.len);
+        fmix64(self.acc ^ hash ^ len_hash ^ state)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let Self {
+            keys,
+            state,
+            acc,
+            words,
+            pending,
+            ..
+        } = self;
+        pending.push(bytes, |w| {
+            let (hash, next) =
+                word::round(*state, u64::from_ne_bytes(w) ^ keys[(*words % 4) as usize]);
+            *acc ^= hash;
+            *state = next;
+            *words += 1;
+        });
+        self.len = self.len.wrapping_add(bytes.len() as u64);
+    }
+}
+
+/// A [`BuildHasher`] that yields [`KeyedHasher`]s sharing one 256-bit key
+#[derive(Clone)]
+pub struct KeyedBuildHasher {
+    keys: [u64; 4],
+}
+
+impl KeyedBuildHasher {
+    /// Returns a [`KeyedBuildHasher`] for the provided key
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            keys: key_schedule(key),
+        }
+    }
+}
+
+/// Hides the key schedule, which would reveal the key
+impl fmt::Debug for KeyedBuildHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedBuildHasher").finish_non_exhaustive()
+    }
+}
+
+impl BuildHasher for KeyedBuildHasher {
+    type Hasher = KeyedHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        KeyedHasher::with_schedule(self.keys)
+    }
+}
@@ -1,4 +1,4 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![deny(missing_docs, missing_debug_implementations)]
 #![feature(bigint_helper_methods, array_chunks)]
 
@@ -8,28 +8,378 @@
 //!
 //! Note: This is not a cryptographically secure hashing algorithm and is primarily meant for use in sharding and hash tables
 
-#[cfg(not(loom))]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+// Lets the macros' `::cmhash` paths resolve in this crate's own tests
+#[cfg(all(test, any(feature = "derive", feature = "macros")))]
+extern crate self as cmhash;
+
+#[cfg(all(not(loom), not(shuttle), not(feature = "portable-atomic")))]
 use core::sync::atomic::AtomicUsize;
 
+// Lets critical-section or single-core backends supply the atomics on targets without CAS
+#[cfg(all(not(loom), not(shuttle), feature = "portable-atomic"))]
+use portable_atomic::AtomicUsize;
+
 #[cfg(loom)]
 use loom::sync::atomic::AtomicUsize;
 
+// Lets shuttle switch threads at every atomic access inside a call, not only between calls
+#[cfg(all(shuttle, not(loom)))]
+use shuttle::sync::atomic::AtomicUsize;
+
 use core::cell::Cell;
 use core::sync::atomic::Ordering;
 
+use crate::hasher::{fmix64, for_each_word};
+
 #[cfg(test)]
 mod test;
 
+mod word;
+
+#[cfg(feature = "rkyv")]
+mod archive;
+
 /// Implementations of `Hasher` and `BuildHasher` using fast Mersenne hashing
 pub mod hasher;
 pub use crate::hasher::*;
 
-// The largest Mersenne Prime that can fit in one word of the target
+/// A bottom-k sketch for sampling distinct keys and estimating similarity
+pub mod bottom_k;
+pub use crate::bottom_k::*;
+
+/// A single entry point for configuring [`CMHasher`] and friends
+pub mod builder;
+pub use crate::builder::*;
+
+/// Hashers whose hash function is chosen by a domain tag
+pub mod domain;
+pub use crate::domain::*;
+
+/// Hashing structured values field by field
+pub mod fields;
+pub use crate::fields::*;
+
+/// Derives [`CmHash`] for structs and enums, feeding each field in declaration order. Fields
+/// marked `#[cmhash(skip)]` are left out.
+#[cfg(feature = "derive")]
+pub use cmhash_derive::CmHash;
+
+/// Bounded caches of computed values, for memoization
+#[cfg(feature = "std")]
+pub mod memo;
+#[cfg(feature = "std")]
+pub use crate::memo::*;
+
+/// Memoizes a function in a bounded [`MemoCache`], keyed by the tuple of its arguments
+///
+/// `#[memoize(capacity = N)]` gives each thread its own cache of at most `N` entries, and
+/// `#[memoize(capacity = N, shared)]` gives all threads one cache behind a mutex.
+/// `hasher = Type` hashes the keys with `Type::default()` instead of a [`CMBuildHasher`].
+///
+/// The arguments must be `Hash + Eq + Clone + 'static`, and taken by value, and the return
+/// type `Clone`; with `shared`, both must also be `Send`. A hit returns a clone of the cached
+/// value after comparing the arguments in full, so arguments whose hashes collide never get
+/// each other's results. Nothing is locked or borrowed while the function body runs, so a
+/// memoized function may call itself. Generic functions, methods, async functions and
+/// borrowed arguments are rejected at compile time, as a cache in a static can't hold them.
+///
+/// # Examples
+///
+/// ```
+/// #[cmhash::memoize(capacity = 128)]
+/// fn fib(n: u64) -> u64 {
+///     if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+/// }
+///
+/// assert_eq!(fib(90), 2_880_067_194_370_816_120);
+/// ```
+#[cfg(feature = "macros")]
+pub use cmhash_derive::memoize;
+
+/// Hashing any serde-serializable value by its structure
+#[cfg(feature = "serde")]
+pub mod serialize;
+#[cfg(feature = "serde")]
+pub use crate::serialize::*;
+
+/// Finalizers that can be applied to the output of [`CMHasher`]
+pub mod mixer;
+pub use crate::mixer::*;
+
+/// A [`Hasher`](core::hash::Hasher) keyed with 256 bits
+pub mod keyed;
+pub use crate::keyed::*;
+
+/// A digest-style interface to the hashers
+pub mod checksum;
+pub use crate::checksum::*;
+
+/// Combining two hashers for defense in depth
+pub mod composite;
+pub use crate::composite::*;
+
+/// Probe sequences for open-addressed hash tables
+pub mod probe;
+pub use crate::probe::*;
+
+/// Deterministic streams of words derived from a seed
+pub mod sequence;
+pub use crate::sequence::*;
+
+/// Variable-length output derived from variable-length input
+pub mod sponge;
+pub use crate::sponge::*;
+
+/// Persisting hasher state as plain bytes
+pub mod snapshot;
+pub use crate::snapshot::*;
+
+/// Hashing in 16- and 32-bit words for narrow outputs
+pub mod small;
+pub use crate::small::*;
+
+/// Hashing keys in batches, optionally prefetching their buckets
+pub mod batch;
+pub use crate::batch::*;
+
+/// Checksums of fixed-size pages
+pub mod page;
+pub use crate::page::*;
+
+/// Stable tags identifying types
+pub mod type_tag;
+pub use crate::type_tag::*;
+
+/// Seeds, and parsing them from configuration
+pub mod seed;
+pub use crate::seed::*;
+
+/// One-shot hashing functions and the [`HashOutput`] they return
+pub mod output;
+pub use crate::output::*;
+
+/// Compact printable identifiers derived from hashes
+pub mod id;
+pub use crate::id::*;
+
+/// Deterministic sampling decisions derived from hashes
+pub mod sample;
+pub use crate::sample::*;
+
+/// Hashing integer grid coordinates for procedural generation
+pub mod coords;
+pub use crate::coords::*;
+
+/// Suppressing duplicates within a window of recent keys
+pub mod recent;
+pub use crate::recent::*;
+
+/// Assigning keys to shards
+pub mod shard;
+pub use crate::shard::*;
+
+/// Hashing network flows, optionally alike in both directions
+pub mod flow;
+pub use crate::flow::*;
+
+/// Control bytes and group matching for Swiss tables
+pub mod table;
+
+/// Directory arithmetic for extendible hashing
+pub mod extendible;
+
+/// The round function and constants the hashers are defined in terms of
+pub mod raw;
+
+/// Bindings for calling the portable hash from JavaScript
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+
+/// Searching for seeds that map a fixed key set to distinct buckets
+pub mod perfect;
+pub use crate::perfect::*;
+
+/// SimHash fingerprints for finding near-duplicates
+pub mod simhash;
+pub use crate::simhash::*;
+
+/// Hashing the overlapping windows of text for similarity estimation
+pub mod shingle;
+pub use crate::shingle::*;
+
+/// The hashing trick for vectorizing features
+pub mod feature;
+pub use crate::feature::*;
+
+/// A fixed-capacity set of hashes that needs no allocator
+pub mod set;
+pub use crate::set::*;
+
+/// A fixed-capacity hash map that needs no allocator
+pub mod map;
+pub use crate::map::*;
+
+/// Content-defined chunking
+pub mod cdc;
+pub use crate::cdc::*;
+
+/// Hashing trees bottom-up
+pub mod merkle;
+pub use crate::merkle::*;
+
+/// Zobrist hashing with incremental updates
+#[cfg(feature = "alloc")]
+pub mod zobrist;
+#[cfg(feature = "alloc")]
+pub use crate::zobrist::*;
+
+/// Hashing non-contiguous [`bytes::Buf`] buffers
+#[cfg(feature = "bytes")]
+pub mod buf;
+#[cfg(feature = "bytes")]
+pub use crate::buf::*;
+
+/// Canonical hashing of JSON documents
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "json")]
+pub use crate::json::*;
+
+/// Hashing strings by their Unicode normalization
+#[cfg(feature = "unicode")]
+pub mod unicode;
+#[cfg(feature = "unicode")]
+pub use crate::unicode::*;
+
+/// An LRU cache hashed with [`CMBuildHasher`]
+#[cfg(feature = "lru")]
+pub mod cache;
+#[cfg(feature = "lru")]
+pub use crate::cache::*;
+
+/// Framing messages with a trailing checksum
+pub mod frame;
+
+/// Worst-case keys for testing behavior under hash flooding
+#[cfg(feature = "testing")]
+pub mod testing;
+
+/// C ABI shims for the SMHasher quality suites
+#[cfg(feature = "smhasher")]
+pub mod smhasher;
+
+/// Fingerprinting of files and readers
+#[cfg(feature = "std")]
+pub mod fs;
+#[cfg(feature = "std")]
+pub use crate::fs::*;
+
+/// Concurrent hash maps sharded by key hash
+#[cfg(feature = "std")]
+pub mod sharded;
+#[cfg(feature = "std")]
+pub use crate::sharded::*;
+
+/// Routing a stream of items to bounded per-shard queues
+#[cfg(feature = "std")]
+pub mod router;
+#[cfg(feature = "std")]
+pub use crate::router::*;
+
+/// Deduplication of collections keyed by hash
+#[cfg(feature = "alloc")]
+pub mod dedup;
+#[cfg(feature = "alloc")]
+pub use crate::dedup::*;
+
+/// Finding the keys that collide under a hasher
+#[cfg(feature = "alloc")]
+pub mod collisions;
+#[cfg(feature = "alloc")]
+pub use crate::collisions::*;
+
+/// Minimal perfect hashing of fixed key sets
+#[cfg(feature = "alloc")]
+pub mod mph;
+#[cfg(feature = "alloc")]
+pub use crate::mph::*;
+
+/// Cuckoo hash maps with two candidate buckets per key
+#[cfg(feature = "alloc")]
+pub mod cuckoo;
+#[cfg(feature = "alloc")]
+pub use crate::cuckoo::*;
+
+/// Versioned, portable serialization of sketches
+#[cfg(feature = "alloc")]
+pub mod sketch;
+#[cfg(feature = "alloc")]
+pub use crate::sketch::*;
+
+/// Xor filters for static approximate membership
+#[cfg(feature = "alloc")]
+pub mod xor_filter;
+#[cfg(feature = "alloc")]
+pub use crate::xor_filter::*;
+
+/// Consistent hashing, optionally with bounded loads
+#[cfg(feature = "alloc")]
+pub mod ring;
+#[cfg(feature = "alloc")]
+pub use crate::ring::*;
+
+/// Analysis of how key sets spread across buckets
+#[cfg(feature = "alloc")]
+pub mod histogram;
+#[cfg(feature = "alloc")]
+pub use crate::histogram::*;
+
+/// Choosing a hash algorithm at runtime
+#[cfg(feature = "alloc")]
+pub mod dynamic;
+#[cfg(feature = "alloc")]
+pub use crate::dynamic::*;
+
+/// Planning the key movement between two shard configurations
+#[cfg(feature = "alloc")]
+pub mod rebalance;
+#[cfg(feature = "alloc")]
+pub use crate::rebalance::*;
+
+/// Reproducible sequences of distinct hasher seeds
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub mod sequenced;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use crate::sequenced::*;
+
+/// Digests of buffers written concurrently by region
+#[cfg(all(
+    feature = "alloc",
+    any(feature = "portable-atomic", target_has_atomic = "64")
+))]
+pub mod concurrent;
+#[cfg(all(
+    feature = "alloc",
+    any(feature = "portable-atomic", target_has_atomic = "64")
+))]
+pub use crate::concurrent::*;
+
+/// Radix partitioning of keys by hash for hash joins
+#[cfg(feature = "alloc")]
+pub mod partition;
+#[cfg(feature = "alloc")]
+pub use crate::partition::*;
+
+// The multiplier of the word hashers. Only the 32-bit one is the largest Mersenne prime that fits
+// in a word: 2^62 - 1 and 2^14 - 1 aren't prime, but changing them would change every hash.
 #[cfg(target_pointer_width = "64")]
 const MERSENNE_PRIME: usize = (2 << 61) - 1;
 
 #[cfg(target_pointer_width = "32")]
-const MERSENNE_PRIME: usize = (2 << 31) - 1;
+const MERSENNE_PRIME: usize = (1 << 31) - 1;
 
 #[cfg(target_pointer_width = "16")]
 const MERSENNE_PRIME: usize = (2 << 13) - 1;
@@ -44,6 +394,26 @@ pub(crate) const DEFAULT_STATE: usize = 0xAAAA_AAAA;
 #[cfg(target_pointer_width = "16")]
 pub(crate) const DEFAULT_STATE: usize = 0xAAAA;
 
+// An integer twice the width of a word, for taking the full product in const contexts
+#[cfg(target_pointer_width = "64")]
+type DoubleWord = u128;
+
+#[cfg(target_pointer_width = "32")]
+type DoubleWord = u64;
+
+#[cfg(target_pointer_width = "16")]
+type DoubleWord = u32;
+
+// An odd constant near 2^w / phi, the multiplier and round constant step of `hash_word_rounds`
+#[cfg(target_pointer_width = "64")]
+const ROUND_CONSTANT: usize = 0x9E37_79B9_7F4A_7C15;
+
+#[cfg(target_pointer_width = "32")]
+const ROUND_CONSTANT: usize = 0x9E37_79B9;
+
+#[cfg(target_pointer_width = "16")]
+const ROUND_CONSTANT: usize = 0x9E37;
+
 /// A Thread-Local Core Hasher that uses Cell to minimize the cost of shared mutable state
 
 #[derive(Debug)]
@@ -75,27 +445,134 @@ impl TLCoreHasher {
         self.0.get()
     }
 
+    /// The size of the snapshot written by [`Self::to_bytes`]
+    pub const SNAPSHOT_SIZE: usize = 9;
+
+    /// Writes the state as a snapshot: a version byte, then the state as a little-endian `u64`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::TLCoreHasher;
+    ///
+    /// let hasher = TLCoreHasher::new();
+    /// hasher.hash_word(1);
+    /// let resumed = TLCoreHasher::from_bytes(hasher.to_bytes()).unwrap();
+    /// assert_eq!(resumed.hash_word(2), hasher.hash_word(2));
+    /// ```
+    pub fn to_bytes(&self) -> [u8; Self::SNAPSHOT_SIZE] {
+        snapshot::word_snapshot(self.get_state())
+    }
+
+    /// Restores a hasher from a snapshot written by [`Self::to_bytes`]
+    pub fn from_bytes(bytes: [u8; Self::SNAPSHOT_SIZE]) -> Result<Self, StateError> {
+        snapshot::word_state(bytes).map(Self::with_state)
+    }
+
     /// Quickly hash a word sized value.
     pub fn hash_word(&self, val: usize) -> usize {
-        let state = self.0.get();
-        let input = val ^ state;
-        let (hash, state) = input.widening_mul(MERSENNE_PRIME);
+        let (hash, state) = hash_word_with_state(self.0.get(), val);
         self.0.set(state);
         hash
     }
 
     /// Hashes a slice of bytes by converting to a slice of usize and repeatedly applying [`Self::hash_word`]
     pub fn hash_bytes(&self, bytes: &[u8]) -> usize {
-        const N: usize = core::mem::size_of::<usize>();
-        let chunks = bytes.array_chunks::<N>();
-        let rem = {
-            let mut r = chunks.remainder().iter();
-            usize::from_ne_bytes([0u8; N].map(|_| *r.next().unwrap_or(&0)))
-        };
-        chunks
-            .map(|c| usize::from_ne_bytes(*c))
-            .chain(core::iter::once(rem))
-            .fold(0, |val, next| val ^ self.hash_word(next))
+        let (hash, state) = hash_bytes_with_state(self.0.get(), bytes);
+        self.0.set(state);
+        hash
+    }
+
+    /// Hashes a word sized value exactly as [`Self::hash_word`] does, accessing the state
+    /// directly instead of through the [`Cell`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::TLCoreHasher;
+    ///
+    /// let mut exclusive = TLCoreHasher::new();
+    /// let shared = TLCoreHasher::new();
+    /// assert_eq!(exclusive.hash_word_mut(42), shared.hash_word(42));
+    /// assert_eq!(exclusive.hash_word(42), shared.hash_word(42));
+    /// ```
+    pub fn hash_word_mut(&mut self, val: usize) -> usize {
+        let state = self.0.get_mut();
+        let (hash, next) = hash_word_with_state(*state, val);
+        *state = next;
+        hash
+    }
+
+    /// Hashes a slice of bytes exactly as [`Self::hash_bytes`] does, accessing the state
+    /// directly instead of through the [`Cell`].
+    pub fn hash_bytes_mut(&mut self, bytes: &[u8]) -> usize {
+        let state = self.0.get_mut();
+        let (hash, next) = hash_bytes_with_state(*state, bytes);
+        *state = next;
+        hash
+    }
+
+    /// Hashes a sequence of byte slices exactly as [`Self::hash_bytes`] would hash their
+    /// concatenation, without joining them into one buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::TLCoreHasher;
+    ///
+    /// let parts: [&[u8]; 3] = [b"tenant-7/", b"orders/", b"row-1234"];
+    /// assert_eq!(
+    ///     TLCoreHasher::new().hash_bytes_vectored(&parts),
+    ///     TLCoreHasher::new().hash_bytes(b"tenant-7/orders/row-1234")
+    /// );
+    /// ```
+    pub fn hash_bytes_vectored(&self, parts: &[&[u8]]) -> usize {
+        let mut hash = 0;
+        for_each_word(parts.iter().copied(), |word| {
+            hash ^= self.hash_word(usize::from_ne_bytes(word))
+        });
+        hash
+    }
+
+    /// Derives an independent child hasher, advancing this hasher's state once.
+    ///
+    /// The child's state is the hash this hasher produces for the default state word
+    /// (`0xAAAA...` filling the word), passed through the 64-bit MurmurHash3 finalizer and
+    /// truncated to a word. Children forked in turn from the same parent start from different
+    /// states, as does the parent itself after forking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::TLCoreHasher;
+    ///
+    /// let parent = TLCoreHasher::new();
+    /// let a = parent.fork();
+    /// let b = parent.fork();
+    /// assert_ne!(a.get_state(), b.get_state());
+    /// ```
+    pub fn fork(&self) -> TLCoreHasher {
+        TLCoreHasher::with_state(fmix64(self.hash_word(DEFAULT_STATE) as u64) as usize)
+    }
+
+    /// Deterministically combines the states of this hasher and `other` into a single word.
+    ///
+    /// Equivalent to [`merge_states`]`(self.get_state(), other.get_state())`, so the order of the
+    /// two hashers matters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::TLCoreHasher;
+    ///
+    /// let root = TLCoreHasher::new();
+    /// let (left, right) = (root.fork(), root.fork());
+    /// left.hash_bytes(b"first half");
+    /// right.hash_bytes(b"second half");
+    /// assert_ne!(left.merge(&right), right.merge(&left));
+    /// ```
+    pub fn merge(&self, other: &TLCoreHasher) -> usize {
+        merge_states(self.get_state(), other.get_state())
     }
 }
 
@@ -108,6 +585,7 @@ impl Default for TLCoreHasher {
 ///A CoreHasher with support for concurrent access
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct CoreHasher(AtomicUsize);
 
 impl CoreHasher {
@@ -131,33 +609,141 @@ impl CoreHasher {
         Self(AtomicUsize::new(state))
     }
 
+    /// Views `raw` as a [`CoreHasher`], so that every hasher created from the same state shares
+    /// it, even across processes.
+    pub fn from_raw(raw: &RawCoreState) -> CoreHasherRef<'_> {
+        CoreHasherRef(&raw.0)
+    }
+
     /// Retrieve the current state.
     pub fn get_state(&self) -> usize {
         self.0.load(Ordering::Acquire)
     }
 
+    /// The size of the snapshot written by [`Self::to_bytes`]
+    pub const SNAPSHOT_SIZE: usize = TLCoreHasher::SNAPSHOT_SIZE;
+
+    /// Writes the state as a snapshot, in the same layout as [`TLCoreHasher::to_bytes`]
+    ///
+    /// This captures the state at one instant; updates made concurrently by other threads after
+    /// it is read are not included.
+    pub fn to_bytes(&self) -> [u8; Self::SNAPSHOT_SIZE] {
+        snapshot::word_snapshot(self.get_state())
+    }
+
+    /// Restores a hasher from a snapshot written by [`Self::to_bytes`]
+    pub fn from_bytes(bytes: [u8; Self::SNAPSHOT_SIZE]) -> Result<Self, StateError> {
+        snapshot::word_state(bytes).map(Self::with_state)
+    }
+
     /// Quickly hash a word sized value.
+    ///
+    /// Concurrent calls never lose an update: each one advances the state exactly once, retrying
+    /// if another thread advanced it first.
     pub fn hash_word(&self, val: usize) -> usize {
-        let state = self.0.load(Ordering::Acquire);
-        let input = val ^ state;
-        let (hash, state) = input.widening_mul(MERSENNE_PRIME);
-        self.0.store(state, Ordering::Release);
-        hash
+        let mut state = self.0.load(Ordering::Acquire);
+        loop {
+            match self.advance(state, val) {
+                Ok(hash) => return hash,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Attempts to hash a word sized value with a single compare-and-swap, returning [`None`] if
+    /// it fails, whether because another thread advanced the state first or spuriously.
+    ///
+    /// A [`None`] result leaves the state untouched, so the caller is free to retry or to hash
+    /// the value some other way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::CoreHasher;
+    ///
+    /// let hasher = CoreHasher::new();
+    /// if let Some(hash) = hasher.try_hash_word(42) {
+    ///     assert_eq!(hash, CoreHasher::new().hash_word(42));
+    /// }
+    /// ```
+    pub fn try_hash_word(&self, val: usize) -> Option<usize> {
+        self.advance(self.0.load(Ordering::Acquire), val).ok()
+    }
+
+    /// Hashes a word sized value with [`Self::try_hash_word`], calling `fallback` with the value
+    /// instead if the state is contended.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cmhash::{CoreHasher, TLCoreHasher};
+    ///
+    /// let shared = CoreHasher::new();
+    /// let local = TLCoreHasher::new();
+    /// let hash = shared.hash_word_or_else(42, |val| local.hash_word(val));
+    /// ```
+    pub fn hash_word_or_else(&self, val: usize, fallback: impl FnOnce(usize) -> usize) -> usize {
+        self.try_hash_word(val).unwrap_or_else(|| fallback(val))
+    }
+
+    /// Hashes exactly as [`Self::hash_word`] does, but with `ordering` for the compare-and-swap
+    /// that advances the state and the matching ordering for loading it: `SeqCst` throughout,
+    /// `AcqRel` with `Acquire` loads as [`Self::hash_word`] uses, or `Relaxed` throughout.
+    ///
+    /// This exists so that the benchmarks can measure what each ordering costs. It is not part
+    /// of the stable API, and only exists with the `bench-internals` feature.
+    #[cfg(any(test, feature = "bench-internals"))]
+    #[doc(hidden)]
+    pub fn hash_word_with_ordering(&self, val: usize, ordering: Ordering) -> usize {
+        let load = match ordering {
+            Ordering::SeqCst => Ordering::SeqCst,
+            Ordering::Relaxed => Ordering::Relaxed,
+            _ => Ordering::Acquire,
+        };
+        let mut state = self.0.load(load);
+        loop {
+            let (hash, next) = hash_word_with_state(state, val);
+            match self.0.compare_exchange_weak(state, next, ordering, load) {
+                Ok(_) => return hash,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Advances the state from `state` by hashing `val`, returning the hash, or the current state
+    /// if it was no longer `state`
+    fn advance(&self, state: usize, val: usize) -> Result<usize, usize> {
+        let (hash, next) = hash_word_with_state(state, val);
+        self.0
+            .compare_exchange_weak(state, next, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| hash)
     }
 
     /// Hashes a slice of bytes by converting to a slice of usize
     /// and repeatedly applying [`Self::hash_word`]
+    ///
+    /// The whole slice is hashed as one update: concurrent calls never interleave their words,
+    /// and if another thread advances the state first the slice is hashed again from the new
+    /// state.
     pub fn hash_bytes(&self, bytes: &[u8]) -> usize {
-        const N: usize = core::mem::size_of::<usize>();
-        let chunks = bytes.array_chunks::<N>();
-        let rem = {
-            let mut r = chunks.remainder().iter();
-            usize::from_ne_bytes([0u8; N].map(|_| *r.next().unwrap_or(&0)))
-        };
-        chunks
-            .map(|c| usize::from_ne_bytes(*c))
-            .chain(core::iter::once(rem))
-            .fold(0, |val, next| val ^ self.hash_word(next))
+        let mut state = self.0.load(Ordering::Acquire);
+        loop {
+            let (hash, next) = hash_bytes_with_state(state, bytes);
+            match self
+                .0
+                .compare_exchange_weak(state, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return hash,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Derives an independent thread-local child hasher, advancing this hasher's state once.
+    ///
+    /// The derivation is the same as [`TLCoreHasher::fork`].
+    pub fn fork(&self) -> TLCoreHasher {
+        TLCoreHasher::with_state(fmix64(self.hash_word(DEFAULT_STATE) as u64) as usize)
     }
 }
 
@@ -167,6 +753,179 @@ impl Default for CoreHasher {
     }
 }
 
+/// The state of a [`CoreHasher`] laid out for placement in memory shared between processes
+///
+/// This is a single atomic word with the size and alignment of a `usize`, holding nothing but the
+/// state, so it may live in a `static` or in a memory-mapped region. Every operation through
+/// [`CoreHasher::from_raw`] updates it in place.
+///
+/// When placing it in shared memory, the region must be aligned to a `usize` and initialized
+/// exactly once before any process creates a view of it, either by writing
+/// [`RawCoreState::INIT`] (or [`RawCoreState::new`]) into it or by writing the initial state as a
+/// native-endian word. Every process sharing it must have the same pointer width and endianness,
+/// and the platform's atomics must be lock-free, as they are on every mainstream target.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{CoreHasher, RawCoreState};
+///
+/// static SHARED: RawCoreState = RawCoreState::INIT;
+///
+/// let a = CoreHasher::from_raw(&SHARED);
+/// let b = CoreHasher::from_raw(&SHARED);
+/// a.hash_word(1);
+/// assert_eq!(b.get_state(), a.get_state());
+/// ```
+#[derive(Debug)]
+#[repr(C)]
+pub struct RawCoreState(CoreHasher);
+
+impl RawCoreState {
+    /// A state holding the default [`CoreHasher`] state, for static placement
+    // Each use of the constant is a fresh state, which is what initialization wants
+    #[cfg(not(any(loom, shuttle)))]
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const INIT: Self = Self::new(DEFAULT_STATE);
+
+    /// Creates a [`RawCoreState`] holding `state`
+    #[cfg(not(any(loom, shuttle)))]
+    pub const fn new(state: usize) -> Self {
+        Self(CoreHasher(AtomicUsize::new(state)))
+    }
+}
+
+/// A [`CoreHasher`] operating on a borrowed [`RawCoreState`], returned by
+/// [`CoreHasher::from_raw`]
+#[derive(Debug, Clone, Copy)]
+pub struct CoreHasherRef<'a>(&'a CoreHasher);
+
+impl core::ops::Deref for CoreHasherRef<'_> {
+    type Target = CoreHasher;
+
+    fn deref(&self) -> &CoreHasher {
+        self.0
+    }
+}
+
+/// Hashes a word sized value from an explicit `state`, returning the hash and the next state.
+///
+/// This is the round [`TLCoreHasher`] and [`CoreHasher`] apply, as a pure function, for keeping
+/// the state somewhere of your own, like an enum variant or a database row, instead of in a
+/// [`Cell`] or an atomic. Threading the returned state into the next call reproduces the
+/// hashes a hasher starting from `state` would give.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_word_with_state, TLCoreHasher};
+///
+/// let hasher = TLCoreHasher::with_state(7);
+/// let (first, state) = hash_word_with_state(7, 1);
+/// let (second, _) = hash_word_with_state(state, 2);
+/// assert_eq!((first, second), (hasher.hash_word(1), hasher.hash_word(2)));
+/// ```
+#[inline]
+pub const fn hash_word_with_state(state: usize, val: usize) -> (usize, usize) {
+    raw::round(state, val)
+}
+
+/// Hashes `bytes` from an explicit `state` exactly as [`TLCoreHasher::hash_bytes`] does,
+/// returning the hash and the next state.
+///
+/// The bytes are read as native-endian words, the last one padded with zeroes, and each goes
+/// through [`hash_word_with_state`]; the hash is the xor of the word hashes.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes_with_state, TLCoreHasher};
+///
+/// const GREETING: (usize, usize) = hash_bytes_with_state(7, b"Hello, World!");
+/// assert_eq!(GREETING.0, TLCoreHasher::with_state(7).hash_bytes(b"Hello, World!"));
+/// ```
+#[inline]
+pub const fn hash_bytes_with_state(mut state: usize, mut bytes: &[u8]) -> (usize, usize) {
+    const N: usize = core::mem::size_of::<usize>();
+    let mut hash = 0;
+    while let Some((word, rest)) = bytes.split_first_chunk::<N>() {
+        (hash, state) = absorb_word(hash, state, usize::from_ne_bytes(*word));
+        bytes = rest;
+    }
+    let mut rem = [0u8; N];
+    let mut i = 0;
+    while i < bytes.len() {
+        rem[i] = bytes[i];
+        i += 1;
+    }
+    absorb_word(hash, state, usize::from_ne_bytes(rem))
+}
+
+/// The word loop shared by [`hash_bytes_with_state`] and [`hash_words_seeded`]: one round from
+/// `state`, its hash xored into the running `hash`, returning both updated
+#[inline]
+const fn absorb_word(hash: usize, state: usize, word: usize) -> (usize, usize) {
+    let (word_hash, next) = hash_word_with_state(state, word);
+    (hash ^ word_hash, next)
+}
+
+/// Hashes a slice of words in one shot, without a hasher, as [`hash_words_seeded`] does from
+/// the default state that [`hash_word_stateless`] starts from
+///
+/// The empty slice hashes as `hash_word_stateless(0)`, since only its length is folded in.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_word_stateless, hash_words_stateless};
+///
+/// assert_ne!(hash_words_stateless(&[1, 2]), hash_words_stateless(&[2, 1]));
+/// assert_ne!(hash_words_stateless(&[1]), hash_words_stateless(&[1, 0]));
+/// assert_eq!(hash_words_stateless(&[]), hash_word_stateless(0));
+/// ```
+#[inline]
+pub const fn hash_words_stateless(words: &[usize]) -> usize {
+    hash_words_seeded(words, DEFAULT_STATE)
+}
+
+/// Hashes a slice of words in one shot, starting from `seed`
+///
+/// Each word goes through one round of the algorithm, exactly as [`hash_bytes_with_state`] treats
+/// each word of its input: the word is xored into the state, the low half of the product is
+/// xored into the running hash and the high half becomes the next state. The length of the slice
+/// is absorbed last the same way, and the result is the running hash xored with the final state,
+/// as [`hash_word_stateless`] xors its two halves. Since the state carries from word to word,
+/// the order of the words matters, and since the length is absorbed, trailing zero words do too.
+/// In terms of [`hash_word_with_state`]:
+///
+/// ```
+/// use cmhash::{hash_word_with_state, hash_words_seeded};
+///
+/// fn reference(words: &[usize], seed: usize) -> usize {
+///     let (mut hash, mut state) = (0, seed);
+///     for &word in words.iter().chain([&words.len()]) {
+///         let (word_hash, next) = hash_word_with_state(state, word);
+///         hash ^= word_hash;
+///         state = next;
+///     }
+///     hash ^ state
+/// }
+///
+/// let words = [3, 1, 4, 1, 5];
+/// assert_eq!(hash_words_seeded(&words, 7), reference(&words, 7));
+/// ```
+#[inline]
+pub const fn hash_words_seeded(words: &[usize], seed: usize) -> usize {
+    let (mut hash, mut state) = (0, seed);
+    let mut i = 0;
+    while i < words.len() {
+        (hash, state) = absorb_word(hash, state, words[i]);
+        i += 1;
+    }
+    let (hash, state) = absorb_word(hash, state, words.len());
+    hash ^ state
+}
+
 /// Quickly hash a word sized value without carrying state.
 /// Achieves this by calling [`usize::widening_mul`] and xoring the two halves together
 ///
@@ -179,6 +938,80 @@ impl Default for CoreHasher {
 /// ```
 #[inline]
 pub fn hash_word_stateless(val: usize) -> usize {
-    let (hash, state) = (val ^ DEFAULT_STATE).widening_mul(MERSENNE_PRIME);
-    hash ^ state
+    word::stateless(val)
+}
+
+/// Hashes a word with `R` rounds of the algorithm, starting from `seed`.
+///
+/// Each round xors the running hash into the state, takes the widening product and xors its two
+/// halves together, then feeds the high half back in as the next state, perturbed by a round
+/// constant derived from `seed`. One round is exactly [`hash_word_stateless`] with `seed` in place
+/// of the default state. Repeated multiplication by a multiplier of the form `2^k - 1` is little
+/// more than a shift and a subtraction, so later rounds multiply by an odd constant near `2^w / phi` instead.
+///
+/// Measured over random 64-bit inputs, the number of output bits flipped by flipping one input
+/// bit is on average about 30 bits away from the ideal of 32 at `R = 1`, 1.1 bits away at `R = 2`
+/// and 0.3 bits away at `R = 3`.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_word_rounds;
+///
+/// assert_ne!(hash_word_rounds::<1>(42, 7), hash_word_rounds::<3>(42, 7));
+/// ```
+#[inline]
+pub fn hash_word_rounds<const R: usize>(val: usize, seed: usize) -> usize {
+    let mut hash = val;
+    let mut state = seed;
+    for r in 0..R {
+        let multiplier = if r == 0 {
+            MERSENNE_PRIME
+        } else {
+            ROUND_CONSTANT
+        };
+        let (lo, hi) = (hash ^ state).widening_mul(multiplier);
+        hash = lo ^ hi;
+        state = hi ^ seed ^ (r + 1).wrapping_mul(ROUND_CONSTANT);
+    }
+    hash
+}
+
+/// Combines two hasher states into a single word, for joining the results of pipelines that
+/// hashed separate parts of a workload.
+///
+/// The result is the xor of the two hashes a freshly constructed [`TLCoreHasher`] produces for
+/// `a` and then `b`:
+///
+/// ```
+/// use cmhash::{merge_states, TLCoreHasher};
+///
+/// let (a, b) = (0x1234, 0x5678);
+/// let h = TLCoreHasher::new();
+/// assert_eq!(merge_states(a, b), h.hash_word(a) ^ h.hash_word(b));
+/// ```
+///
+/// Because the second round is keyed by the state left by the first, `merge_states(a, b)` and
+/// `merge_states(b, a)` differ.
+pub fn merge_states(a: usize, b: usize) -> usize {
+    let (first, state) = hash_word_with_state(DEFAULT_STATE, a);
+    let (second, _) = hash_word_with_state(state, b);
+    first ^ second
+}
+
+/// Hashes a sequence of byte slices as if they were concatenated, using a [`TLCoreHasher`] with
+/// the default state.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes_vectored, TLCoreHasher};
+///
+/// assert_eq!(
+///     hash_bytes_vectored(&[b"Hello, ", b"World!"]),
+///     TLCoreHasher::new().hash_bytes(b"Hello, World!")
+/// );
+/// ```
+pub fn hash_bytes_vectored(parts: &[&[u8]]) -> usize {
+    TLCoreHasher::new().hash_bytes_vectored(parts)
 }
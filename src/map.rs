@@ -0,0 +1,213 @@
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+
+use crate::keyed::KeyedHasher;
+use crate::output::{hash_to_bucket, DEFAULT_SEED};
+
+#[derive(Debug, Clone)]
+struct Entry<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// A fixed-capacity hash map in an array of `N` slots, for `no_std` targets without an
+/// allocator.
+///
+/// Keys are hashed with a [`KeyedHasher`] derived from the map's seed, which hashes every write
+/// a [`Hash`] impl makes, so composite keys like strings and tuples spread as well as integers.
+/// Collisions are resolved by linear probing from the slot [`hash_to_bucket`] picks, and every
+/// slot can be filled. Removal shifts the entries after the removed one back toward their home
+/// slots instead of leaving tombstones, so lookups cost the same after any amount of churn as
+/// they would in a freshly built map. Iteration is in slot order, which depends on the seed and
+/// on the order of insertions and removals.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::CMIndexMap;
+///
+/// static EMPTY: CMIndexMap<&str, u8, 8> = CMIndexMap::with_seed(7);
+/// assert!(EMPTY.get("uart0").is_none());
+///
+/// let mut irqs = CMIndexMap::<&str, u8, 8>::with_seed(7);
+/// assert_eq!(irqs.insert("uart0", 5), Ok(None));
+/// assert_eq!(irqs.insert("uart0", 6), Ok(Some(5)));
+/// assert_eq!(irqs.get("uart0"), Some(&6));
+/// assert_eq!(irqs.remove("uart0"), Some(6));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CMIndexMap<K, V, const N: usize> {
+    seed: u64,
+    len: usize,
+    slots: [Option<Entry<K, V>>; N],
+}
+
+impl<K, V, const N: usize> CMIndexMap<K, V, N> {
+    /// The most entries the map holds
+    pub const CAPACITY: usize = N;
+
+    const NOT_EMPTY: () = assert!(N > 0, "CMIndexMap needs at least one slot");
+    const VACANT: Option<Entry<K, V>> = None;
+
+    /// Creates an empty map hashing keys under [`DEFAULT_SEED`]
+    pub const fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// Creates an empty map hashing keys under `seed`
+    pub const fn with_seed(seed: u64) -> Self {
+        let () = Self::NOT_EMPTY;
+        Self {
+            seed,
+            len: 0,
+            slots: [Self::VACANT; N],
+        }
+    }
+
+    /// Returns the seed keys are hashed under
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the entries in slot order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Iterates over the entries in slot order, with mutable references to the values
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.slots
+            .iter_mut()
+            .flatten()
+            .map(|entry| (&entry.key, &mut entry.value))
+    }
+
+    /// Removes every entry from the map
+    pub fn clear(&mut self) {
+        self.slots = [Self::VACANT; N];
+        self.len = 0;
+    }
+
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        let mut hasher = KeyedHasher::from_seed(self.seed);
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Walks the probe run for `key`, returning the slot holding it, or else the first vacant
+    /// slot of the run if there is one
+    fn find<Q>(&self, key: &Q) -> Result<usize, Option<usize>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        let mut slot = hash_to_bucket(hash, N);
+        for _ in 0..N {
+            match &self.slots[slot] {
+                None => return Err(Some(slot)),
+                Some(entry) if entry.hash == hash && entry.key.borrow() == key => return Ok(slot),
+                Some(_) => slot = (slot + 1) % N,
+            }
+        }
+        Err(None)
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize> CMIndexMap<K, V, N> {
+    /// Inserts `value` under `key`, returning the value it replaced, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `key` and `value` back if `key` isn't already present and the map is full.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        match self.find(&key) {
+            Ok(slot) => {
+                let entry = self.slots[slot].as_mut().expect("found slots are occupied");
+                Ok(Some(core::mem::replace(&mut entry.value, value)))
+            }
+            Err(Some(slot)) => {
+                let hash = self.hash(&key);
+                self.slots[slot] = Some(Entry { hash, key, value });
+                self.len += 1;
+                Ok(None)
+            }
+            Err(None) => Err((key, value)),
+        }
+    }
+
+    /// Returns a reference to the value under `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.find(key).ok()?;
+        self.slots[slot].as_ref().map(|entry| &entry.value)
+    }
+
+    /// Returns a mutable reference to the value under `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.find(key).ok()?;
+        self.slots[slot].as_mut().map(|entry| &mut entry.value)
+    }
+
+    /// Returns `true` if the map holds an entry for `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_ok()
+    }
+
+    /// Removes the entry for `key`, returning its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut hole = self.find(key).ok()?;
+        let removed = self.slots[hole].take()?;
+        self.len -= 1;
+        // Shift each following entry of the run back into the hole if that doesn't move it
+        // before its home slot
+        let mut slot = (hole + 1) % N;
+        while slot != hole {
+            let Some(entry) = &self.slots[slot] else {
+                break;
+            };
+            let home = hash_to_bucket(entry.hash, N);
+            if (hole + N - home) % N < (slot + N - home) % N {
+                self.slots[hole] = self.slots[slot].take();
+                hole = slot;
+            }
+            slot = (slot + 1) % N;
+        }
+        Some(removed.value)
+    }
+}
+
+impl<K, V, const N: usize> Default for CMIndexMap<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,118 @@
+use core::hash::{BuildHasher, Hash};
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::VecDeque;
+
+use crate::hasher::CMBuildHasher;
+
+/// A bounded cache of computed values, keyed by hash with a full equality check, as used by
+/// [`memoize`](crate::memoize)
+///
+/// Keys are bucketed by their hash under `S`, and a lookup compares the key with every entry in
+/// its bucket, so keys whose hashes collide are kept apart rather than returning each other's
+/// values. Once `capacity` entries are held, inserting a new key evicts the oldest one.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::MemoCache;
+///
+/// let mut cache = MemoCache::new(2);
+/// cache.insert("a", 1);
+/// cache.insert("b", 2);
+/// cache.insert("c", 3);
+/// assert_eq!(cache.get(&"a"), None);
+/// assert_eq!(cache.get(&"c"), Some(&3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoCache<K, V, S = CMBuildHasher> {
+    buckets: HashMap<u64, Vec<(K, V)>, CMBuildHasher>,
+    order: VecDeque<u64>,
+    capacity: usize,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V> MemoCache<K, V> {
+    /// Creates an empty cache holding at most `capacity` entries, hashing keys with a
+    /// [`CMBuildHasher`]
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, CMBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> MemoCache<K, V, S> {
+    /// Creates an empty cache holding at most `capacity` entries, hashing keys with `hasher`
+    pub fn with_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            buckets: HashMap::with_hasher(CMBuildHasher::new()),
+            order: VecDeque::new(),
+            capacity,
+            hasher,
+        }
+    }
+
+    /// Returns the most entries the cache holds
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns `true` if the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Returns the value cached for `key`
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.buckets
+            .get(&self.hasher.hash_one(key))?
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Caches `value` for `key`, replacing any value cached for it, and evicting the oldest
+    /// entry if the cache is full and `key` is new
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let hash = self.hasher.hash_one(&key);
+        if let Some((_, v)) = self
+            .buckets
+            .get_mut(&hash)
+            .and_then(|bucket| bucket.iter_mut().find(|(k, _)| *k == key))
+        {
+            *v = value;
+            return;
+        }
+        if self.order.len() == self.capacity {
+            self.evict_oldest();
+        }
+        self.buckets.entry(hash).or_default().push((key, value));
+        self.order.push_back(hash);
+    }
+
+    /// Removes every entry
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.order.clear();
+    }
+
+    /// Removes the entry inserted longest ago, which is the first of its bucket, as buckets
+    /// are appended to in insertion order
+    fn evict_oldest(&mut self) {
+        let Some(hash) = self.order.pop_front() else {
+            return;
+        };
+        if let Entry::Occupied(mut bucket) = self.buckets.entry(hash) {
+            bucket.get_mut().remove(0);
+            if bucket.get().is_empty() {
+                bucket.remove();
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::output::{hash_bytes, hash_combine, DEFAULT_SEED};
+
+// Mixed in first, so that a leaf and an internal node can never share a construction
+const LEAF: u64 = 0x4C45_4146;
+const NODE: u64 = 0x4E4F_4445;
+
+/// Hashes the contents of a leaf of a Merkle-style tree.
+///
+/// The result is `hash_combine(hash_combine(DEFAULT_SEED, LEAF), hash_combine(hash_bytes(bytes,
+/// DEFAULT_SEED), len))`, with `LEAF` a constant distinct from the one
+/// [`merkle_combine`] starts from. Like the rest of this crate, the tree hashes are meant for
+/// change detection and deduplication, not for proving integrity against an adversary: they
+/// are not cryptographic.
+pub fn merkle_leaf(bytes: &[u8]) -> u64 {
+    let content = hash_combine(hash_bytes(bytes, DEFAULT_SEED).0, bytes.len() as u64);
+    hash_combine(hash_combine(DEFAULT_SEED, LEAF), content)
+}
+
+/// Hashes an internal node of a Merkle-style tree from the hashes of its children, in order.
+///
+/// Starting from `hash_combine(DEFAULT_SEED, NODE)`, each child is folded in with
+/// [`hash_combine`], then the number of children, so the result depends on the order and arity
+/// of the children, and a node with one child differs from that child. Combining is not
+/// associative: different tree shapes over the same leaves give different roots.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{merkle_combine, merkle_leaf};
+///
+/// let (a, b, c) = (merkle_leaf(b"a"), merkle_leaf(b"b"), merkle_leaf(b"c"));
+/// let left = merkle_combine(&[merkle_combine(&[a, b]), c]);
+/// let right = merkle_combine(&[a, merkle_combine(&[b, c])]);
+/// assert_ne!(left, right);
+/// ```
+pub fn merkle_combine(children: &[u64]) -> u64 {
+    let acc = children
+        .iter()
+        .fold(hash_combine(DEFAULT_SEED, NODE), |acc, &child| {
+            hash_combine(acc, child)
+        });
+    hash_combine(acc, children.len() as u64)
+}
+
+/// Computes the root of a tree of fixed fan-out over a stream of leaves, keeping only the
+/// unfinished node at each level.
+///
+/// The tree is the one built by combining the leaves in consecutive groups of `fan_out` with
+/// [`merkle_combine`], the last group taking whatever is left, then grouping those nodes the
+/// same way, and so on until a single hash remains. Only `fan_out - 1` hashes per level are
+/// held at once, so a stream of `n` leaves needs `O(fan_out * log(n))` memory.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{merkle_combine, merkle_leaf, MerkleFolder};
+///
+/// let leaves: Vec<u64> = [&b"a"[..], b"b", b"c"].iter().map(|l| merkle_leaf(l)).collect();
+/// let mut folder = MerkleFolder::new(2);
+/// leaves.iter().for_each(|&leaf| folder.push(leaf));
+/// let expected = merkle_combine(&[
+///     merkle_combine(&leaves[..2]),
+///     merkle_combine(&leaves[2..]),
+/// ]);
+/// assert_eq!(folder.finish(), expected);
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct MerkleFolder {
+    fan_out: usize,
+    levels: Vec<Vec<u64>>,
+}
+
+#[cfg(feature = "alloc")]
+impl MerkleFolder {
+    /// Creates a [`MerkleFolder`] for a tree whose internal nodes have up to `fan_out`
+    /// children
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fan_out` is less than 2.
+    pub fn new(fan_out: usize) -> Self {
+        assert!(
+            fan_out >= 2,
+            "a tree node needs room for at least two children"
+        );
+        Self {
+            fan_out,
+            levels: Vec::new(),
+        }
+    }
+
+    /// Adds the next leaf hash, usually from [`merkle_leaf`]
+    pub fn push(&mut self, leaf: u64) {
+        let mut hash = leaf;
+        for level in 0.. {
+            if level == self.levels.len() {
+                self.levels.push(Vec::with_capacity(self.fan_out));
+            }
+            let pending = &mut self.levels[level];
+            pending.push(hash);
+            if pending.len() < self.fan_out {
+                return;
+            }
+            hash = merkle_combine(pending);
+            pending.clear();
+        }
+    }
+
+    /// Returns the root of the tree over every leaf pushed.
+    ///
+    /// A single leaf is its own root, and no leaves give `merkle_combine(&[])`.
+    pub fn finish(self) -> u64 {
+        let top = self.levels.len();
+        let mut carry = None;
+        for (level, mut pending) in self.levels.into_iter().enumerate() {
+            pending.extend(carry.take());
+            if level + 1 == top && pending.len() == 1 {
+                return pending[0];
+            }
+            if !pending.is_empty() {
+                carry = Some(merkle_combine(&pending));
+            }
+        }
+        carry.unwrap_or_else(|| merkle_combine(&[]))
+    }
+}
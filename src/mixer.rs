@@ -0,0 +1,88 @@
+use crate::hasher::fmix64;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A finalizer applied to the output of [`CMHasher`](crate::CMHasher)
+///
+/// Every implementation is bijective, so finalizing never introduces collisions. The trait is
+/// sealed: the mixers are part of the hashes' definition, so only this crate implements it.
+pub trait Mixer: sealed::Sealed {
+    /// Mixes the bits of `h`
+    fn mix(&self, h: u64) -> u64;
+}
+
+/// A [`Mixer`] that returns its input unchanged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct NoMix;
+
+impl sealed::Sealed for NoMix {}
+
+impl Mixer for NoMix {
+    #[inline]
+    fn mix(&self, h: u64) -> u64 {
+        h
+    }
+}
+
+/// A [`Mixer`] using the 64-bit finalizer from MurmurHash3
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Fmix64;
+
+impl sealed::Sealed for Fmix64 {}
+
+impl Mixer for Fmix64 {
+    #[inline]
+    fn mix(&self, h: u64) -> u64 {
+        fmix64(h)
+    }
+}
+
+/// A [`Mixer`] using Pelle Evensen's rrmxmx
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RrmxmxMix;
+
+impl sealed::Sealed for RrmxmxMix {}
+
+impl Mixer for RrmxmxMix {
+    #[inline]
+    fn mix(&self, mut h: u64) -> u64 {
+        h ^= h.rotate_right(49) ^ h.rotate_right(24);
+        h = h.wrapping_mul(0x9FB2_1C65_1E98_DF25);
+        h ^= h >> 28;
+        h = h.wrapping_mul(0x9FB2_1C65_1E98_DF25);
+        h ^ (h >> 28)
+    }
+}
+
+/// A [`Mixer`] chosen at runtime, as configured through
+/// [`CMHasherBuilder::mixer`](crate::CMHasherBuilder::mixer)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum MixerChoice {
+    /// [`NoMix`]
+    #[default]
+    None,
+    /// [`Fmix64`]
+    Fmix64,
+    /// [`RrmxmxMix`]
+    Rrmxmx,
+}
+
+impl sealed::Sealed for MixerChoice {}
+
+impl Mixer for MixerChoice {
+    #[inline]
+    fn mix(&self, h: u64) -> u64 {
+        match self {
+            Self::None => NoMix.mix(h),
+            Self::Fmix64 => Fmix64.mix(h),
+            Self::Rrmxmx => RrmxmxMix.mix(h),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::hasher::fmix64;
+use crate::output::{hash_bytes, hash_to_bucket};
+use crate::perfect::candidate;
+use crate::snapshot::{self, StateError};
+
+// The average number of keys per bucket. Larger buckets make the structure smaller but slower
+// to build.
+const LAMBDA: usize = 5;
+
+// How many seeds are tried before giving up; each fails with small probability
+const MAX_SEEDS: usize = 32;
+
+/// Why a [`MinimalPerfectHash`] couldn't be built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// The key at this index repeats an earlier key
+    DuplicateKey(usize),
+    /// There are more keys than a displacement can address
+    TooManyKeys,
+    /// No seed placed every key, which is vanishingly unlikely for distinct keys
+    NoSeedFound,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateKey(i) => write!(f, "key {i} repeats an earlier key"),
+            Self::TooManyKeys => f.write_str("too many keys for a minimal perfect hash"),
+            Self::NoSeedFound => f.write_str("no seed placed every key"),
+        }
+    }
+}
+
+/// The hashes a key is placed with: its bucket and the two words its displacement applies to
+#[derive(Clone, Copy)]
+struct KeyHash {
+    bucket: usize,
+    f1: u32,
+    f2: u32,
+}
+
+impl KeyHash {
+    fn new(key: &[u8], seed: u64, buckets: usize) -> (u64, Self) {
+        let h = hash_bytes(key, seed).0;
+        // Remixed so that the slot is independent of the bucket, which uses the high bits of h
+        let f = fmix64(h);
+        let key_hash = Self {
+            bucket: hash_to_bucket(h, buckets),
+            f1: f as u32,
+            f2: (f >> 32) as u32,
+        };
+        (h, key_hash)
+    }
+
+    fn slot(self, (d1, d2): (u32, u32), n: usize) -> usize {
+        // f1 * d1 < 2^64 - 2^33, so adding two more 32-bit values can't overflow
+        let mixed = self.f1 as u64 * d1 as u64 + self.f2 as u64 + d2 as u64;
+        (mixed % n as u64) as usize
+    }
+}
+
+/// A minimal perfect hash function for a fixed set of keys, built with the CHD (compress, hash
+/// and displace) algorithm
+///
+/// Each of the `n` keys the function was built from maps to a distinct index in `0..n`. Keys are
+/// hashed into buckets of about five, and each bucket stores a pair of displacements chosen so
+/// that its keys land in free slots, so the structure takes about 1.6 bytes per key. The index
+/// of a key that wasn't in the set is unspecified, so lookups usually compare against the key
+/// stored at the returned index.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::MinimalPerfectHash;
+///
+/// let keywords = ["fn", "let", "match", "impl", "trait"];
+/// let mph = MinimalPerfectHash::build(&keywords).unwrap();
+/// let mut table = [""; 5];
+/// for kw in keywords {
+///     table[mph.index(kw)] = kw;
+/// }
+/// assert_eq!(table[mph.index("impl")], "impl");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(bytecheck(verify))
+)]
+pub struct MinimalPerfectHash {
+    seed: u64,
+    len: usize,
+    displacements: Vec<(u32, u32)>,
+}
+
+impl MinimalPerfectHash {
+    /// Builds a minimal perfect hash function for `keys`, which must be distinct
+    ///
+    /// Fifty thousand keys build in about a tenth of a second.
+    pub fn build<K: AsRef<[u8]>>(keys: &[K]) -> Result<Self, BuildError> {
+        if u32::try_from(keys.len()).is_err() {
+            return Err(BuildError::TooManyKeys);
+        }
+        let n = keys.len();
+        let buckets = n.div_ceil(LAMBDA);
+        let mut hashes = Vec::with_capacity(n);
+        'seeds: for attempt in 0..MAX_SEEDS {
+            let seed = candidate(attempt);
+            hashes.clear();
+            hashes.extend(keys.iter().map(|k| KeyHash::new(k.as_ref(), seed, buckets)));
+            // Keys with the same full hash can never be separated, so look for repeats first
+            let mut order: Vec<usize> = (0..n).collect();
+            order.sort_unstable_by_key(|&i| (hashes[i].0, i));
+            for pair in order.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                if hashes[a].0 == hashes[b].0 {
+                    if keys[a].as_ref() == keys[b].as_ref() {
+                        return Err(BuildError::DuplicateKey(b));
+                    }
+                    continue 'seeds;
+                }
+            }
+            let keyed: Vec<KeyHash> = hashes.iter().map(|&(_, k)| k).collect();
+            if let Some(displacements) = place(&keyed, buckets) {
+                return Ok(Self {
+                    seed,
+                    len: n,
+                    displacements,
+                });
+            }
+        }
+        Err(BuildError::NoSeedFound)
+    }
+
+    /// Returns the number of keys the function was built from
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the function was built from no keys
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the index of `key`, distinct for each key the function was built from and in
+    /// `0..len()`. The result for any other key is unspecified but still in range, unless the
+    /// function is empty, in which case it is 0.
+    pub fn index(&self, key: impl AsRef<[u8]>) -> usize {
+        if self.len == 0 {
+            return 0;
+        }
+        let (_, key_hash) = KeyHash::new(key.as_ref(), self.seed, self.displacements.len());
+        key_hash.slot(self.displacements[key_hash.bucket], self.len)
+    }
+
+    /// Writes the function as bytes for embedding: a version byte, the seed and the number of
+    /// keys as little-endian `u64`s, then each bucket's displacements as little-endian `u32`s
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(17 + 8 * self.displacements.len());
+        bytes.push(snapshot::SNAPSHOT_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.len as u64).to_le_bytes());
+        for &(d1, d2) in &self.displacements {
+            bytes.extend_from_slice(&d1.to_le_bytes());
+            bytes.extend_from_slice(&d2.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Restores a function written by [`Self::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StateError> {
+        if bytes.len() < 17 {
+            return Err(StateError::Malformed);
+        }
+        snapshot::check_version(bytes)?;
+        let seed = snapshot::read_u64(bytes, 1);
+        let len = usize::try_from(snapshot::read_u64(bytes, 9))
+            .ok()
+            .filter(|&len| u32::try_from(len).is_ok())
+            .ok_or(StateError::Malformed)?;
+        let body = &bytes[17..];
+        if len.div_ceil(LAMBDA).checked_mul(8) != Some(body.len()) {
+            return Err(StateError::Malformed);
+        }
+        let displacements = body
+            .chunks_exact(8)
+            .map(|d| {
+                let word = snapshot::read_u64(d, 0);
+                (word as u32, (word >> 32) as u32)
+            })
+            .collect();
+        Ok(Self {
+            seed,
+            len,
+            displacements,
+        })
+    }
+}
+
+/// A [`MinimalPerfectHash`] read in place from an rkyv archive
+///
+/// Archives are only accessible once validated, and validation rejects any whose displacement
+/// table doesn't hold one pair per bucket of its keys, so lookups can't index out of bounds.
+#[cfg(feature = "rkyv")]
+impl ArchivedMinimalPerfectHash {
+    /// Returns the number of keys the function was built from
+    pub fn len(&self) -> usize {
+        self.len.to_native() as usize
+    }
+
+    /// Returns `true` if the function was built from no keys
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the index of `key` exactly as [`MinimalPerfectHash::index`] does, without
+    /// deserializing the displacements
+    pub fn index(&self, key: impl AsRef<[u8]>) -> usize {
+        let len = self.len();
+        if len == 0 {
+            return 0;
+        }
+        let buckets = self.displacements.len();
+        let (_, key_hash) = KeyHash::new(key.as_ref(), self.seed.to_native(), buckets);
+        let d = &self.displacements[key_hash.bucket];
+        key_hash.slot((d.0.to_native(), d.1.to_native()), len)
+    }
+}
+
+// SAFETY: only archives with one displacement pair per bucket of at most `u32::MAX` keys are
+// accepted, which is what `index` relies on and what `MinimalPerfectHash::from_bytes` checks
+#[cfg(feature = "rkyv")]
+unsafe impl<C> rkyv::bytecheck::Verify<C> for ArchivedMinimalPerfectHash
+where
+    C: rkyv::rancor::Fallible + ?Sized,
+    C::Error: rkyv::rancor::Source,
+{
+    fn verify(&self, _: &mut C) -> Result<(), C::Error> {
+        let len = self.len();
+        crate::archive::check(
+            u32::try_from(len).is_ok() && self.displacements.len() == len.div_ceil(LAMBDA),
+            "displacements don't match the number of keys",
+        )
+    }
+}
+
+/// Chooses displacements for each bucket, largest first, so that every key lands in a distinct
+/// slot of `0..keys.len()`. Returns `None` if some bucket can't be placed.
+fn place(keys: &[KeyHash], buckets: usize) -> Option<Vec<(u32, u32)>> {
+    let n = keys.len();
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); buckets];
+    for (i, key) in keys.iter().enumerate() {
+        members[key.bucket].push(i);
+    }
+    let mut order: Vec<usize> = (0..buckets).collect();
+    order.sort_unstable_by_key(|&b| core::cmp::Reverse(members[b].len()));
+
+    let mut displacements = vec![(0, 0); buckets];
+    let mut taken = vec![false; n];
+    // The last attempt that tentatively used each slot, so a bucket whose keys collide with each
+    // other is rejected without clearing anything
+    let mut tried = vec![0u64; n];
+    let mut generation = 0;
+    let mut slots = Vec::with_capacity(LAMBDA * 4);
+    for bucket in order {
+        let bucket_keys = &members[bucket];
+        if bucket_keys.is_empty() {
+            break;
+        }
+        let found = (0..n as u32)
+            .flat_map(|d1| (0..n as u32).map(move |d2| (d1, d2)))
+            .find(|&d| {
+                generation += 1;
+                slots.clear();
+                bucket_keys.iter().all(|&k| {
+                    let slot = keys[k].slot(d, n);
+                    if taken[slot] || tried[slot] == generation {
+                        return false;
+                    }
+                    tried[slot] = generation;
+                    slots.push(slot);
+                    true
+                })
+            })?;
+        displacements[bucket] = found;
+        for &slot in &slots {
+            taken[slot] = true;
+        }
+    }
+    Some(displacements)
+}
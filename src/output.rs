@@ -0,0 +1,324 @@
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use crate::hasher::{fmix64, Fmix64Hasher};
+use crate::keyed::KeyedHasher;
+use crate::mixer::Fmix64;
+use crate::word;
+
+/// The finished output of one of the one-shot hashing functions.
+///
+/// Wrapping the raw `u64` keeps hashes from being accidentally printed in decimal, truncated
+/// carelessly, or compared with numbers that didn't come from the same algorithm. All of the
+/// formatting impls print the full, zero-padded 16 hex digits.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::HashOutput;
+///
+/// let h = HashOutput(0xDEAD_BEEF);
+/// assert_eq!(h.to_string(), "00000000deadbeef");
+/// assert_eq!(format!("{:#X}", h), "0x00000000DEADBEEF");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct HashOutput(pub u64);
+
+impl HashOutput {
+    /// Returns the hash as little-endian bytes
+    pub const fn to_le_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    /// Folds the hash down to 32 bits by xoring the high half into the low half, so that every
+    /// input bit still influences the result.
+    pub const fn fold32(self) -> u32 {
+        (self.0 ^ (self.0 >> 32)) as u32
+    }
+
+    /// Returns the `n` most significant bits of the hash, which are the best mixed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub const fn top_bits(self, n: u32) -> u64 {
+        assert!(n <= 64, "a hash only has 64 bits");
+        match n {
+            0 => 0,
+            n => self.0 >> (64 - n),
+        }
+    }
+
+    /// Maps the hash to a bucket in `0..n_buckets`, agreeing with [`hash_to_bucket`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_buckets` is zero.
+    pub fn bucket(self, n_buckets: usize) -> usize {
+        hash_to_bucket(self.0, n_buckets)
+    }
+
+    /// Returns `true` if every bit of the hash is zero
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<HashOutput> for u64 {
+    fn from(h: HashOutput) -> Self {
+        h.0
+    }
+}
+
+impl fmt::LowerHex for HashOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl fmt::UpperHex for HashOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        write!(f, "{:016X}", self.0)
+    }
+}
+
+impl fmt::Display for HashOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+/// The seed used by [`CMHasher::new`](crate::CMHasher::new) and by the one-shot functions that
+/// don't take a seed
+pub const DEFAULT_SEED: u64 = crate::hasher::DEFAULT_HASHER_STATE;
+
+/// Maps a hash to a bucket in `0..n_buckets` using the high bits of the hash.
+///
+/// This uses a multiply and shift rather than a modulo, so it is fast for any bucket count, and
+/// every bucket receives an equal share of the hash range up to rounding.
+///
+/// # Panics
+///
+/// Panics if `n_buckets` is zero.
+pub fn hash_to_bucket(hash: u64, n_buckets: usize) -> usize {
+    assert_ne!(n_buckets, 0, "cannot map a hash to zero buckets");
+    ((hash as u128 * n_buckets as u128) >> 64) as usize
+}
+
+/// Hashes `bytes` with a [`CMHasher`](crate::CMHasher) seeded with `seed` and
+/// finalizes the result.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_bytes;
+///
+/// assert_eq!(hash_bytes(b"key", 1), hash_bytes(b"key", 1));
+/// assert_ne!(hash_bytes(b"key", 1), hash_bytes(b"key", 2));
+/// ```
+pub fn hash_bytes(bytes: &[u8], seed: u64) -> HashOutput {
+    let mut h = Fmix64Hasher::with_mixer(seed, Fmix64);
+    h.write(bytes);
+    HashOutput(h.finish())
+}
+
+/// Hashes the UTF-8 bytes of `s` with a [`CMHasher`](crate::CMHasher) seeded with
+/// [`DEFAULT_SEED`] and finalizes the result, as [`hash_bytes`] does.
+pub fn hash_str(s: &str) -> u64 {
+    hash_bytes(s.as_bytes(), DEFAULT_SEED).0
+}
+
+/// Hashes a single `u64` with a [`CMHasher`](crate::CMHasher) seeded with `seed` and
+/// finalizes the result.
+pub fn hash_u64(val: u64, seed: u64) -> HashOutput {
+    let mut h = Fmix64Hasher::with_mixer(seed, Fmix64);
+    h.write_u64(val);
+    HashOutput(h.finish())
+}
+
+/// Hashes any [`Hash`] value with a [`CMHasher`](crate::CMHasher) seeded with `seed` and
+/// finalizes the result.
+pub fn hash_value<T: Hash + ?Sized>(val: &T, seed: u64) -> HashOutput {
+    let mut h = Fmix64Hasher::with_mixer(seed, Fmix64);
+    val.hash(&mut h);
+    HashOutput(h.finish())
+}
+
+/// Folds `hash` into the running hash `acc`, for building the hash of a composite key from the
+/// hashes of its parts.
+///
+/// `hash` goes through one round of the algorithm under `acc` and the product is finalized, so
+/// the result depends on every bit of both and on the order parts are combined in.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes, hash_combine, hash_u64, DEFAULT_SEED};
+///
+/// let tenant = hash_u64(7, DEFAULT_SEED).0;
+/// let table = hash_bytes(b"users", DEFAULT_SEED).0;
+/// let key = hash_combine(hash_combine(DEFAULT_SEED, tenant), table);
+/// assert_ne!(key, hash_combine(hash_combine(DEFAULT_SEED, table), tenant));
+/// ```
+pub fn hash_combine(acc: u64, hash: u64) -> u64 {
+    let (hash, carry) = word::round(acc, hash);
+    fmix64(hash ^ carry.rotate_left(32))
+}
+
+/// Remixes a precomputed key hash under `salt`, for deriving several independent bucket
+/// mappings from a single hash of the key.
+///
+/// Hashing a long key once and salting the result per table is much cheaper than rehashing the
+/// key's bytes under a different seed for each table. `key_hash ^ salt` goes through one
+/// widening-multiply round and is finalized, so a salt of `0` still remixes the hash, and keys
+/// that share a bucket under one salt are no more likely than chance to share one under
+/// another. This is [`hash_combine`]`(salt, key_hash)`; [`salts_for`] generates salts for it.
+///
+/// Salting can't separate keys whose hashes are already equal: those collide under every salt.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes, hash_to_bucket, hash_with_salt, salts_for};
+///
+/// let key_hash = hash_bytes(b"tenant/42/orders/2024-06-01/9f8a", 0).0;
+/// let buckets: Vec<usize> = salts_for(7, 3)
+///     .map(|salt| hash_to_bucket(hash_with_salt(key_hash, salt), 1024))
+///     .collect();
+/// assert_eq!(buckets.len(), 3);
+/// ```
+pub fn hash_with_salt(key_hash: u64, salt: u64) -> u64 {
+    hash_combine(salt, key_hash)
+}
+
+/// Returns `n` distinct-looking salts for [`hash_with_salt`] derived from `base_seed`.
+///
+/// Salt `i` is [`hash_combine`] of `i` under the hashed seed, so the same `base_seed` always
+/// gives the same salts, and each prefix of a longer run is a shorter run.
+pub fn salts_for(base_seed: u64, n: usize) -> impl Iterator<Item = u64> {
+    let key = hash_combine(DEFAULT_SEED, base_seed);
+    (0..n as u64).map(move |i| hash_combine(key, i))
+}
+
+/// Hashes a sequence of tokens such that no two different sequences are joined the same way,
+/// unlike hashing them concatenated with a separator, where `["a/b", "c"]` and `["a", "b/c"]`
+/// both become `"a/b/c"`.
+///
+/// The result is exactly this construction, with `n` the number of tokens:
+///
+/// ```
+/// # use cmhash::{hash_bytes, hash_combine, hash_tokens};
+/// # let (tokens, seed) = (["usr", "local", "bin"], 7);
+/// let mut acc = seed;
+/// for token in tokens {
+///     let token_hash = hash_combine(hash_bytes(token.as_bytes(), seed).0, token.len() as u64);
+///     acc = hash_combine(acc, token_hash);
+/// }
+/// let hash = hash_combine(acc, tokens.len() as u64);
+/// assert_eq!(hash, hash_tokens(tokens, seed));
+/// ```
+///
+/// Mixing in each token's length keeps a token from colliding with itself padded with zero
+/// bytes, and chaining through [`hash_combine`] makes the result depend on the order of the
+/// tokens. An empty sequence hashes to `hash_combine(seed, 0)`. Nothing is allocated.
+pub fn hash_tokens<I>(tokens: I, seed: u64) -> u64
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut count = 0u64;
+    let acc = tokens.into_iter().fold(seed, |acc, token| {
+        let token = token.as_ref();
+        count += 1;
+        let token_hash = hash_combine(hash_bytes(token, seed).0, token.len() as u64);
+        hash_combine(acc, token_hash)
+    });
+    hash_combine(acc, count)
+}
+
+/// Hashes the pieces of `text` split at each `separator` with [`hash_tokens`].
+///
+/// Every separator delimits a token, so leading, trailing and doubled separators give empty
+/// tokens, and `"a/b/"` hashes differently from `"a/b"`.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_split, hash_tokens};
+///
+/// assert_eq!(hash_split("usr/local/bin", '/', 7), hash_tokens(["usr", "local", "bin"], 7));
+/// ```
+pub fn hash_split(text: &str, separator: char, seed: u64) -> u64 {
+    hash_tokens(text.split(separator), seed)
+}
+
+/// Hashes a sequence of [`Hash`] items into one digest without collecting them.
+///
+/// Each item is hashed on its own by a [`KeyedHasher`] keyed from `seed`, which unlike
+/// [`hash_value`] takes every write of a multi-part [`Hash`] impl into account. Its hash is mixed
+/// with its index through [`hash_combine`] and chained into the result, and the number of items
+/// is folded in last, so the result depends on the order of the items and a sequence never
+/// collides with one of its extensions by construction. The empty sequence hashes to
+/// `hash_combine(seed, 0)`.
+///
+/// Items are hashed one by one rather than as one stream, so this is not [`hash_value`] of the
+/// equivalent slice or `Vec`, and a one-item sequence doesn't hash to [`hash_value`] of the item:
+/// the index and count are mixed in even then.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_iter_of;
+///
+/// let params = [("page", 2), ("sort", 1)];
+/// assert_eq!(hash_iter_of(params, 7), hash_iter_of(params.iter(), 7));
+/// assert_ne!(hash_iter_of(params, 7), hash_iter_of(params.iter().rev(), 7));
+/// ```
+pub fn hash_iter_of<T: Hash, I: IntoIterator<Item = T>>(iter: I, seed: u64) -> u64 {
+    let mut count = 0u64;
+    let acc = iter.into_iter().fold(seed, |acc, item| {
+        let item_hash = hash_combine(hash_item(&item, seed), count);
+        count += 1;
+        hash_combine(acc, item_hash)
+    });
+    hash_combine(acc, count)
+}
+
+/// Hashes a collection of [`Hash`] items independently of their order, for sets and tags that
+/// aren't kept sorted.
+///
+/// The hash of each item, as in [`hash_iter_of`], is summed, wrapping, and the sum and the number
+/// of items are then chained through [`hash_combine`]. Addition is commutative, so any
+/// permutation of the items hashes the same, but it counts duplicates: `[a, a]` and `[a]` hash
+/// differently. The empty collection hashes to `hash_combine(hash_combine(seed, 0), 0)`.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_iter_of_unordered;
+///
+/// assert_eq!(
+///     hash_iter_of_unordered(["red", "large"], 7),
+///     hash_iter_of_unordered(["large", "red"], 7)
+/// );
+/// ```
+pub fn hash_iter_of_unordered<T: Hash, I: IntoIterator<Item = T>>(iter: I, seed: u64) -> u64 {
+    let mut count = 0u64;
+    let sum = iter.into_iter().fold(0u64, |sum, item| {
+        count += 1;
+        sum.wrapping_add(hash_item(&item, seed))
+    });
+    hash_combine(hash_combine(seed, sum), count)
+}
+
+fn hash_item<T: Hash>(item: &T, seed: u64) -> u64 {
+    let mut h = KeyedHasher::from_seed(seed);
+    item.hash(&mut h);
+    h.finish()
+}
@@ -0,0 +1,164 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::output::hash_u64;
+
+/// The most bits [`radix_partition`] takes: 32, or one less than a word on narrower targets so
+/// that the `2^bits + 1` partition boundaries can be counted in a `usize`
+const MAX_BITS: u32 = if usize::BITS - 1 < 32 {
+    usize::BITS - 1
+} else {
+    32
+};
+
+/// Keys grouped into `2^bits` partitions by their hashes, as computed by [`radix_partition`]
+///
+/// The keys themselves aren't copied: the partitioning is a permutation of their indices, in
+/// which each partition's indices are one contiguous run, and the boundaries of those runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partitioned {
+    order: Vec<usize>,
+    bounds: Vec<usize>,
+}
+
+impl Partitioned {
+    /// Returns the number of partitions
+    pub fn partitions(&self) -> usize {
+        self.bounds.len() - 1
+    }
+
+    /// Returns the indices of all the keys, partition by partition, each partition's in the
+    /// order the keys were given in
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Returns the `partitions() + 1` boundaries of the runs in [`Self::order`], partition `p`
+    /// being the run from `bounds()[p]` to `bounds()[p + 1]`
+    pub fn bounds(&self) -> &[usize] {
+        &self.bounds
+    }
+
+    /// Returns the run of [`Self::order`] holding partition `p`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is out of range.
+    pub fn range(&self, p: usize) -> Range<usize> {
+        self.bounds[p]..self.bounds[p + 1]
+    }
+
+    /// Returns the indices of the keys in partition `p`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is out of range.
+    pub fn indices(&self, p: usize) -> &[usize] {
+        &self.order[self.range(p)]
+    }
+}
+
+/// Partitions `keys` into `2^bits` partitions by the top `bits` bits of their
+/// [`hash_u64`] under `seed`
+///
+/// This is the two-pass radix partitioning of hash joins: the first pass counts the keys in
+/// each partition, a prefix sum of the counts gives where each partition's run starts, and the
+/// second pass scatters each key's index to the next free place in its run. Only the indices
+/// and `2^bits` counts are allocated, and the hash of each key is computed once per pass rather
+/// than stored.
+///
+/// # Panics
+///
+/// Panics if `bits` is over 32, or over 31 on 32-bit targets.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_u64, radix_partition};
+///
+/// let keys: Vec<u64> = (0..1000).collect();
+/// let partitioned = radix_partition(&keys, 4, 7);
+/// assert_eq!(partitioned.partitions(), 16);
+/// for &i in partitioned.indices(3) {
+///     assert_eq!(hash_u64(keys[i], 7).top_bits(4), 3);
+/// }
+/// ```
+pub fn radix_partition(keys: &[u64], bits: u32, seed: u64) -> Partitioned {
+    assert!(
+        bits <= MAX_BITS,
+        "2^{bits} partitions are too many to count"
+    );
+    let partition = |key: u64| hash_u64(key, seed).top_bits(bits) as usize;
+    let mut bounds = vec![0; (1 << bits) + 1];
+    for &key in keys {
+        bounds[partition(key) + 1] += 1;
+    }
+    for p in 1..bounds.len() {
+        bounds[p] += bounds[p - 1];
+    }
+    let mut next = bounds[..bounds.len() - 1].to_vec();
+    let mut order = vec![0; keys.len()];
+    for (i, &key) in keys.iter().enumerate() {
+        let slot = &mut next[partition(key)];
+        order[*slot] = i;
+        *slot += 1;
+    }
+    Partitioned { order, bounds }
+}
+
+/// Partitions `keys` as [`radix_partition`] does, and rearranges `payload`, whose elements
+/// belong to the keys at the same indices, into the same order
+///
+/// Afterwards `payload[j]` is the element that was at `order()[j]`, so each partition's
+/// elements are the contiguous run [`Partitioned::range`] gives. The elements are moved in
+/// place by following the cycles of the permutation, without cloning them.
+///
+/// # Panics
+///
+/// Panics if `payload` isn't as long as `keys`, or if `bits` is over 32, or over 31 on 32-bit
+/// targets.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::radix_partition_with_payload;
+///
+/// let keys = [10, 20, 30, 40, 50, 60];
+/// let mut names = ["ten", "twenty", "thirty", "forty", "fifty", "sixty"];
+/// let partitioned = radix_partition_with_payload(&keys, &mut names, 1, 0);
+/// for p in 0..2 {
+///     for (&i, &name) in partitioned.indices(p).iter().zip(&names[partitioned.range(p)]) {
+///         assert_eq!(name, ["ten", "twenty", "thirty", "forty", "fifty", "sixty"][i]);
+///     }
+/// }
+/// ```
+pub fn radix_partition_with_payload<T>(
+    keys: &[u64],
+    payload: &mut [T],
+    bits: u32,
+    seed: u64,
+) -> Partitioned {
+    assert_eq!(
+        keys.len(),
+        payload.len(),
+        "the payload must have one element per key"
+    );
+    let partitioned = radix_partition(keys, bits, seed);
+    let mut placed = vec![false; payload.len()];
+    for start in 0..payload.len() {
+        if placed[start] {
+            continue;
+        }
+        // Pull each element of the cycle into the place that wants it
+        let mut j = start;
+        placed[j] = true;
+        while partitioned.order[j] != start {
+            let from = partitioned.order[j];
+            payload.swap(j, from);
+            j = from;
+            placed[j] = true;
+        }
+    }
+    partitioned
+}
@@ -0,0 +1,85 @@
+use crate::output::{hash_bytes, DEFAULT_SEED};
+use crate::sequence::HashSequence;
+
+/// The seed tried on attempt `attempt`, the same on every run so searches are reproducible
+pub(crate) fn candidate(attempt: usize) -> u64 {
+    HashSequence::new(DEFAULT_SEED).get(attempt as u64)
+}
+
+/// Returns `true` if no two of `keys` share a bucket, marking each bucket in `taken`
+fn injective<K: AsRef<[u8]>>(keys: &[K], seed: u64, taken: &mut [bool]) -> bool {
+    taken.fill(false);
+    keys.iter().all(|key| {
+        let bucket = hash_bytes(key.as_ref(), seed).bucket(taken.len());
+        !core::mem::replace(&mut taken[bucket], true)
+    })
+}
+
+fn search<K: AsRef<[u8]>>(keys: &[K], max_attempts: usize, taken: &mut [bool]) -> Option<u64> {
+    if keys.len() > taken.len() {
+        return None;
+    }
+    (0..max_attempts)
+        .map(candidate)
+        .find(|&seed| injective(keys, seed, taken))
+}
+
+/// Searches for a seed under which `keys` map to distinct buckets of a table of `table_size`
+/// slots, trying at most `max_attempts` seeds.
+///
+/// Key `k` maps to [`hash_bytes`]`(k, seed).bucket(table_size)`. Seeds are tried in a fixed
+/// order, so the same keys always find the same seed. Returns `None` if no seed was found, which
+/// is certain when there are more keys than slots.
+///
+/// A single seed maps `n` keys into `m` slots without collision with probability of roughly
+/// `exp(-n² / 2m)`, so the expected number of attempts grows exponentially in `n² / m`. A table
+/// twice the size of the key set is practical up to a few dozen keys (about 20 attempts for 10
+/// keys, a few thousand for 30); a few hundred keys need a table on the order of `n²` slots.
+///
+/// The keys are assumed distinct: a repeated key never maps injectively.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{find_perfect_seed, hash_bytes, verify_perfect};
+///
+/// let keys = ["add", "sub", "mul", "div", "load", "store"];
+/// let seed = find_perfect_seed(&keys, 12, 1000).unwrap();
+/// assert!(verify_perfect(seed, &keys, 12));
+/// let slot = hash_bytes(b"mul", seed).bucket(12);
+/// assert!(slot < 12);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn find_perfect_seed<K: AsRef<[u8]>>(
+    keys: &[K],
+    table_size: usize,
+    max_attempts: usize,
+) -> Option<u64> {
+    search(keys, max_attempts, &mut alloc::vec![false; table_size])
+}
+
+/// Returns `true` if `keys` map to distinct buckets of a table of `table_size` slots under
+/// `seed`, as [`find_perfect_seed`] requires
+#[cfg(feature = "alloc")]
+pub fn verify_perfect<K: AsRef<[u8]>>(seed: u64, keys: &[K], table_size: usize) -> bool {
+    keys.len() <= table_size && injective(keys, seed, &mut alloc::vec![false; table_size])
+}
+
+/// [`find_perfect_seed`](crate::find_perfect_seed) for a table of `N` slots, without allocating
+///
+/// This tries the same seeds in the same order, so it finds the same seed.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::find_small_perfect_seed;
+///
+/// let keys = [b"on", b"of", b"up"];
+/// assert!(find_small_perfect_seed::<8, _>(&keys, 1000).is_some());
+/// ```
+pub fn find_small_perfect_seed<const N: usize, K: AsRef<[u8]>>(
+    keys: &[K],
+    max_attempts: usize,
+) -> Option<u64> {
+    search(keys, max_attempts, &mut [false; N])
+}
@@ -0,0 +1,102 @@
+use crate::word::Word;
+
+/// A double hashing probe sequence over a power-of-two sized open-addressed table.
+///
+/// The start slot and the stride are both taken from the one widening multiply of `key_hash`:
+/// the high half picks the start and the low half picks the stride, which is forced odd so that
+/// the sequence visits every slot of the table exactly once before it ends.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::ProbeSeq;
+///
+/// let mut slots: Vec<usize> = ProbeSeq::new(0xDEAD_BEEF_CAFE_F00D, 7).collect();
+/// slots.sort();
+/// assert_eq!(slots, [0, 1, 2, 3, 4, 5, 6, 7]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProbeSeq {
+    pos: usize,
+    stride: usize,
+    mask: usize,
+    remaining: usize,
+}
+
+impl ProbeSeq {
+    /// Starts the probe sequence for `key_hash` in a table of `table_mask + 1` slots.
+    ///
+    /// `table_mask` must be one less than a power of two.
+    pub fn new(key_hash: u64, table_mask: usize) -> Self {
+        debug_assert!(
+            table_mask.wrapping_add(1).is_power_of_two(),
+            "table_mask must be one less than a power of two"
+        );
+        let (lo, hi) = key_hash.wide_mul(u64::PRIME);
+        Self {
+            pos: hi as usize & table_mask,
+            stride: (lo >> 32) as usize | 1,
+            mask: table_mask,
+            remaining: table_mask + 1,
+        }
+    }
+}
+
+impl Iterator for ProbeSeq {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let pos = self.pos;
+        self.pos = self.pos.wrapping_add(self.stride) & self.mask;
+        Some(pos)
+    }
+}
+
+/// A triangular probe sequence over a power-of-two sized open-addressed table, the same scheme
+/// hashbrown uses with a group width of one slot.
+///
+/// Starting from the slot picked by the high half of the widening multiply of `key_hash`, the
+/// distance from the start grows by 1, 2, 3, ... slots, which visits every slot of a power-of-two
+/// table exactly once before the sequence ends.
+#[derive(Debug, Clone)]
+pub struct TriangularProbeSeq {
+    pos: usize,
+    stride: usize,
+    mask: usize,
+}
+
+impl TriangularProbeSeq {
+    /// Starts the probe sequence for `key_hash` in a table of `table_mask + 1` slots.
+    ///
+    /// `table_mask` must be one less than a power of two.
+    pub fn new(key_hash: u64, table_mask: usize) -> Self {
+        debug_assert!(
+            table_mask.wrapping_add(1).is_power_of_two(),
+            "table_mask must be one less than a power of two"
+        );
+        let (_, hi) = key_hash.wide_mul(u64::PRIME);
+        Self {
+            pos: hi as usize & table_mask,
+            stride: 0,
+            mask: table_mask,
+        }
+    }
+}
+
+impl Iterator for TriangularProbeSeq {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.stride > self.mask {
+            return None;
+        }
+        let pos = self.pos;
+        self.stride += 1;
+        self.pos = self.pos.wrapping_add(self.stride) & self.mask;
+        Some(pos)
+    }
+}
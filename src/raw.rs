@@ -0,0 +1,92 @@
+//! The round function every hasher in this crate is built from, and the constants it uses.
+//!
+//! These are the stability-bearing core: [`TLCoreHasher`](crate::TLCoreHasher),
+//! [`CoreHasher`](crate::CoreHasher), [`hash_word_with_state`](crate::hash_word_with_state) and
+//! the functions built on them are [`round`](crate::raw::round) applied word by word, and
+//! [`CMHasher`](crate::CMHasher) and [`StatelessHasher`](crate::StatelessHasher) are
+//! [`round_u64`](crate::raw::round_u64), so they change only if these do. They are exposed for
+//! constructions of your own, such as a sponge or a streaming protocol, that need the primitive
+//! rather than a hasher wrapped around it.
+//!
+//! A round xors a word into the state and takes the full product with the prime. The low half
+//! of the product is the word's hash and the high half is the next state.
+//! [`TLCoreHasher::hash_word`](crate::TLCoreHasher::hash_word) returns the hash and keeps the
+//! state; [`hash_word_stateless`](crate::hash_word_stateless) xors the two halves of a round
+//! from [`DEFAULT_STATE`](crate::raw::DEFAULT_STATE); a [`CMHasher`](crate::CMHasher) xors the
+//! hashes of the words it is written into its starting state.
+//!
+//! # Examples
+//!
+//! ```
+//! use cmhash::raw::{round, DEFAULT_STATE};
+//! use cmhash::TLCoreHasher;
+//!
+//! let hasher = TLCoreHasher::new();
+//! let (first, state) = round(DEFAULT_STATE, 1);
+//! let (second, _) = round(state, 2);
+//! assert_eq!((first, second), (hasher.hash_word(1), hasher.hash_word(2)));
+//!
+//! const TABLE_KEY: usize = round(DEFAULT_STATE, 42).0;
+//! assert_eq!(TABLE_KEY, TLCoreHasher::new().hash_word(42));
+//! ```
+
+use crate::hasher::{fmix64, DEFAULT_HASHER_STATE, DEFAULT_PRIME};
+use crate::word::Word;
+use crate::DoubleWord;
+
+/// The multiplier of [`round`]: `2^62 - 1` on 64-bit targets, the same as [`PRIME_U64`],
+/// `2^31 - 1` on 32-bit targets and `2^14 - 1` on 16-bit targets. Only the 32-bit one is a
+/// Mersenne prime; the others are kept so that existing hashes don't change.
+pub const PRIME: usize = crate::MERSENNE_PRIME;
+
+/// The state the word hashers start from unless given one
+pub const DEFAULT_STATE: usize = crate::DEFAULT_STATE;
+
+/// The multiplier of [`round_u64`] in a [`CMHasher`](crate::CMHasher) left at
+/// [`Prime::Classic`](crate::Prime::Classic)
+pub const PRIME_U64: u64 = DEFAULT_PRIME;
+
+/// The state a [`CMHasher`](crate::CMHasher) starts from unless given one
+pub const DEFAULT_STATE_U64: u64 = DEFAULT_HASHER_STATE;
+
+/// The multiplier of the 32-bit hashers
+pub const PRIME_U32: u32 = <u32 as Word>::PRIME;
+
+/// The state the 32-bit hashers start from
+pub const DEFAULT_STATE_U32: u32 = <u32 as Word>::DEFAULT_STATE;
+
+/// The multiplier of the 16-bit hashers, such as [`hash_word_u16`](crate::hash_word_u16)
+pub const PRIME_U16: u16 = <u16 as Word>::PRIME;
+
+/// The state the 16-bit hashers start from
+pub const DEFAULT_STATE_U16: u16 = <u16 as Word>::DEFAULT_STATE;
+
+/// Xors `word` into `state` and multiplies by [`PRIME`], returning the low half of the product
+/// as the hash and the high half as the next state
+#[inline]
+pub const fn round(state: usize, word: usize) -> (usize, usize) {
+    let product = (word ^ state) as DoubleWord * PRIME as DoubleWord;
+    (product as usize, (product >> usize::BITS) as usize)
+}
+
+/// Xors `word` into `state` and multiplies by `prime`, returning the low half of the product as
+/// the hash and the high half as the next state
+///
+/// This is the round of a [`CMHasher`](crate::CMHasher) using
+/// [`Strategy::Multiply`](crate::Strategy::Multiply), with `prime` the value of its
+/// [`Prime`](crate::Prime), [`PRIME_U64`] by default.
+#[inline]
+pub const fn round_u64(state: u64, word: u64, prime: u64) -> (u64, u64) {
+    let product = (word ^ state) as u128 * prime as u128;
+    (product as u64, (product >> 64) as u64)
+}
+
+/// The 64-bit finalizer from MurmurHash3, as [`Fmix64`](crate::Fmix64) applies it, truncated to
+/// a word on narrower targets
+///
+/// A round alone spreads its input unevenly over the output bits, so finalize a hash before
+/// reducing it to a range.
+#[inline]
+pub const fn finalize(h: usize) -> usize {
+    fmix64(h as u64) as usize
+}
@@ -0,0 +1,178 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::histogram::Histogram;
+use crate::shard::ShardSelector;
+
+/// A key that changes shards between two configurations, as listed by
+/// [`rebalance_plan_with_keys`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MovedKey {
+    /// The key's bytes
+    pub key: Vec<u8>,
+    /// The shard the key is in under the old configuration
+    pub from: usize,
+    /// The shard the key is in under the new configuration
+    pub to: usize,
+}
+
+/// Which keys change shards between two [`ShardSelector`]s, as computed by [`rebalance_plan`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebalancePlan {
+    old_shards: usize,
+    new_shards: usize,
+    counts: Vec<u64>,
+    total: u64,
+    moved: u64,
+    moved_keys: Option<Vec<MovedKey>>,
+}
+
+impl RebalancePlan {
+    fn new(old: &ShardSelector, new: &ShardSelector, keep_keys: bool) -> Self {
+        let (old_shards, new_shards) = (old.shards().get(), new.shards().get());
+        Self {
+            old_shards,
+            new_shards,
+            counts: vec![0; old_shards * new_shards],
+            total: 0,
+            moved: 0,
+            moved_keys: keep_keys.then(Vec::new),
+        }
+    }
+
+    fn record(&mut self, key: &[u8], from: usize, to: usize) {
+        self.counts[from * self.new_shards + to] += 1;
+        self.total += 1;
+        if from != to {
+            self.moved += 1;
+            if let Some(moved_keys) = &mut self.moved_keys {
+                moved_keys.push(MovedKey {
+                    key: key.to_vec(),
+                    from,
+                    to,
+                });
+            }
+        }
+    }
+
+    /// Returns the number of shards in the old configuration
+    pub fn old_shards(&self) -> usize {
+        self.old_shards
+    }
+
+    /// Returns the number of shards in the new configuration
+    pub fn new_shards(&self) -> usize {
+        self.new_shards
+    }
+
+    /// Returns the number of keys that are in shard `from` under the old configuration and in
+    /// shard `to` under the new one
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is out of range for its configuration.
+    pub fn count(&self, from: usize, to: usize) -> u64 {
+        assert!(
+            from < self.old_shards && to < self.new_shards,
+            "no such shard"
+        );
+        self.counts[from * self.new_shards + to]
+    }
+
+    /// Returns the number of keys planned
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the number of keys that change shards
+    pub fn moved(&self) -> u64 {
+        self.moved
+    }
+
+    /// Returns the fraction of keys that change shards, or `0.0` if there are no keys
+    pub fn moved_fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.moved as f64 / self.total as f64
+    }
+
+    /// Returns how the keys spread across the new shards
+    pub fn new_loads(&self) -> Histogram {
+        Histogram::from_counts(
+            (0..self.new_shards)
+                .map(|to| (0..self.old_shards).map(|from| self.count(from, to)).sum())
+                .collect(),
+        )
+    }
+
+    /// Returns how the keys spread across the old shards
+    pub fn old_loads(&self) -> Histogram {
+        Histogram::from_counts(
+            self.counts
+                .chunks(self.new_shards)
+                .map(|row| row.iter().sum())
+                .collect(),
+        )
+    }
+
+    /// Returns the keys that change shards, if they were kept by [`rebalance_plan_with_keys`]
+    pub fn moved_keys(&self) -> Option<&[MovedKey]> {
+        self.moved_keys.as_deref()
+    }
+}
+
+/// Computes which of `keys` change shards when moving from the `old` configuration to the `new`
+/// one, and how balanced the new one is
+///
+/// Only counts are kept, so memory use doesn't grow with the number of keys. Use
+/// [`rebalance_plan_with_keys`] to also list the keys that move.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use cmhash::{rebalance_plan, ShardSelector};
+///
+/// let keys: Vec<String> = (0..10_000).map(|i| format!("user/{i}")).collect();
+/// let old = ShardSelector::jump(NonZeroUsize::new(4).unwrap(), 7);
+/// let new = ShardSelector::jump(NonZeroUsize::new(5).unwrap(), 7);
+/// let plan = rebalance_plan(&keys, &old, &new);
+/// // Only the keys bound for the new shard move
+/// assert!((plan.moved_fraction() - 0.2).abs() < 0.02);
+/// assert!((0..4).all(|shard| plan.count(shard, 4) > 0));
+/// ```
+pub fn rebalance_plan<I>(keys: I, old: &ShardSelector, new: &ShardSelector) -> RebalancePlan
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    plan(keys, old, new, false)
+}
+
+/// Computes the same plan as [`rebalance_plan`], also keeping a copy of every key that changes
+/// shards
+pub fn rebalance_plan_with_keys<I>(
+    keys: I,
+    old: &ShardSelector,
+    new: &ShardSelector,
+) -> RebalancePlan
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    plan(keys, old, new, true)
+}
+
+fn plan<I>(keys: I, old: &ShardSelector, new: &ShardSelector, keep_keys: bool) -> RebalancePlan
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut plan = RebalancePlan::new(old, new, keep_keys);
+    for key in keys {
+        let key = key.as_ref();
+        plan.record(key, old.select(key), new.select(key));
+    }
+    plan
+}
@@ -0,0 +1,131 @@
+use crate::output::hash_to_bucket;
+
+// Marks an empty slot. A key hash of 0 is stored as `ZERO_HASH` instead, so the two collide.
+const EMPTY: u64 = 0;
+const ZERO_HASH: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// One generation of a [`RecentSet`]: a linearly probed table of `N` slots holding at most
+/// `N / 2` hashes
+#[derive(Debug, Clone)]
+struct Generation<const N: usize> {
+    slots: [u64; N],
+    len: usize,
+}
+
+impl<const N: usize> Generation<N> {
+    const fn new() -> Self {
+        Self {
+            slots: [EMPTY; N],
+            len: 0,
+        }
+    }
+
+    /// Returns the slot holding `hash`, or else the empty slot it would go in
+    fn probe(&self, hash: u64) -> (usize, bool) {
+        let mut i = hash_to_bucket(hash, N);
+        loop {
+            match self.slots[i] {
+                EMPTY => return (i, false),
+                h if h == hash => return (i, true),
+                _ => i = (i + 1) % N,
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.slots = [EMPTY; N];
+        self.len = 0;
+    }
+}
+
+/// A fixed-size window of recently seen key hashes, for suppressing duplicate events
+///
+/// The set keeps two generations, each an open-addressed table of `N` slots. Hashes go into the
+/// current generation; once it holds `N / 2` of them it becomes the previous generation, and the
+/// old previous one is forgotten. A hash found in the previous generation is copied into the
+/// current one, so keys that keep recurring are kept. Nothing allocates, and the set takes a
+/// little over `16 * N` bytes inline.
+///
+/// Membership is approximate in both directions:
+///
+/// - A key seen among the last `N / 2` distinct keys is always reported as present, but a key
+///   further back than that may already have been forgotten, and one further back than `N`
+///   distinct keys always has been.
+/// - A key never seen is reported as present only if its 64-bit hash collides with that of a
+///   key that was. The hash `0` is stored as another value, so it collides with that value too.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes, RecentSet};
+///
+/// let mut recent = RecentSet::<1024>::new();
+/// let event = hash_bytes(b"disk full on /dev/sda1", 0).0;
+/// assert!(!recent.check_and_insert(event));
+/// assert!(recent.check_and_insert(event));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecentSet<const N: usize> {
+    current: Generation<N>,
+    previous: Generation<N>,
+}
+
+impl<const N: usize> RecentSet<N> {
+    /// Creates an empty set
+    ///
+    /// Fails to compile unless `N` is at least 2.
+    pub const fn new() -> Self {
+        const { assert!(N >= 2, "a RecentSet needs at least 2 slots") };
+        Self {
+            current: Generation::new(),
+            previous: Generation::new(),
+        }
+    }
+
+    /// Returns `true` if `hash` was seen recently
+    pub fn contains(&self, hash: u64) -> bool {
+        let hash = stored(hash);
+        self.current.probe(hash).1 || self.previous.probe(hash).1
+    }
+
+    /// Records `hash` as seen, returning whether it had been seen recently
+    pub fn check_and_insert(&mut self, hash: u64) -> bool {
+        let hash = stored(hash);
+        let (slot, found) = self.current.probe(hash);
+        if found {
+            return true;
+        }
+        let seen = self.previous.probe(hash).1;
+        if self.current.len < N / 2 {
+            self.current.slots[slot] = hash;
+            self.current.len += 1;
+        } else {
+            core::mem::swap(&mut self.current, &mut self.previous);
+            self.current.clear();
+            let (slot, _) = self.current.probe(hash);
+            self.current.slots[slot] = hash;
+            self.current.len = 1;
+        }
+        seen
+    }
+
+    /// Forgets every hash
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.previous.clear();
+    }
+}
+
+impl<const N: usize> Default for RecentSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn stored(hash: u64) -> u64 {
+    if hash == EMPTY {
+        ZERO_HASH
+    } else {
+        hash
+    }
+}
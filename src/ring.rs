@@ -0,0 +1,246 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::output::{hash_bytes, hash_combine, DEFAULT_SEED};
+
+/// A consistent hash ring, which maps keys to nodes such that adding or removing a node only
+/// moves the keys that belong to it.
+///
+/// Each node is placed at `replicas` points around a ring of `u64` positions, point `r` being
+/// `hash_combine(hash_bytes(node, DEFAULT_SEED), r)`, and a key belongs to the node owning the
+/// first point at or after the key's [`hash_bytes`] position, wrapping around. More replicas
+/// even out the share of the ring each node owns at the cost of memory.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::HashRing;
+///
+/// let mut ring = HashRing::new(64);
+/// ring.add("cache-a");
+/// ring.add("cache-b");
+/// let owner = *ring.node_for(b"user:42").unwrap();
+/// ring.add("cache-c");
+/// let moved = *ring.node_for(b"user:42").unwrap();
+/// assert!(moved == owner || moved == "cache-c");
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashRing<N> {
+    replicas: u64,
+    points: Vec<(u64, N)>,
+}
+
+impl<N: AsRef<[u8]> + Ord + Clone> HashRing<N> {
+    /// Creates an empty ring placing each node at `replicas` points
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replicas` is zero.
+    pub fn new(replicas: usize) -> Self {
+        assert_ne!(replicas, 0, "a node needs at least one point on the ring");
+        Self {
+            replicas: replicas as u64,
+            points: Vec::new(),
+        }
+    }
+
+    /// Adds `node` to the ring, returning `false` if it was already there
+    pub fn add(&mut self, node: N) -> bool {
+        if self.contains(&node) {
+            return false;
+        }
+        let base = hash_bytes(node.as_ref(), DEFAULT_SEED).0;
+        self.points
+            .extend((0..self.replicas).map(|r| (hash_combine(base, r), node.clone())));
+        self.points.sort_unstable();
+        true
+    }
+
+    /// Removes `node` from the ring, returning `false` if it wasn't there
+    pub fn remove(&mut self, node: &N) -> bool {
+        let len = self.points.len();
+        self.points.retain(|(_, n)| n != node);
+        self.points.len() != len
+    }
+
+    /// Returns `true` if `node` is on the ring
+    pub fn contains(&self, node: &N) -> bool {
+        self.points.iter().any(|(_, n)| n == node)
+    }
+
+    /// Returns the number of nodes on the ring
+    pub fn len(&self) -> usize {
+        self.points.len() / self.replicas as usize
+    }
+
+    /// Returns `true` if there are no nodes on the ring
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Iterates over the nodes on the ring, in no particular order
+    pub fn nodes(&self) -> impl Iterator<Item = &N> {
+        let mut seen = Vec::new();
+        self.points.iter().filter_map(move |(_, n)| {
+            if seen.contains(&n) {
+                None
+            } else {
+                seen.push(n);
+                Some(n)
+            }
+        })
+    }
+
+    /// Returns the node `key` belongs to, or [`None`] if the ring is empty
+    pub fn node_for(&self, key: &[u8]) -> Option<&N> {
+        self.successors(key).next()
+    }
+
+    /// Iterates over every node once, in the order met walking the ring from `key`'s position,
+    /// starting with the node `key` belongs to
+    pub fn successors(&self, key: &[u8]) -> impl Iterator<Item = &N> {
+        let position = hash_bytes(key, DEFAULT_SEED).0;
+        let start = self.points.partition_point(|(point, _)| *point < position);
+        let (after, before) = self.points.split_at(start);
+        let mut seen = Vec::new();
+        before.iter().chain(after).filter_map(move |(_, n)| {
+            if seen.contains(&n) {
+                None
+            } else {
+                seen.push(n);
+                Some(n)
+            }
+        })
+    }
+}
+
+/// Consistent hashing with bounded loads: a [`HashRing`] that passes a key on to the next node
+/// around the ring whenever its own node is already at its share of the load.
+///
+/// A node's bound is `ceil(c * (total + 1) / nodes)`, where `total` is the load across all
+/// nodes before the new key is placed and `c >= 1` is the capacity factor, so no node ever
+/// holds more than `c` times the average. With `c` large enough no node is ever full and keys
+/// go where the plain ring puts them. The choice depends only on the ring and the loads, so
+/// the same loads always give the same node.
+///
+/// Loads can come from anywhere through [`Self::assign`], or be tracked by the ring itself
+/// with [`Self::place`] and [`Self::release`].
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::BoundedLoadRing;
+///
+/// let mut ring = BoundedLoadRing::new(64, 1.25);
+/// for node in ["a", "b", "c", "d"] {
+///     ring.add(node);
+/// }
+/// for key in 0..1000u32 {
+///     ring.place(&key.to_le_bytes());
+/// }
+/// assert!(ring.loads().all(|(_, &load)| load <= 313));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BoundedLoadRing<N> {
+    ring: HashRing<N>,
+    capacity_factor: f64,
+    loads: BTreeMap<N, usize>,
+}
+
+impl<N: AsRef<[u8]> + Ord + Clone> BoundedLoadRing<N> {
+    /// Creates an empty ring placing each node at `replicas` points, whose nodes may hold up to
+    /// `capacity_factor` times the average load
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replicas` is zero or `capacity_factor` is less than 1.
+    pub fn new(replicas: usize, capacity_factor: f64) -> Self {
+        let mut ring = Self {
+            ring: HashRing::new(replicas),
+            capacity_factor: 1.0,
+            loads: BTreeMap::new(),
+        };
+        ring.set_capacity_factor(capacity_factor);
+        ring
+    }
+
+    /// Returns the underlying [`HashRing`]
+    pub fn ring(&self) -> &HashRing<N> {
+        &self.ring
+    }
+
+    /// Returns the capacity factor
+    pub fn capacity_factor(&self) -> f64 {
+        self.capacity_factor
+    }
+
+    /// Sets the capacity factor, affecting keys assigned from now on
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity_factor` is less than 1, since the nodes couldn't hold every key.
+    pub fn set_capacity_factor(&mut self, capacity_factor: f64) {
+        assert!(
+            capacity_factor >= 1.0,
+            "the capacity factor must be at least 1"
+        );
+        self.capacity_factor = capacity_factor;
+    }
+
+    /// Adds `node` with no load, returning `false` if it was already there
+    pub fn add(&mut self, node: N) -> bool {
+        self.loads.entry(node.clone()).or_insert(0);
+        self.ring.add(node)
+    }
+
+    /// Removes `node` and forgets its load, returning `false` if it wasn't there
+    pub fn remove(&mut self, node: &N) -> bool {
+        self.loads.remove(node);
+        self.ring.remove(node)
+    }
+
+    /// Returns the most load a node may hold before a key is placed, when `total` is the load
+    /// across all nodes
+    pub fn bound(&self, total: usize) -> usize {
+        let nodes = self.ring.len().max(1) as f64;
+        let bound = self.capacity_factor * (total as f64 + 1.0) / nodes;
+        let floor = bound as usize;
+        if (floor as f64) < bound {
+            floor + 1
+        } else {
+            floor
+        }
+    }
+
+    /// Returns the node `key` should go to given the current load of each node, as reported by
+    /// `load`, without recording anything. Returns [`None`] if the ring is empty.
+    pub fn assign(&self, key: &[u8], load: impl Fn(&N) -> usize) -> Option<&N> {
+        let total = self.loads.keys().map(&load).sum();
+        let bound = self.bound(total);
+        self.ring.successors(key).find(|node| load(node) < bound)
+    }
+
+    /// Assigns `key` using the loads tracked by the ring and adds one to its node's load
+    pub fn place(&mut self, key: &[u8]) -> Option<&N> {
+        let node = self.assign(key, |n| self.load(n))?.clone();
+        *self.loads.get_mut(&node).expect("every node has a load") += 1;
+        self.loads.get_key_value(&node).map(|(node, _)| node)
+    }
+
+    /// Subtracts one from the tracked load of `node`, once a key placed on it is gone
+    pub fn release(&mut self, node: &N) {
+        if let Some(load) = self.loads.get_mut(node) {
+            *load = load.saturating_sub(1);
+        }
+    }
+
+    /// Returns the tracked load of `node`
+    pub fn load(&self, node: &N) -> usize {
+        self.loads.get(node).copied().unwrap_or(0)
+    }
+
+    /// Iterates over every node and its tracked load, in the nodes' order
+    pub fn loads(&self) -> impl Iterator<Item = (&N, &usize)> {
+        self.loads.iter()
+    }
+}
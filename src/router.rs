@@ -0,0 +1,340 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::seed::Seed;
+use crate::shard::ShardSelector;
+
+/// How long [`ShardRouter::drain`] sleeps between checks of the queue depths
+const DRAIN_POLL: Duration = Duration::from_micros(100);
+
+/// Why a [`ShardSink`] refused an item, handing it back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkError<T> {
+    /// The queue is at capacity
+    Full(T),
+    /// Nothing reads the queue any more
+    Closed(T),
+}
+
+/// A bounded queue a [`ShardRouter`] can offer items to without blocking
+///
+/// [`ShardSender`] implements it over a [`std::sync::mpsc::sync_channel`]. Implement it over
+/// another channel, such as crossbeam's, to route into that instead.
+pub trait ShardSink<T> {
+    /// Offers `item` to the queue, handing it back if the queue is full or closed
+    fn try_send(&self, item: T) -> Result<(), SinkError<T>>;
+
+    /// Returns how many items are queued and not yet taken
+    fn depth(&self) -> usize;
+}
+
+/// Creates a bounded queue holding at most `capacity` items, whose sending half is a
+/// [`ShardSink`] that reports its depth
+///
+/// A `capacity` of zero makes a rendezvous queue, which only accepts an item while a receiver
+/// is blocked waiting for one.
+pub fn shard_queue<T>(capacity: usize) -> (ShardSender<T>, ShardReceiver<T>) {
+    let (tx, rx) = mpsc::sync_channel(capacity);
+    let depth = Arc::new(AtomicUsize::new(0));
+    (
+        ShardSender {
+            tx,
+            depth: depth.clone(),
+        },
+        ShardReceiver { rx, depth },
+    )
+}
+
+/// The sending half of a [`shard_queue`]
+#[derive(Debug)]
+pub struct ShardSender<T> {
+    tx: SyncSender<T>,
+    depth: Arc<AtomicUsize>,
+}
+
+impl<T> Clone for ShardSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            depth: self.depth.clone(),
+        }
+    }
+}
+
+impl<T> ShardSink<T> for ShardSender<T> {
+    fn try_send(&self, item: T) -> Result<(), SinkError<T>> {
+        // Counted before the send so that the receiver, which uncounts after taking the item,
+        // never sees it first
+        self.depth.fetch_add(1, Ordering::AcqRel);
+        self.tx.try_send(item).map_err(|e| {
+            self.depth.fetch_sub(1, Ordering::AcqRel);
+            match e {
+                TrySendError::Full(item) => SinkError::Full(item),
+                TrySendError::Disconnected(item) => SinkError::Closed(item),
+            }
+        })
+    }
+
+    fn depth(&self) -> usize {
+        self.depth.load(Ordering::Acquire)
+    }
+}
+
+/// The receiving half of a [`shard_queue`], yielding items in the order they were sent until
+/// every sender is gone and the queue is empty
+#[derive(Debug)]
+pub struct ShardReceiver<T> {
+    rx: Receiver<T>,
+    depth: Arc<AtomicUsize>,
+}
+
+impl<T> ShardReceiver<T> {
+    /// Waits for the next item, returning `None` once every sender is gone and the queue is
+    /// empty
+    pub fn recv(&self) -> Option<T> {
+        let item = self.rx.recv().ok()?;
+        self.depth.fetch_sub(1, Ordering::AcqRel);
+        Some(item)
+    }
+
+    /// Returns the next item if one is queued, without waiting
+    pub fn try_recv(&self) -> Option<T> {
+        let item = self.rx.try_recv().ok()?;
+        self.depth.fetch_sub(1, Ordering::AcqRel);
+        Some(item)
+    }
+
+    /// Returns how many items are queued and not yet taken
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Iterator for ShardReceiver<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.recv()
+    }
+}
+
+/// Why [`ShardRouter::send`] didn't route an item, handing it back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError<T> {
+    /// The item's shard is at capacity. The item is not offered to any other shard, so that
+    /// every item with the same key still reaches its shard in order.
+    Full {
+        /// The shard the item belongs to
+        shard: usize,
+        /// The item
+        item: T,
+    },
+    /// Nothing reads the item's shard any more
+    Closed {
+        /// The shard the item belongs to
+        shard: usize,
+        /// The item
+        item: T,
+    },
+}
+
+impl<T> SendError<T> {
+    /// Returns the shard the item belongs to
+    pub fn shard(&self) -> usize {
+        match self {
+            Self::Full { shard, .. } | Self::Closed { shard, .. } => *shard,
+        }
+    }
+
+    /// Returns the item that wasn't routed
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full { item, .. } | Self::Closed { item, .. } => item,
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full { shard, .. } => write!(f, "shard {shard} is full"),
+            Self::Closed { shard, .. } => write!(f, "shard {shard} is closed"),
+        }
+    }
+}
+
+/// A hook a [`ShardRouter`] calls with a shard and its depth whenever the shard refuses an item
+/// for being full
+type FullHook = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Routes a stream of items to `N` bounded queues, each item to the queue of its key's shard
+///
+/// The key `key` extracts from an item is assigned a shard by a [`ShardSelector`] over `N`
+/// shards, so every item with the same key goes to the same queue, in the order it was sent,
+/// for the router's lifetime. [`send`](Self::send) never blocks: an item whose queue is full is
+/// handed back rather than sent elsewhere, leaving the caller to retry, drop or spill it, and
+/// [`on_full`](Self::on_full) registers a hook to hear about it. The router can be shared
+/// between producer threads by reference.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{SendError, ShardRouter};
+///
+/// #[derive(Debug)]
+/// struct Event {
+///     user: u64,
+///     action: &'static str,
+/// }
+///
+/// let (router, receivers) =
+///     ShardRouter::<Event, 4, _>::new(7, 2, |e: &Event| e.user.to_le_bytes());
+/// let shard = router.shard_of(&Event { user: 1, action: "" });
+/// router.send(Event { user: 1, action: "login" }).unwrap();
+/// router.send(Event { user: 1, action: "click" }).unwrap();
+/// assert_eq!(router.depth(shard), 2);
+///
+/// // Backpressure is the caller's to handle: the full shard hands the event back
+/// let err = router.send(Event { user: 1, action: "logout" }).unwrap_err();
+/// assert!(matches!(err, SendError::Full { .. }));
+/// assert_eq!(err.into_inner().action, "logout");
+///
+/// // Dropping the router closes the queues, and workers still get what was queued
+/// drop(router);
+/// let worker = receivers.into_iter().nth(shard).unwrap();
+/// let actions: Vec<_> = worker.map(|e| e.action).collect();
+/// assert_eq!(actions, ["login", "click"]);
+/// ```
+pub struct ShardRouter<T, const N: usize, F, S = ShardSender<T>> {
+    selector: ShardSelector,
+    key: F,
+    sinks: [S; N],
+    routed: [AtomicUsize; N],
+    on_full: Option<FullHook>,
+    _items: PhantomData<fn(T)>,
+}
+
+impl<T, const N: usize, F, S: fmt::Debug> fmt::Debug for ShardRouter<T, N, F, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardRouter")
+            .field("selector", &self.selector)
+            .field("sinks", &self.sinks)
+            .field("routed", &self.routed)
+            .field("on_full", &self.on_full.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, const N: usize, F, K> ShardRouter<T, N, F>
+where
+    F: Fn(&T) -> K,
+    K: AsRef<[u8]>,
+{
+    /// Creates a router over `N` new [`shard_queue`]s of `capacity` items each, returning it
+    /// with the queues' receivers in shard order
+    pub fn new(seed: impl Into<Seed>, capacity: usize, key: F) -> (Self, [ShardReceiver<T>; N]) {
+        let mut receivers = Vec::with_capacity(N);
+        let sinks = core::array::from_fn(|_| {
+            let (tx, rx) = shard_queue(capacity);
+            receivers.push(rx);
+            tx
+        });
+        let Ok(receivers) = receivers.try_into() else {
+            unreachable!("one receiver per shard")
+        };
+        (Self::with_sinks(seed, sinks, key), receivers)
+    }
+}
+
+impl<T, const N: usize, F, K, S> ShardRouter<T, N, F, S>
+where
+    F: Fn(&T) -> K,
+    K: AsRef<[u8]>,
+    S: ShardSink<T>,
+{
+    /// Creates a router over the given sinks, the `i`th receiving the items of shard `i`
+    pub fn with_sinks(seed: impl Into<Seed>, sinks: [S; N], key: F) -> Self {
+        const { assert!(N > 0, "a ShardRouter needs at least one shard") };
+        Self {
+            selector: ShardSelector::new(NonZeroUsize::new(N).unwrap(), seed),
+            key,
+            sinks,
+            routed: core::array::from_fn(|_| AtomicUsize::new(0)),
+            on_full: None,
+            _items: PhantomData,
+        }
+    }
+
+    /// Calls `hook` with the shard and its depth whenever [`send`](Self::send) finds a shard
+    /// full, before handing the item back, e.g. to count or log stalls
+    pub fn on_full(mut self, hook: impl Fn(usize, usize) + Send + Sync + 'static) -> Self {
+        self.on_full = Some(Box::new(hook));
+        self
+    }
+
+    /// Returns the selector that assigns keys to shards
+    pub fn selector(&self) -> ShardSelector {
+        self.selector
+    }
+
+    /// Returns the shard `item` is routed to
+    pub fn shard_of(&self, item: &T) -> usize {
+        self.selector.select((self.key)(item).as_ref())
+    }
+
+    /// Offers `item` to its shard's queue without blocking, handing it back if the queue is
+    /// full or closed
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        let shard = self.shard_of(&item);
+        match self.sinks[shard].try_send(item) {
+            Ok(()) => {
+                self.routed[shard].fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(SinkError::Full(item)) => {
+                if let Some(hook) = &self.on_full {
+                    hook(shard, self.sinks[shard].depth());
+                }
+                Err(SendError::Full { shard, item })
+            }
+            Err(SinkError::Closed(item)) => Err(SendError::Closed { shard, item }),
+        }
+    }
+
+    /// Returns how many items are queued for `shard` and not yet taken
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard` is not below `N`.
+    pub fn depth(&self, shard: usize) -> usize {
+        self.sinks[shard].depth()
+    }
+
+    /// Returns how many items are queued for each shard and not yet taken
+    pub fn depths(&self) -> [usize; N] {
+        core::array::from_fn(|shard| self.sinks[shard].depth())
+    }
+
+    /// Returns how many items have been routed to each shard
+    pub fn routed(&self) -> [usize; N] {
+        core::array::from_fn(|shard| self.routed[shard].load(Ordering::Relaxed))
+    }
+
+    /// Shuts the router down: waits until every queued item has been taken, then drops the
+    /// sinks, so that receivers end once they're empty. Returns how many items were routed to
+    /// each shard.
+    ///
+    /// This waits forever if a shard's items are never taken, e.g. because its worker has
+    /// stopped. Drop the router instead to close the queues without waiting.
+    pub fn drain(self) -> [usize; N] {
+        while self.sinks.iter().any(|sink| sink.depth() > 0) {
+            std::thread::sleep(DRAIN_POLL);
+        }
+        self.routed()
+    }
+}
@@ -0,0 +1,228 @@
+use crate::hasher::DEFAULT_HASHER_STATE;
+use crate::output::{hash_bytes, hash_to_bucket, hash_u64};
+use crate::sequence::HashSequence;
+
+/// Deterministically decides whether `key` is included in a sample taken at `rate`.
+///
+/// The seeded, finalized hash of `key` is mapped onto the unit interval `[0, 1)` and the key is
+/// included if it lands below `rate`, so every host makes the same decision for the same
+/// `(key, rate, seed)`. A `rate` of `0.0` or less never includes a key and a `rate` of `1.0` or
+/// more always does.
+///
+/// # Panics
+///
+/// Panics if `rate` is NaN.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::sample_by_hash;
+///
+/// let seed = 0x5EED;
+/// let included = sample_by_hash(b"request-1234", 0.25, seed);
+/// assert_eq!(included, sample_by_hash(b"request-1234", 0.25, seed));
+/// assert!(!sample_by_hash(b"request-1234", 0.0, seed));
+/// assert!(sample_by_hash(b"request-1234", 1.0, seed));
+/// ```
+pub fn sample_by_hash(key: &[u8], rate: f64, seed: u64) -> bool {
+    below_rate(seeded_hash(key, seed), rate)
+}
+
+/// Deterministically decides whether the word sized `key` is included in a sample taken at `rate`.
+///
+/// See [`sample_by_hash`] for the behavior at the edges of `rate`.
+///
+/// # Panics
+///
+/// Panics if `rate` is NaN.
+pub fn sample_by_hash_word(key: u64, rate: f64, seed: u64) -> bool {
+    below_rate(hash_u64(key, seed).0, rate)
+}
+
+/// Deterministically includes `k` of every `n` keys, for "1 in 100" style sampling rules.
+///
+/// `k >= n` includes every key and `k == 0` includes none.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::sample_modulo;
+///
+/// assert!(sample_modulo(b"request-1234", 10, 10, 0));
+/// assert!(!sample_modulo(b"request-1234", 10, 0, 0));
+/// ```
+pub fn sample_modulo(key: &[u8], n: u64, k: u64, seed: u64) -> bool {
+    assert_ne!(n, 0, "sample_modulo requires a nonzero n");
+    let slot = ((seeded_hash(key, seed) as u128 * n as u128) >> 64) as u64;
+    slot < k
+}
+
+/// Errors returned by [`ab_bucket`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketError {
+    /// No variants were provided
+    NoVariants,
+    /// Every variant has a weight of zero
+    ZeroWeight,
+}
+
+impl core::fmt::Display for BucketError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoVariants => f.write_str("no variants to assign to"),
+            Self::ZeroWeight => f.write_str("all variant weights are zero"),
+        }
+    }
+}
+
+/// Assigns `unit_id` to one of the weighted variants of `experiment`, returning the variant's index.
+///
+/// The experiment name is hashed into the seed, so the same units are assigned independently
+/// across experiments. Variant `i` receives a share of units proportional to `weights[i]`, chosen
+/// with integer arithmetic only, and variants with a weight of zero are never chosen.
+///
+/// The assignment is stable: a given `(unit_id, experiment, weights)` maps to the same variant
+/// in every process and in every future version of this crate.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::ab_bucket;
+///
+/// // A 90/10 split between control and treatment
+/// let variant = ab_bucket(b"user-42", "new-checkout", &[90, 10]).unwrap();
+/// assert!(variant < 2);
+/// ```
+pub fn ab_bucket(unit_id: &[u8], experiment: &str, weights: &[u32]) -> Result<usize, BucketError> {
+    if weights.is_empty() {
+        return Err(BucketError::NoVariants);
+    }
+    let seed = seeded_hash(experiment.as_bytes(), DEFAULT_HASHER_STATE);
+    weighted_index(
+        seeded_hash(unit_id, seed),
+        weights.iter().map(|&w| w as u128),
+    )
+    .ok_or(BucketError::ZeroWeight)
+}
+
+/// Picks an index into `weights` with probability proportional to its weight, deterministically
+/// for a given `key_hash`.
+///
+/// `key_hash` is mapped onto the total weight by its position in the `u64` range, so it should be
+/// a well mixed hash such as the output of [`weighted_choice_key`]'s hashing. The arithmetic is
+/// done in 128 bits, so the weights may be as large as `u64::MAX` without overflowing.
+///
+/// Returns `None` if the total weight is zero. Indices with a weight of zero are never chosen.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::weighted_choice;
+///
+/// assert_eq!(weighted_choice(0, &[0, 3, 1]), Some(1));
+/// assert_eq!(weighted_choice(u64::MAX, &[0, 3, 1]), Some(2));
+/// assert_eq!(weighted_choice(12345, &[0, 0]), None);
+/// ```
+pub fn weighted_choice(key_hash: u64, weights: &[u64]) -> Option<usize> {
+    weighted_index(key_hash, weights.iter().map(|&w| w as u128))
+}
+
+/// Hashes `key` with `seed` and picks an index into `weights` with [`weighted_choice`].
+pub fn weighted_choice_key(key: &[u8], weights: &[u64], seed: u64) -> Option<usize> {
+    weighted_choice(seeded_hash(key, seed), weights)
+}
+
+/// Maps `hash` onto the cumulative ranges of `weights`
+fn weighted_index(hash: u64, mut weights: impl Iterator<Item = u128> + Clone) -> Option<usize> {
+    let total: u128 = weights.clone().sum();
+    if total == 0 {
+        return None;
+    }
+    // floor(hash * total / 2^64), split so that the product can't overflow
+    let point = hash as u128 * (total >> 64) + ((hash as u128 * (total as u64 as u128)) >> 64);
+    let mut end = 0;
+    weights.position(|w| {
+        end += w;
+        point < end
+    })
+}
+
+/// The seeded, finalized hash of `bytes`
+fn seeded_hash(bytes: &[u8], seed: u64) -> u64 {
+    hash_bytes(bytes, seed).0
+}
+
+fn below_rate(hash: u64, rate: f64) -> bool {
+    assert!(!rate.is_nan(), "sampling rate must not be NaN");
+    // The top 53 bits are exactly representable, so the mapping never rounds up to 1.0
+    let unit = (hash >> 11) as f64 * (1.0 / (1u64 << 53) as f64);
+    unit < rate
+}
+
+/// The index Fisher–Yates swaps with position `i` when shuffling with `sequence`
+fn swap_index(sequence: &HashSequence, i: usize) -> usize {
+    hash_to_bucket(sequence.get(i as u64), i + 1)
+}
+
+/// Shuffles `slice` in place into a permutation determined entirely by `seed` and its length.
+///
+/// This is a Fisher–Yates shuffle whose swap indices are drawn from a [`HashSequence`] with
+/// 64-bit arithmetic, so the same `(len, seed)` produces the same permutation on every platform,
+/// without allocating.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::consistent_shuffle;
+///
+/// let mut a = [1, 2, 3, 4, 5];
+/// let mut b = a;
+/// consistent_shuffle(&mut a, 7);
+/// consistent_shuffle(&mut b, 7);
+/// assert_eq!(a, b);
+/// ```
+pub fn consistent_shuffle<T>(slice: &mut [T], seed: u64) {
+    let sequence = HashSequence::new(seed);
+    for i in (1..slice.len()).rev() {
+        slice.swap(i, swap_index(&sequence, i));
+    }
+}
+
+/// Returns the index of the element that [`consistent_shuffle`] would move to position `i` of a
+/// slice of length `len`, without shuffling anything.
+///
+/// After `consistent_shuffle(&mut shuffled, seed)`, `shuffled[i]` is the element that was at
+/// `shuffled_index(i, shuffled.len(), seed)`. This takes time proportional to `len` but no
+/// memory.
+///
+/// # Panics
+///
+/// Panics if `i >= len`.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{consistent_shuffle, shuffled_index};
+///
+/// let original = [10, 20, 30, 40];
+/// let mut shuffled = original;
+/// consistent_shuffle(&mut shuffled, 3);
+/// assert_eq!(shuffled[1], original[shuffled_index(1, 4, 3)]);
+/// ```
+pub fn shuffled_index(i: usize, len: usize, seed: u64) -> usize {
+    assert!(i < len, "index {i} out of range for length {len}");
+    let sequence = HashSequence::new(seed);
+    // Follow position `i` back through the swaps in the reverse of the order they were made
+    (1..len).fold(i, |pos, k| {
+        let j = swap_index(&sequence, k);
+        match pos {
+            p if p == k => j,
+            p if p == j => k,
+            p => p,
+        }
+    })
+}
@@ -0,0 +1,251 @@
+use core::fmt;
+use core::hash::Hasher;
+use core::num::IntErrorKind;
+use core::str::FromStr;
+
+use crate::hasher::{CMHasher, DEFAULT_HASHER_STATE, DEFAULT_PRIME};
+use crate::mixer::Fmix64;
+
+/// A seed for the hashers in this crate
+///
+/// Seeded constructors take `impl Into<Seed>`, so a plain `u64` can be passed wherever a [`Seed`]
+/// is expected. A [`Seed`] can also be parsed from configuration with [`FromStr`], which accepts:
+///
+/// - decimal, e.g. `"42"`
+/// - `0x`-prefixed hex of up to 16 digits, e.g. `"0x2a"`
+/// - exactly 16 bare hex digits, e.g. `"000000000000002a"`, which is the form [`Display`]
+///   writes
+///
+/// A string of 16 decimal digits is also 16 hex digits, so it is read as hex. Prefix a 16-digit
+/// decimal seed with `+` to have it read as decimal.
+///
+/// [`Display`]: fmt::Display
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::Seed;
+///
+/// let seed: Seed = "0x2a".parse().unwrap();
+/// assert_eq!(seed, Seed::from_u64(42));
+/// assert_eq!(seed.to_string(), "000000000000002a");
+/// assert_eq!(seed.to_string().parse::<Seed>(), Ok(seed));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Seed(pub u64);
+
+impl Seed {
+    /// Wraps `seed`
+    pub const fn from_u64(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Returns the seed as a word
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Derives a seed from arbitrary bytes, such as a passphrase or a configuration name
+    ///
+    /// The bytes are hashed little-endian from the default state and finalized with [`Fmix64`],
+    /// so a given input yields the same seed on every platform and in every version of this
+    /// crate.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut h = CMHasher::configured(DEFAULT_HASHER_STATE, Fmix64, DEFAULT_PRIME, true);
+        h.write(bytes);
+        Self(h.finish())
+    }
+
+    /// Draws a seed from the operating system's random number generator
+    #[cfg(feature = "getrandom")]
+    pub fn random() -> Result<Self, getrandom::Error> {
+        getrandom::u64().map(Self)
+    }
+
+    /// Derives a seed from the CPU's cycle or timer counter, for `no_std` targets without an
+    /// operating system to ask for randomness
+    ///
+    /// The counter is `rdtsc` on `x86` and `x86_64`, `CNTVCT_EL0` on `aarch64`, and `rdcycle` on
+    /// RISC-V. On any other architecture the method doesn't exist, even with the `hw-entropy`
+    /// feature, so that the feature can stay on in builds for every target.
+    /// Several readings are taken, each fed through the round function and followed by a spin of
+    /// a length that depends on the state so far, and the result is finalized with [`Fmix64`].
+    /// The entropy is in the low bits of the readings: how long boot took, interrupts, cache and
+    /// memory timing.
+    ///
+    /// This is enough that someone who can't observe the machine can't precompute a set of keys
+    /// that collide under the seed, which is what seeding hash tables needs. It is not a secret:
+    /// anyone who can read the counter, or estimate it closely, for example from the uptime, can
+    /// narrow the seed down to a range they can search. Use [`Self::random`] where an operating
+    /// system is available, and keep keys that must stay secret away from this.
+    ///
+    /// On RISC-V, `rdcycle` traps in Linux user space unless the kernel allows it; this is meant
+    /// for code running without an operating system, or in the kernel.
+    #[cfg(all(
+        feature = "hw-entropy",
+        any(
+            target_arch = "x86_64",
+            target_arch = "x86",
+            target_arch = "aarch64",
+            target_arch = "riscv32",
+            target_arch = "riscv64"
+        )
+    ))]
+    pub fn from_hardware_counter() -> Self {
+        let mut state = DEFAULT_HASHER_STATE;
+        let mut acc = 0;
+        for _ in 0..HW_READINGS {
+            let (hash, next) = crate::word::round(state, hw::counter());
+            acc ^= hash;
+            state = next;
+            // Space the next reading out by a varying amount of work
+            for _ in 0..(hash >> 58) {
+                core::hint::spin_loop();
+            }
+        }
+        Self(crate::hasher::fmix64(acc ^ state))
+    }
+}
+
+/// The number of counter readings [`Seed::from_hardware_counter`] mixes
+#[cfg(all(
+    feature = "hw-entropy",
+    any(
+        target_arch = "x86_64",
+        target_arch = "x86",
+        target_arch = "aarch64",
+        target_arch = "riscv32",
+        target_arch = "riscv64"
+    )
+))]
+const HW_READINGS: usize = 8;
+
+#[cfg(all(
+    feature = "hw-entropy",
+    any(
+        target_arch = "x86_64",
+        target_arch = "x86",
+        target_arch = "aarch64",
+        target_arch = "riscv32",
+        target_arch = "riscv64"
+    )
+))]
+mod hw {
+    #[cfg(target_arch = "x86_64")]
+    pub(super) fn counter() -> u64 {
+        // SAFETY: `rdtsc` is available on every x86_64 CPU
+        unsafe { core::arch::x86_64::_rdtsc() }
+    }
+
+    #[cfg(target_arch = "x86")]
+    pub(super) fn counter() -> u64 {
+        // SAFETY: `rdtsc` is available on every CPU Rust supports as `x86`
+        unsafe { core::arch::x86::_rdtsc() }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    pub(super) fn counter() -> u64 {
+        let count: u64;
+        // SAFETY: the virtual count register is readable at EL0 and above on Linux, macOS and
+        // Windows, and by firmware and kernels themselves
+        unsafe {
+            core::arch::asm!(
+                "mrs {count}, cntvct_el0",
+                count = out(reg) count,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        count
+    }
+
+    #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+    pub(super) fn counter() -> u64 {
+        let count: usize;
+        // SAFETY: reading the cycle CSR has no side effects; see the RISC-V note on
+        // `Seed::from_hardware_counter`
+        unsafe {
+            core::arch::asm!(
+                "rdcycle {count}",
+                count = out(reg) count,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        count as u64
+    }
+}
+
+impl From<u64> for Seed {
+    fn from(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl From<Seed> for u64 {
+    fn from(seed: Seed) -> Self {
+        seed.0
+    }
+}
+
+/// Writes the seed as 16 zero-padded lowercase hex digits
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl fmt::LowerHex for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+/// The reason a string could not be parsed as a [`Seed`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseSeedError {
+    /// The string, or the digits after `0x`, was empty
+    Empty,
+    /// The string contained a character that isn't a digit of its base
+    InvalidDigit,
+    /// The value doesn't fit in a `u64`
+    Overflow,
+}
+
+impl fmt::Display for ParseSeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("cannot parse a seed from an empty string"),
+            Self::InvalidDigit => f.write_str("invalid digit in seed"),
+            Self::Overflow => f.write_str("seed does not fit in 64 bits"),
+        }
+    }
+}
+
+impl core::error::Error for ParseSeedError {}
+
+impl FromStr for Seed {
+    type Err = ParseSeedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, radix) = match s.strip_prefix("0x") {
+            Some(hex) => (hex, 16),
+            None if s.len() == 16 && s.bytes().all(|b| b.is_ascii_hexdigit()) => (s, 16),
+            None => (s, 10),
+        };
+        // `from_str_radix` accepts a sign, which only makes sense in decimal
+        if radix == 16 && digits.starts_with('+') {
+            return Err(ParseSeedError::InvalidDigit);
+        }
+        u64::from_str_radix(digits, radix)
+            .map(Self)
+            .map_err(|e| match e.kind() {
+                IntErrorKind::Empty => ParseSeedError::Empty,
+                IntErrorKind::PosOverflow => ParseSeedError::Overflow,
+                _ => ParseSeedError::InvalidDigit,
+            })
+    }
+}
@@ -0,0 +1,54 @@
+use crate::output::{hash_u64, DEFAULT_SEED};
+use crate::seed::Seed;
+
+/// A deterministic stream of well mixed words derived from a seed.
+///
+/// Item `i` of the sequence is [`hash_u64`]`(i, key)`, where `key` is
+/// [`hash_u64`]`(seed, DEFAULT_SEED)`, so the stream is the same on every run and any position
+/// can be reached directly with [`HashSequence::get`] or [`Iterator::nth`] without generating
+/// the words before it. Hashing the seed first keeps the streams of nearby seeds, like `4` and
+/// `5`, from being reorderings of each other.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::HashSequence;
+///
+/// let words: Vec<u64> = HashSequence::new(42).take(4).collect();
+/// assert_eq!(words[3], HashSequence::new(42).get(3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashSequence {
+    key: u64,
+    index: u64,
+}
+
+impl HashSequence {
+    /// Starts the sequence for `seed` at its first item
+    pub fn new(seed: impl Into<Seed>) -> Self {
+        Self {
+            key: hash_u64(seed.into().0, DEFAULT_SEED).0,
+            index: 0,
+        }
+    }
+
+    /// Returns item `index` of the sequence without advancing it
+    pub fn get(&self, index: u64) -> u64 {
+        hash_u64(index, self.key).0
+    }
+}
+
+impl Iterator for HashSequence {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let word = self.get(self.index);
+        self.index = self.index.wrapping_add(1);
+        Some(word)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<u64> {
+        self.index = self.index.wrapping_add(n as u64);
+        self.next()
+    }
+}
@@ -0,0 +1,106 @@
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+use core::sync::atomic::Ordering;
+
+use crate::hasher::{CMBuildHasher, CMHasher};
+use crate::output::hash_combine;
+use crate::seed::Seed;
+use crate::AtomicUsize;
+
+/// A [`BuildHasher`] whose forks each get the next seed of a sequence derived from one master
+/// seed, so every map in a run hashes differently but a rerun with the same master seed
+/// replays the same seeds
+///
+/// The builder returned by [`Self::new`] has index 0, and every fork, of it or of another
+/// fork, takes the next index from a counter they all share. The builder with index `i` hashes
+/// as a [`CMBuildHasher`] with the state [`Self::seed_for`]`(master_seed, i)`.
+///
+/// A clone keeps the index, and so the seed, of the builder it was cloned from, so a cloned
+/// map finds its keys like any other. Which index a fork gets depends only on how many forks
+/// were made before it, so for the sequence to replay, the forks must be made in the same
+/// order in every run.
+///
+/// # Examples
+///
+/// ```
+/// use core::hash::BuildHasher;
+/// use std::collections::HashMap;
+/// use cmhash::SequencedBuildHasher;
+///
+/// let run = |master_seed| {
+///     let root = SequencedBuildHasher::new(master_seed);
+///     let entities: HashMap<u32, &str, _> = HashMap::with_hasher(root.fork());
+///     let events: HashMap<u32, &str, _> = HashMap::with_hasher(root.fork());
+///     [entities.hasher().seed(), events.hasher().seed()]
+/// };
+/// let seeds = run(7);
+/// assert_ne!(seeds[0], seeds[1]);
+/// assert_eq!(run(7), seeds);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SequencedBuildHasher {
+    master_seed: u64,
+    next: Arc<AtomicUsize>,
+    index: usize,
+    inner: CMBuildHasher,
+}
+
+impl SequencedBuildHasher {
+    /// Starts a sequence from `master_seed`, returning its first builder, of index 0
+    pub fn new(master_seed: impl Into<Seed>) -> Self {
+        Self::at(master_seed.into().0, Arc::new(AtomicUsize::new(1)), 0)
+    }
+
+    fn at(master_seed: u64, next: Arc<AtomicUsize>, index: usize) -> Self {
+        Self {
+            master_seed,
+            next,
+            index,
+            inner: CMBuildHasher::with_state(Self::seed_for(master_seed, index)),
+        }
+    }
+
+    /// Returns the seed of the builder with index `index` in the sequence from `master_seed`
+    ///
+    /// This is [`hash_combine`]`(master_seed, index)`, and is stable across releases and
+    /// platforms, so a run can be replayed by a later build.
+    pub fn seed_for(master_seed: u64, index: usize) -> u64 {
+        hash_combine(master_seed, index as u64)
+    }
+
+    /// Returns the master seed of the sequence
+    pub fn master_seed(&self) -> u64 {
+        self.master_seed
+    }
+
+    /// Returns this builder's index in the sequence
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the seed this builder's hashers start from
+    pub fn seed(&self) -> u64 {
+        Self::seed_for(self.master_seed, self.index)
+    }
+
+    /// Returns the index the next fork made from any builder of the sequence will get, for
+    /// debugging a run that doesn't replay
+    pub fn peek_next_index(&self) -> usize {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    /// Returns the next builder of the sequence, which hashes differently from this one and
+    /// from every other builder of the sequence
+    pub fn fork(&self) -> Self {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        Self::at(self.master_seed, self.next.clone(), index)
+    }
+}
+
+impl BuildHasher for SequencedBuildHasher {
+    type Hasher = CMHasher;
+
+    fn build_hasher(&self) -> CMHasher {
+        self.inner.build_hasher()
+    }
+}
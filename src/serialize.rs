@@ -0,0 +1,518 @@
+use core::fmt::{self, Write};
+
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+
+use crate::fields::FieldHasher;
+use crate::hasher::WordBuffer;
+
+/// The error returned when a value's [`Serialize`] implementation fails
+///
+/// It carries no message, since building one would need an allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializeError;
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the value failed to serialize")
+    }
+}
+
+impl ser::StdError for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(_: T) -> Self {
+        Self
+    }
+}
+
+/// Hashes the structure of any [`Serialize`] value under `seed`, without buffering its
+/// serialized form.
+///
+/// The hash depends only on the data model serde exposes, not on how the value is laid out in
+/// memory. The rules are:
+///
+/// - Integers, `bool` and `char` are single words, signed integers sign-extended, so equal
+///   values of different integer types hash the same. `u128` and `i128` values beyond the range
+///   of `u64` and `i64` are two words, low then high. Floats are hashed by the bits of their
+///   `f64` value.
+/// - Strings and byte arrays hash their bytes and length. Strings produced with
+///   [`collect_str`](Serializer::collect_str) hash the same as the equal `&str`.
+/// - `None` and `Some(v)` are distinguished by a tag before `v`. Unit values and unit structs
+///   contribute nothing, and newtype structs hash as the value they wrap.
+/// - Sequences, tuples and tuple structs are **order-sensitive**: elements are hashed in order,
+///   along with their count.
+/// - Maps, structs and struct variants are **order-insensitive**: each entry or field is
+///   hashed on its own, key or field name together with value, and the entry hashes are summed.
+///   So reordering a struct's field declarations doesn't change its hash, a `HashMap` hashes the
+///   same whatever its iteration order and the same as a `BTreeMap` of the same entries, but
+///   swapping two fields' values or renaming a field does.
+/// - Enum variants are identified by name, so reordering variants doesn't change the hash.
+///   Type names are not hashed.
+///
+/// Serializers are told they are not human readable, so types with both forms use their compact
+/// one.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::{BTreeMap, HashMap};
+/// use cmhash::hash_serialize;
+///
+/// let hashed: HashMap<&str, u32> = [("a", 1), ("b", 2)].into();
+/// let sorted: BTreeMap<&str, u32> = [("b", 2), ("a", 1)].into();
+/// assert_eq!(hash_serialize(&hashed, 0), hash_serialize(&sorted, 0));
+/// ```
+pub fn hash_serialize<T: Serialize + ?Sized>(value: &T, seed: u64) -> Result<u64, SerializeError> {
+    let mut fields = FieldHasher::new(seed);
+    value.serialize(Structural {
+        fields: &mut fields,
+        seed,
+    })?;
+    Ok(fields.finish())
+}
+
+/// Feeds the value it serializes to `fields`, hashing nested parts under `seed`
+struct Structural<'a> {
+    fields: &'a mut FieldHasher,
+    seed: u64,
+}
+
+impl<'a> Structural<'a> {
+    fn child(&self) -> FieldHasher {
+        FieldHasher::new(self.seed)
+    }
+
+    fn word(self, val: u64) -> Result<(), SerializeError> {
+        self.fields.write_word(val);
+        Ok(())
+    }
+
+    /// Feeds `bytes` as a string, which is also how variant names are hashed
+    fn text(&mut self, bytes: &[u8]) {
+        let mut text = Text::new(self.child());
+        text.push(bytes);
+        text.finish(self.fields);
+    }
+
+    fn ordered(self) -> Result<Ordered<'a>, SerializeError> {
+        Ok(Ordered {
+            child: self.child(),
+            len: 0,
+            parent: self,
+        })
+    }
+}
+
+/// A string or byte array streamed into its own hasher: its words, then its length
+struct Text {
+    child: FieldHasher,
+    pending: WordBuffer<8>,
+    len: u64,
+}
+
+impl Text {
+    fn new(child: FieldHasher) -> Self {
+        Self {
+            child,
+            pending: WordBuffer::new(),
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let Self { child, pending, .. } = self;
+        pending.push(bytes, |w| child.write_word(u64::from_le_bytes(w)));
+        self.len += bytes.len() as u64;
+    }
+
+    fn finish(mut self, parent: &mut FieldHasher) {
+        self.child
+            .write_word(u64::from_le_bytes(self.pending.finish()));
+        self.child.write_word(self.len);
+        parent.write_word(self.child.finish());
+    }
+}
+
+impl Write for Text {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// The parts of a sequence, tuple or tuple struct, hashed in order into their own hasher
+struct Ordered<'a> {
+    parent: Structural<'a>,
+    child: FieldHasher,
+    len: u64,
+}
+
+impl Ordered<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.len += 1;
+        value.serialize(Structural {
+            fields: &mut self.child,
+            seed: self.parent.seed,
+        })
+    }
+
+    fn finish(self) -> Result<(), SerializeError> {
+        self.parent.fields.write_word(self.len);
+        self.parent.fields.write_word(self.child.finish());
+        Ok(())
+    }
+}
+
+/// The entries of a map, struct or struct variant, each hashed on its own and summed
+struct Unordered<'a> {
+    parent: Structural<'a>,
+    entry: FieldHasher,
+    sum: u64,
+    len: u64,
+}
+
+impl<'a> Unordered<'a> {
+    fn new(parent: Structural<'a>) -> Self {
+        Self {
+            entry: parent.child(),
+            parent,
+            sum: 0,
+            len: 0,
+        }
+    }
+
+    fn part<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(Structural {
+            fields: &mut self.entry,
+            seed: self.parent.seed,
+        })
+    }
+
+    fn end_entry(&mut self) {
+        let entry = core::mem::replace(&mut self.entry, self.parent.child());
+        self.sum = self.sum.wrapping_add(entry.finish());
+        self.len += 1;
+    }
+
+    fn field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.part(name)?;
+        self.part(value)?;
+        self.end_entry();
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), SerializeError> {
+        self.parent.fields.write_word(self.len);
+        self.parent.fields.write_word(self.sum);
+        Ok(())
+    }
+}
+
+impl<'a> Serializer for Structural<'a> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Ordered<'a>;
+    type SerializeTuple = Ordered<'a>;
+    type SerializeTupleStruct = Ordered<'a>;
+    type SerializeTupleVariant = Ordered<'a>;
+    type SerializeMap = Unordered<'a>;
+    type SerializeStruct = Unordered<'a>;
+    type SerializeStructVariant = Unordered<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerializeError> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => self.serialize_u128(v as u128),
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        self.word(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerializeError> {
+        if let Ok(v) = u64::try_from(v) {
+            return self.word(v);
+        }
+        self.fields.write_word(v as u64);
+        self.word((v >> 64) as u64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), SerializeError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), SerializeError> {
+        self.word(v.to_bits())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.word(v as u64)
+    }
+
+    fn serialize_str(mut self, v: &str) -> Result<(), SerializeError> {
+        self.text(v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(mut self, v: &[u8]) -> Result<(), SerializeError> {
+        self.text(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        self.word(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        self.fields.write_word(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.text(variant.as_bytes());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Ordered<'a>, SerializeError> {
+        self.ordered()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Ordered<'a>, SerializeError> {
+        self.ordered()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Ordered<'a>, SerializeError> {
+        self.ordered()
+    }
+
+    fn serialize_tuple_variant(
+        mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Ordered<'a>, SerializeError> {
+        self.text(variant.as_bytes());
+        self.ordered()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Unordered<'a>, SerializeError> {
+        Ok(Unordered::new(self))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Unordered<'a>, SerializeError> {
+        Ok(Unordered::new(self))
+    }
+
+    fn serialize_struct_variant(
+        mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Unordered<'a>, SerializeError> {
+        self.text(variant.as_bytes());
+        Ok(Unordered::new(self))
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        let mut text = Text::new(self.child());
+        write!(text, "{value}").map_err(|_| SerializeError)?;
+        text.finish(self.fields);
+        Ok(())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl SerializeSeq for Ordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl SerializeTuple for Ordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleStruct for Ordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleVariant for Ordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl SerializeMap for Unordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.part(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.part(value)?;
+        self.end_entry();
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl SerializeStruct for Unordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl SerializeStructVariant for Unordered<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
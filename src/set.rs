@@ -0,0 +1,133 @@
+use core::fmt;
+
+use crate::probe::ProbeSeq;
+
+/// The error returned when inserting into a [`U64Set`] that already holds
+/// [`U64Set::CAPACITY`] values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Full;
+
+impl fmt::Display for Full {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("set is full")
+    }
+}
+
+/// A fixed-capacity set of 64-bit hashes in an open-addressed array of `N` slots, for
+/// remembering what has been seen without allocating.
+///
+/// `N` must be a power of two, which is checked at compile time. Values are placed along the
+/// [`ProbeSeq`] of their own bits, so they should already be hashes. An empty slot holds `0`, so
+/// the value `0` is tracked by a separate flag instead and never takes up a slot.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes, U64Set};
+///
+/// let mut seen = U64Set::<64>::new();
+/// assert_eq!(seen.insert(hash_bytes(b"frame 1", 0).0), Ok(true));
+/// assert_eq!(seen.insert(hash_bytes(b"frame 1", 0).0), Ok(false));
+/// assert!(!seen.contains(hash_bytes(b"frame 2", 0).0));
+/// ```
+///
+/// Other sizes are rejected when the set is created:
+///
+/// ```compile_fail
+/// let set = cmhash::U64Set::<48>::new();
+/// ```
+#[derive(Debug, Clone)]
+pub struct U64Set<const N: usize> {
+    slots: [u64; N],
+    len: usize,
+    has_zero: bool,
+}
+
+impl<const N: usize> U64Set<N> {
+    /// The most nonzero values the set holds, 7/8 of its slots, which keeps probe sequences
+    /// short. `0` can be held on top of these.
+    pub const CAPACITY: usize = N - N / 8;
+
+    const POWER_OF_TWO: () = assert!(N.is_power_of_two(), "U64Set needs a power-of-two size");
+
+    /// Creates an empty set
+    pub const fn new() -> Self {
+        let () = Self::POWER_OF_TWO;
+        Self {
+            slots: [0; N],
+            len: 0,
+            has_zero: false,
+        }
+    }
+
+    /// Adds `value` to the set, returning whether it wasn't already present.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Full`] if `value` is absent and the set already holds [`Self::CAPACITY`]
+    /// nonzero values.
+    pub fn insert(&mut self, value: u64) -> Result<bool, Full> {
+        if value == 0 {
+            let added = !self.has_zero;
+            self.has_zero = true;
+            return Ok(added);
+        }
+        let mut empty = None;
+        for slot in ProbeSeq::new(value, N - 1) {
+            match self.slots[slot] {
+                v if v == value => return Ok(false),
+                0 => {
+                    empty = Some(slot);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        match empty {
+            Some(slot) if self.len < Self::CAPACITY => {
+                self.slots[slot] = value;
+                self.len += 1;
+                Ok(true)
+            }
+            _ => Err(Full),
+        }
+    }
+
+    /// Returns `true` if `value` is in the set
+    pub fn contains(&self, value: u64) -> bool {
+        if value == 0 {
+            return self.has_zero;
+        }
+        for slot in ProbeSeq::new(value, N - 1) {
+            match self.slots[slot] {
+                v if v == value => return true,
+                0 => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Returns the number of values in the set, counting `0`
+    pub fn len(&self) -> usize {
+        self.len + self.has_zero as usize
+    }
+
+    /// Returns `true` if the set holds no values
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every value from the set
+    pub fn clear(&mut self) {
+        self.slots = [0; N];
+        self.len = 0;
+        self.has_zero = false;
+    }
+}
+
+impl<const N: usize> Default for U64Set<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
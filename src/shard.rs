@@ -0,0 +1,170 @@
+use core::cmp::Ordering;
+use core::num::NonZeroUsize;
+
+use crate::output::{hash_bytes, hash_to_bucket};
+use crate::seed::Seed;
+
+/// Maps a hash to a bucket in `0..n_buckets` with jump consistent hashing
+///
+/// This is the algorithm of Lamping and Veach, "A Fast, Minimal Memory, Consistent Hash
+/// Algorithm". Growing from `n` to `n + 1` buckets moves only the `1 / (n + 1)` share of hashes
+/// that land in the new bucket, where [`hash_to_bucket`] would move about half of them. Buckets
+/// can only be added or removed at the end.
+///
+/// # Panics
+///
+/// Panics if `n_buckets` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_bytes, jump_hash};
+///
+/// let hash = hash_bytes(b"user/42", 0).0;
+/// let before = jump_hash(hash, 10);
+/// let after = jump_hash(hash, 11);
+/// assert!(after == before || after == 10);
+/// ```
+pub fn jump_hash(hash: u64, n_buckets: usize) -> usize {
+    assert_ne!(n_buckets, 0, "cannot map a hash to zero buckets");
+    let (mut key, mut bucket, mut next) = (hash, 0i64, 0i64);
+    while next < n_buckets as i64 {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as usize
+}
+
+/// Assigns keys to one of a fixed number of shards
+///
+/// Keys are hashed with [`hash_bytes`] under the selector's seed, then mapped to a shard either
+/// with [`hash_to_bucket`] or, for selectors made with [`ShardSelector::jump`], with
+/// [`jump_hash`]. Selectors are cheap to copy and compare, so a configuration can be kept
+/// alongside the data it shards.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use cmhash::ShardSelector;
+///
+/// let shards = ShardSelector::jump(NonZeroUsize::new(8).unwrap(), 7);
+/// assert!(shards.select(b"user/42") < 8);
+/// assert_eq!(shards.select(b"user/42"), shards.select(b"user/42"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShardSelector {
+    shards: NonZeroUsize,
+    seed: u64,
+    jump: bool,
+}
+
+impl ShardSelector {
+    /// Creates a selector over `shards` shards that maps hashes with [`hash_to_bucket`]
+    pub fn new(shards: NonZeroUsize, seed: impl Into<Seed>) -> Self {
+        Self {
+            shards,
+            seed: seed.into().0,
+            jump: false,
+        }
+    }
+
+    /// Creates a selector over `shards` shards that maps hashes with [`jump_hash`], so that
+    /// changing the number of shards moves as few keys as possible
+    pub fn jump(shards: NonZeroUsize, seed: impl Into<Seed>) -> Self {
+        Self {
+            shards,
+            seed: seed.into().0,
+            jump: true,
+        }
+    }
+
+    /// Returns the number of shards
+    pub fn shards(&self) -> NonZeroUsize {
+        self.shards
+    }
+
+    /// Returns the seed keys are hashed under
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns whether hashes are mapped with [`jump_hash`]
+    pub fn is_jump(&self) -> bool {
+        self.jump
+    }
+
+    /// Returns the shard in `0..shards` that `key` belongs to
+    pub fn select(&self, key: &[u8]) -> usize {
+        let hash = hash_bytes(key, self.seed).0;
+        if self.jump {
+            jump_hash(hash, self.shards.get())
+        } else {
+            hash_to_bucket(hash, self.shards.get())
+        }
+    }
+}
+
+/// Returns two candidate buckets in `0..n` for a key with hash `key_hash`, for "power of two
+/// choices" placement, which differ whenever `n` is over 1
+///
+/// The first candidate is the high half of the widening multiply of `key_hash` by `n`, as in
+/// [`hash_to_bucket`]. The low half is the fraction the reduction discarded, uniform and
+/// independent of the high half, and the second candidate is drawn from it among the other
+/// `n - 1` buckets, so it is uniform over those and never equals the first.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use cmhash::{hash_bytes, two_choice};
+///
+/// let (a, b) = two_choice(hash_bytes(b"job-17", 0).0, NonZeroUsize::new(8).unwrap());
+/// assert!(a < 8 && b < 8 && a != b);
+/// ```
+pub fn two_choice(key_hash: u64, n: NonZeroUsize) -> (usize, usize) {
+    let n = n.get() as u128;
+    let product = key_hash as u128 * n;
+    let first = (product >> 64) as usize;
+    if n == 1 {
+        return (0, 0);
+    }
+    let second = ((product as u64 as u128 * (n - 1)) >> 64) as usize;
+    (first, second + usize::from(second >= first))
+}
+
+/// Returns whichever of the [`two_choice`] candidates of `key_hash` has the smaller load in
+/// `loads`, one load per bucket
+///
+/// Equal loads are broken by the lowest bit of `key_hash`, picking the first candidate if it is
+/// clear, so the same key and loads always pick the same bucket. Placing each key in the less
+/// loaded of two random buckets keeps the fullest bucket within a few keys of the average,
+/// where one random bucket per key leaves it many standard deviations above.
+///
+/// # Panics
+///
+/// Panics if `loads` is empty.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_u64, pick_less_loaded};
+///
+/// let mut loads = vec![0u64; 16];
+/// for job in 0..1000 {
+///     let bucket = pick_less_loaded(hash_u64(job, 7).0, &loads);
+///     loads[bucket] += 1;
+/// }
+/// assert!(loads.iter().all(|&load| load <= 66));
+/// ```
+pub fn pick_less_loaded(key_hash: u64, loads: &[u64]) -> usize {
+    let n = NonZeroUsize::new(loads.len()).expect("cannot pick from zero buckets");
+    let (first, second) = two_choice(key_hash, n);
+    match loads[first].cmp(&loads[second]) {
+        Ordering::Less => first,
+        Ordering::Greater => second,
+        Ordering::Equal if key_hash & 1 == 0 => first,
+        Ordering::Equal => second,
+    }
+}
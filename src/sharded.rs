@@ -0,0 +1,273 @@
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::hasher::{fmix64, CMBuildHasher};
+use crate::keyed::KeyedHasher;
+use crate::output::{hash_to_bucket, DEFAULT_SEED};
+use crate::seed::Seed;
+
+/// A key stored with the hash it was routed by, so the shard's own table hashes that one word
+/// instead of the whole key again
+#[derive(Debug, Clone)]
+struct Prehashed<K> {
+    hash: u64,
+    key: K,
+}
+
+impl<K> Hash for Prehashed<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<K: PartialEq> PartialEq for Prehashed<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key == other.key
+    }
+}
+
+impl<K: Eq> Eq for Prehashed<K> {}
+
+/// A borrowed form of a [`Prehashed`] key, so that lookups by `&Q` don't need an owned `K`
+trait Lookup<Q: ?Sized> {
+    fn hash(&self) -> u64;
+    fn key(&self) -> &Q;
+}
+
+impl<K: Borrow<Q>, Q: ?Sized> Lookup<Q> for Prehashed<K> {
+    fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn key(&self) -> &Q {
+        self.key.borrow()
+    }
+}
+
+impl<'a, K: Borrow<Q> + 'a, Q: ?Sized + 'a> Borrow<dyn Lookup<Q> + 'a> for Prehashed<K> {
+    fn borrow(&self) -> &(dyn Lookup<Q> + 'a) {
+        self
+    }
+}
+
+impl<Q: ?Sized> Hash for dyn Lookup<Q> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(Lookup::hash(self));
+    }
+}
+
+impl<Q: PartialEq + ?Sized> PartialEq for dyn Lookup<Q> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        Lookup::hash(self) == Lookup::hash(other) && self.key() == other.key()
+    }
+}
+
+impl<Q: Eq + ?Sized> Eq for dyn Lookup<Q> + '_ {}
+
+type Shard<K, V> = HashMap<Prehashed<K>, V, CMBuildHasher>;
+
+/// A concurrent hash map made of `N` independently locked [`HashMap`]s, with each key routed to
+/// one of them by its hash
+///
+/// Keys are hashed once, with a [`KeyedHasher`] derived from the map's seed. The high bits of
+/// that hash pick the shard, as [`hash_to_bucket`] does, and the shard's table stores the hash
+/// with the key and hashes only that word again, under a [`CMBuildHasher`] whose state is
+/// derived from the seed and the shard's index. Threads working on keys in different shards
+/// don't contend, so with `N` a few times the number of threads most operations take an
+/// uncontended lock.
+///
+/// # Locking
+///
+/// Every method locks the one shard its key routes to and releases it before returning, and
+/// none runs caller code while holding a lock other than the `Eq` impl of `K`, the `Clone` impl
+/// of `V` and the callback of [`with`](Self::with). That callback runs with its key's shard
+/// locked, so it must not use the map at all: a key in the same shard deadlocks its own thread,
+/// and a key in another shard can deadlock against a thread doing the same the other way round.
+/// To use the map while looking at a value, take a copy with
+/// [`get_cloned`](Self::get_cloned) instead. [`iter_shards`](Self::iter_shards) also keeps
+/// each shard locked until its item is dropped: while holding one, a thread must not use the map
+/// for keys in that shard.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::ShardedHashMap;
+///
+/// let sessions: ShardedHashMap<u64, String, 16> = ShardedHashMap::new();
+/// std::thread::scope(|s| {
+///     for user in 0..4 {
+///         let sessions = &sessions;
+///         s.spawn(move || sessions.insert(user, format!("session-{user}")));
+///     }
+/// });
+/// assert_eq!(sessions.len(), 4);
+/// assert_eq!(sessions.get_cloned(&2).as_deref(), Some("session-2"));
+/// ```
+#[derive(Debug)]
+pub struct ShardedHashMap<K, V, const N: usize> {
+    seed: u64,
+    shards: [Mutex<Shard<K, V>>; N],
+}
+
+impl<K, V, const N: usize> ShardedHashMap<K, V, N> {
+    /// Creates an empty map hashing keys under [`DEFAULT_SEED`]
+    ///
+    /// Fails to compile unless `N` is a power of two.
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// Creates an empty map hashing keys under `seed`, from which each shard's seed is derived
+    ///
+    /// Fails to compile unless `N` is a power of two.
+    pub fn with_seed(seed: impl Into<Seed>) -> Self {
+        let seed = seed.into().0;
+        const {
+            assert!(
+                N.is_power_of_two(),
+                "a ShardedHashMap needs a power of two shards"
+            )
+        };
+        Self {
+            seed,
+            shards: core::array::from_fn(|i| {
+                let state = fmix64(seed ^ fmix64(i as u64 + 1));
+                Mutex::new(HashMap::with_hasher(CMBuildHasher::with_state(state)))
+            }),
+        }
+    }
+
+    /// Returns the seed keys are hashed under
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of entries in the map
+    ///
+    /// The shards are counted one at a time, so entries inserted or removed meanwhile may or may
+    /// not be counted.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| lock(shard).len()).sum()
+    }
+
+    /// Returns `true` if the map holds no entries, with the same caveat as [`Self::len`]
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| lock(shard).is_empty())
+    }
+
+    /// Iterates over the shards in index order, locking each as it is reached and keeping it
+    /// locked until its guard is dropped
+    ///
+    /// Using the map for a key in a shard whose guard the thread holds deadlocks.
+    pub fn iter_shards(&self) -> impl Iterator<Item = ShardGuard<'_, K, V>> {
+        self.shards
+            .iter()
+            .map(|shard| ShardGuard { shard: lock(shard) })
+    }
+
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        let mut hasher = KeyedHasher::from_seed(self.seed);
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The shard `hash` routes to
+    fn shard(&self, hash: u64) -> MutexGuard<'_, Shard<K, V>> {
+        lock(&self.shards[hash_to_bucket(hash, N)])
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize> ShardedHashMap<K, V, N> {
+    /// Inserts `value` under `key`, returning the value it replaced, if any
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let hash = self.hash(&key);
+        self.shard(hash).insert(Prehashed { hash, key }, value)
+    }
+
+    /// Returns a clone of the value under `key`
+    pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        let hash = self.hash(key);
+        self.shard(hash)
+            .get(&Prehashed { hash, key } as &dyn Lookup<Q>)
+            .cloned()
+    }
+
+    /// Calls `f` with the value under `key`, returning its result
+    ///
+    /// `f` runs with the key's shard locked, without cloning the value, so it must not use the
+    /// map; see [Locking](Self#locking).
+    pub fn with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        self.shard(hash)
+            .get(&Prehashed { hash, key } as &dyn Lookup<Q>)
+            .map(f)
+    }
+
+    /// Returns `true` if the map holds an entry for `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        self.shard(hash)
+            .contains_key(&Prehashed { hash, key } as &dyn Lookup<Q>)
+    }
+
+    /// Removes the entry for `key`, returning its value
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        self.shard(hash)
+            .remove(&Prehashed { hash, key } as &dyn Lookup<Q>)
+    }
+}
+
+impl<K, V, const N: usize> Default for ShardedHashMap<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A locked shard of a [`ShardedHashMap`], from [`ShardedHashMap::iter_shards`]
+#[derive(Debug)]
+pub struct ShardGuard<'a, K, V> {
+    shard: MutexGuard<'a, Shard<K, V>>,
+}
+
+impl<K, V> ShardGuard<'_, K, V> {
+    /// Returns the number of entries in the shard
+    pub fn len(&self) -> usize {
+        self.shard.len()
+    }
+
+    /// Returns `true` if the shard holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.shard.is_empty()
+    }
+
+    /// Iterates over the entries of the shard in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.shard.iter().map(|(key, value)| (&key.key, value))
+    }
+}
+
+/// Locks `shard`, ignoring poisoning: no method leaves a shard half-updated when a `K` or `V`
+/// impl panics, as the underlying [`HashMap`] doesn't
+fn lock<T>(shard: &Mutex<T>) -> MutexGuard<'_, T> {
+    shard.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,133 @@
+use crate::hasher::fmix64;
+use crate::output::{hash_bytes, hash_u64};
+
+// The odd base of the polynomial each window is evaluated as
+const BASE: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Hashes every window of `k` consecutive characters of `text`, in order, for feeding MinHash
+/// or SimHash.
+///
+/// Windows are counted in `char`s, so they never split a multi-byte character and the same text
+/// gives the same shingles however it is encoded in memory. Each hash equals
+/// [`hash_shingle`] of its window, but is computed with a rolling hash, so the whole text costs
+/// `O(n)` rather than `O(nk)`: the characters are hashed under `seed`, each window is a
+/// polynomial of its characters' hashes modulo 2<sup>64</sup>, and that is finalized with the
+/// MurmurHash3 finalizer. Text shorter than `k` characters yields nothing.
+///
+/// # Panics
+///
+/// Panics if `k` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_shingle, shingle_hashes};
+///
+/// let hashes: Vec<u64> = shingle_hashes("héllo", 3, 7).collect();
+/// assert_eq!(hashes.len(), 3);
+/// assert_eq!(hashes[1], hash_shingle("éll", 7));
+/// ```
+pub fn shingle_hashes(text: &str, k: usize, seed: u64) -> impl Iterator<Item = u64> + '_ {
+    assert_ne!(k, 0, "a shingle must contain at least one character");
+    Rolling::new(text.chars().map(move |c| char_hash(c, seed)), k)
+}
+
+/// Hashes every run of `k` consecutive words of `text`, in order, where words are separated by
+/// ASCII whitespace.
+///
+/// Each hash equals [`hash_word_shingle`] of the words in its window, rolled as
+/// [`shingle_hashes`] does with characters, so it depends on the words and their order but not
+/// on the whitespace between them. Text with fewer than `k` words yields nothing, unlike
+/// `shingles` for SimHash, which gives one shingle of all of them.
+///
+/// # Panics
+///
+/// Panics if `k` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_word_shingle, word_shingle_hashes};
+///
+/// let hashes: Vec<u64> = word_shingle_hashes("to be or  not", 2, 7).collect();
+/// assert_eq!(hashes.len(), 3);
+/// assert_eq!(hashes[2], hash_word_shingle("or not", 7));
+/// ```
+pub fn word_shingle_hashes(text: &str, k: usize, seed: u64) -> impl Iterator<Item = u64> + '_ {
+    assert_ne!(k, 0, "a shingle must contain at least one word");
+    Rolling::new(
+        text.split_ascii_whitespace()
+            .map(move |word| hash_bytes(word.as_bytes(), seed).0),
+        k,
+    )
+}
+
+/// Hashes all of `window` as one shingle of [`shingle_hashes`], from scratch
+pub fn hash_shingle(window: &str, seed: u64) -> u64 {
+    polynomial(window.chars().map(|c| char_hash(c, seed)))
+}
+
+/// Hashes the words of `window` as one shingle of [`word_shingle_hashes`], from scratch
+pub fn hash_word_shingle(window: &str, seed: u64) -> u64 {
+    polynomial(
+        window
+            .split_ascii_whitespace()
+            .map(|word| hash_bytes(word.as_bytes(), seed).0),
+    )
+}
+
+fn char_hash(c: char, seed: u64) -> u64 {
+    hash_u64(c as u64, seed).0
+}
+
+fn polynomial(units: impl Iterator<Item = u64>) -> u64 {
+    fmix64(units.fold(0, |acc, unit| acc.wrapping_mul(BASE).wrapping_add(unit)))
+}
+
+/// The polynomial of each window of `k` units, updated in constant time per unit by adding the
+/// unit entering the window and subtracting the one leaving it
+struct Rolling<I> {
+    ahead: I,
+    behind: I,
+    k: usize,
+    filled: usize,
+    // BASE^k, the weight of the unit leaving the window after the shift
+    outgoing: u64,
+    acc: u64,
+}
+
+impl<I: Iterator<Item = u64> + Clone> Rolling<I> {
+    fn new(units: I, k: usize) -> Self {
+        Self {
+            behind: units.clone(),
+            ahead: units,
+            k,
+            filled: 0,
+            outgoing: (0..k).fold(1, |pow: u64, _| pow.wrapping_mul(BASE)),
+            acc: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u64> + Clone> Iterator for Rolling<I> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.filled < self.k {
+            while self.filled < self.k {
+                let unit = self.ahead.next()?;
+                self.acc = self.acc.wrapping_mul(BASE).wrapping_add(unit);
+                self.filled += 1;
+            }
+        } else {
+            let unit = self.ahead.next()?;
+            let leaving = self.behind.next()?;
+            self.acc = self
+                .acc
+                .wrapping_mul(BASE)
+                .wrapping_add(unit)
+                .wrapping_sub(leaving.wrapping_mul(self.outgoing));
+        }
+        Some(fmix64(self.acc))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::output::hash_bytes;
+
+/// Computes the SimHash fingerprint of a set of weighted features.
+///
+/// Each feature is hashed with [`hash_bytes`] under `seed`, and every bit of its hash casts a
+/// vote of its weight for that bit being set or clear. The fingerprint has a bit set wherever
+/// the votes for it outweigh those against, so documents sharing most of their features have
+/// fingerprints a small [`hamming`] distance apart, while unrelated documents differ in about
+/// half of the 64 bits. Ties and an empty input give clear bits.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hamming, simhash};
+///
+/// let a = simhash([("the", 1), ("quick", 3), ("brown", 2), ("fox", 3)], 0);
+/// let b = simhash([("the", 1), ("quick", 3), ("brown", 2), ("cat", 3)], 0);
+/// assert!(hamming(a, b) < 32);
+/// ```
+pub fn simhash<F: AsRef<[u8]>>(
+    weighted_features: impl IntoIterator<Item = (F, u32)>,
+    seed: u64,
+) -> u64 {
+    let mut votes = [0i64; 64];
+    for (feature, weight) in weighted_features {
+        let hash = hash_bytes(feature.as_ref(), seed).0;
+        for (bit, vote) in votes.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *vote += weight as i64;
+            } else {
+                *vote -= weight as i64;
+            }
+        }
+    }
+    votes
+        .iter()
+        .enumerate()
+        .fold(0, |fingerprint, (bit, &vote)| {
+            fingerprint | ((vote > 0) as u64) << bit
+        })
+}
+
+/// Returns the number of bits in which `a` and `b` differ
+pub const fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Splits `text` into its overlapping runs of `k` whitespace-separated words, each with weight
+/// 1, ready for [`simhash`].
+///
+/// Each shingle borrows from `text`, keeping whatever whitespace separated its words. Text with
+/// fewer than `k` words gives a single shingle of all of them, and text with no words gives
+/// none.
+///
+/// # Panics
+///
+/// Panics if `k` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::shingles;
+///
+/// assert_eq!(
+///     shingles("to be or not", 2),
+///     [("to be", 1), ("be or", 1), ("or not", 1)]
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn shingles(text: &str, k: usize) -> alloc::vec::Vec<(&str, u32)> {
+    assert_ne!(k, 0, "a shingle must contain at least one word");
+    let words: alloc::vec::Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return alloc::vec::Vec::new();
+    }
+    let span = |first: &str, last: &str| {
+        let start = first.as_ptr() as usize - text.as_ptr() as usize;
+        let end = last.as_ptr() as usize - text.as_ptr() as usize + last.len();
+        (&text[start..end], 1)
+    };
+    if words.len() < k {
+        return alloc::vec![span(words[0], words[words.len() - 1])];
+    }
+    words.windows(k).map(|w| span(w[0], w[k - 1])).collect()
+}
@@ -0,0 +1,234 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+
+use crate::snapshot::read_u64;
+
+/// The bytes that start every serialized sketch
+const MAGIC: [u8; 4] = *b"CMSK";
+
+/// The version of the sketch format written by this release
+pub const SKETCH_FORMAT_VERSION: u8 = 1;
+
+/// The endianness tag of a payload written little-endian, the only order this release writes
+const LITTLE_ENDIAN: u8 = b'L';
+
+/// The length of the header that starts every serialized sketch
+pub const SKETCH_HEADER_LEN: usize = 32;
+
+/// Which sketch a serialized sketch holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub enum SketchKind {
+    /// A [`BottomK`](crate::BottomK)
+    BottomK,
+    /// An [`XorFilter`](crate::XorFilter) with `u8` fingerprints
+    XorFilter8,
+    /// An [`XorFilter`](crate::XorFilter) with `u16` fingerprints
+    XorFilter16,
+}
+
+impl SketchKind {
+    const fn tag(self) -> u8 {
+        match self {
+            Self::BottomK => 1,
+            Self::XorFilter8 => 2,
+            Self::XorFilter16 => 3,
+        }
+    }
+
+    const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::BottomK),
+            2 => Some(Self::XorFilter8),
+            3 => Some(Self::XorFilter16),
+            _ => None,
+        }
+    }
+}
+
+/// Why a serialized sketch couldn't be read, or two sketches couldn't be merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SketchError {
+    /// The bytes end before the header or the payload it describes does
+    Truncated,
+    /// The bytes don't start with the sketch magic, so they aren't a sketch at all
+    BadMagic,
+    /// The sketch kind is one this release doesn't know
+    UnknownKind(u8),
+    /// The bytes hold a different kind of sketch than the one being read
+    WrongKind {
+        /// The kind being read
+        expected: SketchKind,
+        /// The kind in the header
+        found: SketchKind,
+    },
+    /// The sketch was written in a format version this release doesn't know
+    UnknownVersion(u8),
+    /// The payload is in a byte order this release doesn't read
+    UnknownEndianness(u8),
+    /// The dimensions in the header don't match the type being read, or each other
+    DimensionMismatch,
+    /// The header or payload is inconsistent
+    Malformed,
+    /// Two sketches with different seeds or dimensions can't be merged
+    HeaderMismatch,
+}
+
+impl fmt::Display for SketchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => f.write_str("sketch is truncated"),
+            Self::BadMagic => f.write_str("not a serialized sketch"),
+            Self::UnknownKind(kind) => write!(f, "unknown sketch kind {kind}"),
+            Self::WrongKind { expected, found } => {
+                write!(f, "expected a {expected:?} sketch, found a {found:?}")
+            }
+            Self::UnknownVersion(v) => write!(f, "unknown sketch format version {v}"),
+            Self::UnknownEndianness(tag) => write!(f, "unknown endianness tag {tag:#04x}"),
+            Self::DimensionMismatch => f.write_str("sketch dimensions don't match"),
+            Self::Malformed => f.write_str("sketch is inconsistent"),
+            Self::HeaderMismatch => {
+                f.write_str("cannot merge sketches with different seeds or dimensions")
+            }
+        }
+    }
+}
+
+/// The header that starts every serialized sketch
+///
+/// It is [`SKETCH_HEADER_LEN`] bytes: the magic `CMSK`, the sketch kind, the format version, an
+/// endianness tag, a reserved zero byte, then the seed and two dimensions as little-endian
+/// `u64`s. What the dimensions mean depends on the kind. The payload that follows is
+/// little-endian too. [`SketchHeader::parse`] reads the header alone, for telling which type to
+/// read a sketch of unknown kind as.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{BottomK, SketchHeader, SketchKind};
+///
+/// let mut sketch = BottomK::<16>::new(7);
+/// sketch.offer(b"key");
+/// let bytes = sketch.to_bytes();
+/// let header = SketchHeader::parse(&bytes).unwrap();
+/// assert_eq!(header.kind(), SketchKind::BottomK);
+/// assert_eq!(header.seed(), 7);
+/// assert_eq!(BottomK::<16>::from_bytes(&bytes), Ok(sketch));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct SketchHeader {
+    kind: SketchKind,
+    seed: u64,
+    dims: [u64; 2],
+}
+
+impl SketchHeader {
+    pub(crate) const fn new(kind: SketchKind, seed: u64, dims: [u64; 2]) -> Self {
+        Self { kind, seed, dims }
+    }
+
+    /// Reads and checks the header at the start of `bytes`
+    pub fn parse(bytes: &[u8]) -> Result<Self, SketchError> {
+        if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+            return Err(if MAGIC.starts_with(bytes) {
+                SketchError::Truncated
+            } else {
+                SketchError::BadMagic
+            });
+        }
+        if bytes.len() < SKETCH_HEADER_LEN {
+            return Err(SketchError::Truncated);
+        }
+        let kind = SketchKind::from_tag(bytes[4]).ok_or(SketchError::UnknownKind(bytes[4]))?;
+        if bytes[5] != SKETCH_FORMAT_VERSION {
+            return Err(SketchError::UnknownVersion(bytes[5]));
+        }
+        if bytes[6] != LITTLE_ENDIAN {
+            return Err(SketchError::UnknownEndianness(bytes[6]));
+        }
+        if bytes[7] != 0 {
+            return Err(SketchError::Malformed);
+        }
+        Ok(Self {
+            kind,
+            seed: read_u64(bytes, 8),
+            dims: [read_u64(bytes, 16), read_u64(bytes, 24)],
+        })
+    }
+
+    /// Reads the header of a sketch of `kind`, returning it and the payload after it
+    pub(crate) fn parse_kind(bytes: &[u8], kind: SketchKind) -> Result<(Self, &[u8]), SketchError> {
+        let header = Self::parse(bytes)?;
+        if header.kind != kind {
+            return Err(SketchError::WrongKind {
+                expected: kind,
+                found: header.kind,
+            });
+        }
+        Ok((header, &bytes[SKETCH_HEADER_LEN..]))
+    }
+
+    /// Returns the kind of sketch that follows
+    pub fn kind(&self) -> SketchKind {
+        self.kind
+    }
+
+    /// Returns the seed the sketch hashes keys with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the dimensions of the sketch, whose meaning depends on its kind
+    pub fn dims(&self) -> [u64; 2] {
+        self.dims
+    }
+
+    /// Appends the header to `bytes`
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&[self.kind.tag(), SKETCH_FORMAT_VERSION, LITTLE_ENDIAN, 0]);
+        for word in [self.seed, self.dims[0], self.dims[1]] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl ArchivedSketchHeader {
+    /// Returns the kind of sketch that follows, as [`SketchHeader::kind`] does
+    pub fn kind(&self) -> SketchKind {
+        match self.kind {
+            ArchivedSketchKind::BottomK => SketchKind::BottomK,
+            ArchivedSketchKind::XorFilter8 => SketchKind::XorFilter8,
+            ArchivedSketchKind::XorFilter16 => SketchKind::XorFilter16,
+        }
+    }
+
+    /// Returns the seed the sketch hashes keys with
+    pub fn seed(&self) -> u64 {
+        self.seed.to_native()
+    }
+
+    /// Returns the dimensions of the sketch, whose meaning depends on its kind
+    pub fn dims(&self) -> [u64; 2] {
+        self.dims.map(|d| d.to_native())
+    }
+}
+
+/// Checks that `payload` is exactly the `expected` length its header's dimensions describe
+pub(crate) fn check_payload_len(payload: &[u8], expected: usize) -> Result<(), SketchError> {
+    match payload.len().cmp(&expected) {
+        Ordering::Equal => Ok(()),
+        Ordering::Less => Err(SketchError::Truncated),
+        Ordering::Greater => Err(SketchError::Malformed),
+    }
+}
@@ -0,0 +1,131 @@
+use core::cell::Cell;
+
+use crate::hasher::{for_each_word, WordBuffer};
+use crate::word::{self, Word};
+
+macro_rules! small_hasher {
+    ($(#[$attr:meta])* $name:ident($word:ty, $bytes:literal)) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name {
+            state: Cell<$word>,
+            initial: $word,
+            acc: $word,
+            pending: WordBuffer<$bytes>,
+        }
+
+        impl $name {
+            #[doc = concat!("Creates a new [`", stringify!($name), "`] with default state.")]
+            pub fn new() -> Self {
+                Self::with_state(<$word>::DEFAULT_STATE)
+            }
+
+            #[doc = concat!("Creates a new [`", stringify!($name), "`] with a specific state.")]
+            pub fn with_state(state: $word) -> Self {
+                Self {
+                    state: Cell::new(state),
+                    initial: state,
+                    acc: 0,
+                    pending: WordBuffer::new(),
+                }
+            }
+
+            /// Retrieve the current state.
+            pub fn get_state(&self) -> $word {
+                self.state.get()
+            }
+
+            #[doc = concat!("Quickly hash a ", stringify!($word), " value.")]
+            pub fn hash_word(&self, val: $word) -> $word {
+                let (hash, state) = word::round(self.state.get(), val);
+                self.state.set(state);
+                hash
+            }
+
+            #[doc = concat!(
+                "Hashes a slice of bytes by converting to a slice of ",
+                stringify!($word),
+                " and repeatedly applying [`Self::hash_word`]"
+            )]
+            pub fn hash_bytes(&self, bytes: &[u8]) -> $word {
+                let mut acc = 0;
+                for_each_word([bytes], |w| acc ^= self.hash_word(<$word>::from_ne_bytes(w)));
+                acc
+            }
+
+            /// Feeds `bytes` to a [`Self::hash_bytes`] whose input arrives in parts
+            pub(crate) fn append(&mut self, bytes: &[u8]) {
+                let Self {
+                    state,
+                    acc,
+                    pending,
+                    ..
+                } = self;
+                pending.push(bytes, |w| {
+                    let (hash, next) = word::round(state.get(), <$word>::from_ne_bytes(w));
+                    state.set(next);
+                    *acc ^= hash;
+                });
+            }
+
+            /// Completes the bytes fed by [`Self::append`], returning their [`Self::hash_bytes`]
+            pub(crate) fn finish_append(&self) -> $word {
+                self.acc ^ self.hash_word(<$word>::from_ne_bytes(self.pending.finish()))
+            }
+
+            /// Returns to the state the hasher was created with, discarding any appended bytes
+            pub(crate) fn reset(&mut self) {
+                *self = Self::with_state(self.initial);
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+small_hasher! {
+    /// A Thread-Local Core Hasher that works natively in 16-bit words, using the 2<sup>13</sup> - 1
+    /// Mersenne prime and a 16×16→32 bit multiply.
+    ///
+    /// This is meant for small tables on 16-bit microcontrollers, where widening a hash to a `u64`
+    /// only to truncate it again is wasted work.
+    CMHasher16(u16, 2)
+}
+
+small_hasher! {
+    /// A Thread-Local Core Hasher that works in 32-bit words on every target, using the
+    /// 2<sup>31</sup> - 1 Mersenne prime and a 32×32→64 bit multiply.
+    ///
+    /// Its hashes are 32 bits wide, and the same on 32- and 64-bit targets, unlike those of
+    /// [`TLCoreHasher`](crate::TLCoreHasher), which are a word wide.
+    CMHasher32(u32, 4)
+}
+
+/// Quickly hash a 16-bit value without carrying state, the 16-bit counterpart of
+/// [`hash_word_stateless`](crate::hash_word_stateless).
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_word_u16;
+///
+/// assert_eq!(hash_word_u16(0x1234), hash_word_u16(0x1234));
+/// ```
+#[inline]
+pub fn hash_word_u16(val: u16) -> u16 {
+    word::stateless(val)
+}
+
+/// Hashes a 16-bit value down to an 8-bit bucket index by folding the halves of
+/// [`hash_word_u16`] together.
+///
+/// For tables smaller than 256 entries, shift the result right rather than masking it.
+#[inline]
+pub fn bucket8(val: u16) -> u8 {
+    let hash = hash_word_u16(val);
+    (hash >> 8) as u8 ^ hash as u8
+}
@@ -0,0 +1,69 @@
+//! C ABI entry points in the shape SMHasher and SMHasher3 expect of a hash function.
+//!
+//! Each shim hashes `len` bytes at `key` under `seed` and writes the hash to `out` as a
+//! native-endian integer, the convention both harnesses use. To test them, build a static
+//! library with the shims exported:
+//!
+//! ```text
+//! cargo rustc --release --features smhasher,std --crate-type staticlib
+//! ```
+//!
+//! then declare the shims in the harness as
+//! `void cmhash_seeded_64(const void *key, int len, uint32_t seed, void *out);` and so on,
+//! register them in its list of hashes with their output width, and link
+//! `target/release/libcmhash.a` into it.
+
+use core::hash::Hasher;
+
+use crate::hasher::StatelessHasher;
+use crate::output::hash_bytes;
+
+/// Borrows the key bytes, treating a null pointer or a non-positive length as no bytes
+///
+/// # Safety
+///
+/// `key` must be valid for reads of `len` bytes if `len` is positive and `key` isn't null.
+unsafe fn key_bytes<'a>(key: *const u8, len: i32) -> &'a [u8] {
+    match usize::try_from(len) {
+        Ok(len) if len > 0 && !key.is_null() => core::slice::from_raw_parts(key, len),
+        _ => &[],
+    }
+}
+
+/// Hashes with [`StatelessHasher`], which takes no seed, so `seed` is ignored, writing 8 bytes
+///
+/// # Safety
+///
+/// `key` must be valid for reads of `len` bytes, unless `len` is 0, and `out` must be valid for
+/// writes of 8 bytes. Neither needs to be aligned.
+#[no_mangle]
+pub unsafe extern "C" fn cmhash_stateless_64(key: *const u8, len: i32, _seed: u32, out: *mut u8) {
+    let mut hasher = StatelessHasher::new();
+    hasher.write(key_bytes(key, len));
+    core::ptr::copy_nonoverlapping(hasher.finish().to_ne_bytes().as_ptr(), out, 8);
+}
+
+/// Hashes with [`hash_bytes`] under `seed`, writing 8 bytes
+///
+/// # Safety
+///
+/// `key` must be valid for reads of `len` bytes, unless `len` is 0, and `out` must be valid for
+/// writes of 8 bytes. Neither needs to be aligned.
+#[no_mangle]
+pub unsafe extern "C" fn cmhash_seeded_64(key: *const u8, len: i32, seed: u32, out: *mut u8) {
+    let hash = hash_bytes(key_bytes(key, len), seed as u64).0;
+    core::ptr::copy_nonoverlapping(hash.to_ne_bytes().as_ptr(), out, 8);
+}
+
+/// Hashes with [`hash_bytes`] under `seed` folded to 32 bits by
+/// [`HashOutput::fold32`](crate::HashOutput::fold32), writing 4 bytes
+///
+/// # Safety
+///
+/// `key` must be valid for reads of `len` bytes, unless `len` is 0, and `out` must be valid for
+/// writes of 4 bytes. Neither needs to be aligned.
+#[no_mangle]
+pub unsafe extern "C" fn cmhash_seeded_32(key: *const u8, len: i32, seed: u32, out: *mut u8) {
+    let hash = hash_bytes(key_bytes(key, len), seed as u64).fold32();
+    core::ptr::copy_nonoverlapping(hash.to_ne_bytes().as_ptr(), out, 4);
+}
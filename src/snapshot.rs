@@ -0,0 +1,57 @@
+use core::fmt;
+
+/// The version byte that starts every snapshot written by this release
+pub(crate) const SNAPSHOT_VERSION: u8 = 1;
+
+/// Why a snapshot couldn't be restored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// The snapshot was written in a layout this release doesn't know
+    UnknownVersion(u8),
+    /// The state doesn't fit in a word on this platform
+    StateOutOfRange,
+    /// Flag bits this release doesn't define are set
+    UnknownFlags(u8),
+    /// The snapshot is truncated or its contents are inconsistent
+    Malformed,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownVersion(v) => write!(f, "unknown snapshot version {v}"),
+            Self::StateOutOfRange => f.write_str("state doesn't fit in a word on this platform"),
+            Self::UnknownFlags(flags) => write!(f, "unknown snapshot flags {flags:#04x}"),
+            Self::Malformed => f.write_str("snapshot is truncated or inconsistent"),
+        }
+    }
+}
+
+/// Checks the version byte of a snapshot
+pub(crate) fn check_version(bytes: &[u8]) -> Result<(), StateError> {
+    match bytes[0] {
+        SNAPSHOT_VERSION => Ok(()),
+        v => Err(StateError::UnknownVersion(v)),
+    }
+}
+
+/// Reads the little-endian `u64` at `offset`
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(word)
+}
+
+/// A word-sized hasher snapshot: the version byte then the state as a little-endian `u64`
+pub(crate) fn word_snapshot(state: usize) -> [u8; 9] {
+    let mut bytes = [0; 9];
+    bytes[0] = SNAPSHOT_VERSION;
+    bytes[1..].copy_from_slice(&(state as u64).to_le_bytes());
+    bytes
+}
+
+/// Reads back a snapshot written by [`word_snapshot`]
+pub(crate) fn word_state(bytes: [u8; 9]) -> Result<usize, StateError> {
+    check_version(&bytes)?;
+    usize::try_from(read_u64(&bytes, 1)).map_err(|_| StateError::StateOutOfRange)
+}
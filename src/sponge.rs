@@ -0,0 +1,164 @@
+use core::mem::size_of;
+
+use crate::hasher::{for_each_word, WordBuffer};
+use crate::{hash_word_rounds, word, DEFAULT_STATE};
+
+const N: usize = size_of::<usize>();
+
+/// Absorbed once when switching from absorbing to squeezing, so that no sequence of absorbed
+/// words can reproduce the state the squeeze phase starts from
+const SQUEEZE_DOMAIN: usize = !DEFAULT_STATE;
+
+/// Derives an arbitrary amount of output from arbitrary-length input.
+///
+/// Input is absorbed a word at a time through the round function. The first squeeze closes the
+/// absorb phase by absorbing the number of words seen so far and a domain separation constant;
+/// output is then produced in counter mode, word `i` being [`hash_word_rounds`] of `i` keyed with
+/// the final state. Output forms a single byte stream, so how it is split across calls to
+/// [`squeeze_word`](Sponge::squeeze_word) and [`squeeze_bytes`](Sponge::squeeze_bytes) doesn't
+/// change it.
+///
+/// This is **not** cryptographic: it is fine for deriving salts or identifiers from trusted
+/// input, but must never be used for keys, MACs or anything an adversary can probe.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::Sponge;
+///
+/// let mut sponge = Sponge::new();
+/// sponge.absorb_bytes(b"tenant-7");
+/// let mut id = [0u8; 32];
+/// sponge.squeeze_bytes(&mut id);
+/// let salts: [usize; 4] = core::array::from_fn(|_| sponge.squeeze_word());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sponge {
+    state: usize,
+    absorbed: usize,
+    squeezing: bool,
+    squeezed: usize,
+    out: [u8; N],
+    out_pos: usize,
+    open: Option<OpenMessage>,
+}
+
+/// A message whose bytes arrive over several calls to [`Sponge::append`]
+#[derive(Debug, Clone, Copy)]
+struct OpenMessage {
+    words: WordBuffer<N>,
+    len: usize,
+}
+
+impl Sponge {
+    /// Creates an empty [`Sponge`]
+    pub fn new() -> Self {
+        Self {
+            state: DEFAULT_STATE,
+            absorbed: 0,
+            squeezing: false,
+            squeezed: 0,
+            out: [0; N],
+            out_pos: N,
+            open: None,
+        }
+    }
+
+    /// Absorbs a single word
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sponge has already been squeezed.
+    pub fn absorb_word(&mut self, val: usize) {
+        assert!(
+            !self.squeezing,
+            "cannot absorb into a sponge after squeezing"
+        );
+        self.close();
+        self.absorb(val);
+    }
+
+    /// Absorbs `bytes` as one message: its little-endian words, zero-padded, followed by its
+    /// length, so that `absorb_bytes(b"ab")` then `absorb_bytes(b"c")` differs from
+    /// `absorb_bytes(b"abc")`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sponge has already been squeezed.
+    pub fn absorb_bytes(&mut self, bytes: &[u8]) {
+        assert!(
+            !self.squeezing,
+            "cannot absorb into a sponge after squeezing"
+        );
+        self.close();
+        for_each_word::<N>([bytes], |w| self.absorb(usize::from_le_bytes(w)));
+        self.absorb(bytes.len());
+    }
+
+    /// Squeezes the next word of output
+    pub fn squeeze_word(&mut self) -> usize {
+        let mut out = [0; N];
+        self.squeeze_bytes(&mut out);
+        usize::from_le_bytes(out)
+    }
+
+    /// Fills `out` with the next bytes of output
+    pub fn squeeze_bytes(&mut self, out: &mut [u8]) {
+        self.close();
+        if !self.squeezing {
+            self.absorb(self.absorbed);
+            self.absorb(SQUEEZE_DOMAIN);
+            self.squeezing = true;
+        }
+        for byte in out {
+            if self.out_pos == N {
+                self.out = hash_word_rounds::<3>(self.squeezed, self.state).to_le_bytes();
+                self.squeezed += 1;
+                self.out_pos = 0;
+            }
+            *byte = self.out[self.out_pos];
+            self.out_pos += 1;
+        }
+    }
+
+    /// Appends `bytes` to a message left open across calls, which the sponge's next absorb or
+    /// squeeze completes exactly as one [`Self::absorb_bytes`] of everything appended
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sponge has already been squeezed.
+    pub(crate) fn append(&mut self, bytes: &[u8]) {
+        assert!(
+            !self.squeezing,
+            "cannot absorb into a sponge after squeezing"
+        );
+        let mut open = self.open.take().unwrap_or(OpenMessage {
+            words: WordBuffer::new(),
+            len: 0,
+        });
+        open.words
+            .push(bytes, |w| self.absorb(usize::from_le_bytes(w)));
+        open.len += bytes.len();
+        self.open = Some(open);
+    }
+
+    /// Completes the message left open by [`Self::append`], if there is one
+    fn close(&mut self) {
+        if let Some(open) = self.open.take() {
+            self.absorb(usize::from_le_bytes(open.words.finish()));
+            self.absorb(open.len);
+        }
+    }
+
+    fn absorb(&mut self, val: usize) {
+        let (hash, carry) = word::round(self.state, val);
+        self.state = hash_word_rounds::<2>(hash, carry);
+        self.absorbed += 1;
+    }
+}
+
+impl Default for Sponge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
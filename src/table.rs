@@ -0,0 +1,126 @@
+//! Helpers for tables probed a group of control bytes at a time, in the style of hashbrown and
+//! Abseil's Swiss tables.
+//!
+//! Each slot of such a table has a control byte: [`EMPTY`](crate::table::EMPTY),
+//! [`DELETED`](crate::table::DELETED), or the 7-bit `h2` of the hash of the key in the slot. A
+//! lookup splits its hash with [`split_hash`](crate::table::split_hash), starts probing at
+//! [`probe_start`](crate::table::probe_start) of `h1`, and checks
+//! [`GROUP_WIDTH`](crate::table::GROUP_WIDTH) control bytes at once with
+//! [`group_match`](crate::table::group_match), comparing keys only in the slots whose control byte
+//! matches `h2`.
+//!
+//! With the `simd` feature, [`group_match`](crate::table::group_match) compares the group with one
+//! SSE2 instruction on `x86_64` and with NEON on `aarch64`. Elsewhere, or without the feature, it
+//! compares a byte at a time; the results are the same either way.
+//!
+//! # Examples
+//!
+//! ```
+//! use cmhash::table::{group_match, probe_start, split_hash, EMPTY, GROUP_WIDTH};
+//!
+//! let mut ctrl = [EMPTY; GROUP_WIDTH];
+//! let (h1, h2) = split_hash(cmhash::hash_bytes(b"key", 0).0);
+//! let slot = probe_start(h1, GROUP_WIDTH - 1);
+//! ctrl[slot] = h2;
+//! assert_eq!(group_match(&ctrl, h2), 1 << slot);
+//! ```
+
+/// The control byte of a slot that has never held an entry
+pub const EMPTY: u8 = 0xFF;
+
+/// The control byte of a slot whose entry was removed
+pub const DELETED: u8 = 0x80;
+
+/// The number of control bytes [`group_match`] compares at once
+pub const GROUP_WIDTH: usize = 16;
+
+/// Splits `hash` into `h1`, which picks where probing starts, and `h2`, the control byte stored
+/// for the slot
+///
+/// `h2` is the top 7 bits of the hash, so its high bit is always clear and it never equals
+/// [`EMPTY`] or [`DELETED`]. `h1` is the hash itself, truncated to a `usize`; as
+/// [`probe_start`] masks it down to its low bits, `h1` and `h2` come from disjoint bits of the
+/// hash for any table of fewer than `2^57` buckets.
+#[inline]
+pub const fn split_hash(hash: u64) -> (usize, u8) {
+    (hash as usize, (hash >> 57) as u8)
+}
+
+/// Returns the position at which probing for `h1` starts in a table of `bucket_mask + 1`
+/// buckets
+///
+/// `bucket_mask` must be one less than a power of two.
+#[inline]
+pub const fn probe_start(h1: usize, bucket_mask: usize) -> usize {
+    debug_assert!(
+        bucket_mask.wrapping_add(1).is_power_of_two(),
+        "bucket_mask must be one less than a power of two"
+    );
+    h1 & bucket_mask
+}
+
+/// Returns a bitmask with bit `i` set where `ctrl[i] == h2`
+#[inline]
+pub fn group_match(ctrl: &[u8; GROUP_WIDTH], h2: u8) -> u16 {
+    #[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"))]
+    {
+        sse2::group_match(ctrl, h2)
+    }
+    #[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+    {
+        neon::group_match(ctrl, h2)
+    }
+    #[cfg(not(all(
+        feature = "simd",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "aarch64", target_feature = "neon")
+        )
+    )))]
+    {
+        group_match_portable(ctrl, h2)
+    }
+}
+
+/// [`group_match`] a byte at a time, which it falls back to without the `simd` feature
+pub fn group_match_portable(ctrl: &[u8; GROUP_WIDTH], h2: u8) -> u16 {
+    ctrl.iter()
+        .enumerate()
+        .fold(0, |mask, (i, &c)| mask | (u16::from(c == h2) << i))
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"))]
+mod sse2 {
+    use core::arch::x86_64::{_mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+
+    #[inline]
+    pub(super) fn group_match(ctrl: &[u8; super::GROUP_WIDTH], h2: u8) -> u16 {
+        // SAFETY: SSE2 is enabled for the target, and the load is unaligned and reads exactly
+        // the 16 bytes of `ctrl`
+        unsafe {
+            let group = _mm_loadu_si128(ctrl.as_ptr().cast());
+            _mm_movemask_epi8(_mm_cmpeq_epi8(group, _mm_set1_epi8(h2 as i8))) as u16
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use core::arch::aarch64::{
+        vaddv_u8, vandq_u8, vceqq_u8, vdupq_n_u8, vget_high_u8, vget_low_u8, vld1q_u8,
+    };
+
+    // The bit each lane contributes to its half of the mask
+    const LANE_BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+    #[inline]
+    pub(super) fn group_match(ctrl: &[u8; super::GROUP_WIDTH], h2: u8) -> u16 {
+        // SAFETY: NEON is enabled for the target, and the loads read exactly the 16 bytes of
+        // `ctrl` and `LANE_BITS`
+        unsafe {
+            let matches = vceqq_u8(vld1q_u8(ctrl.as_ptr()), vdupq_n_u8(h2));
+            let bits = vandq_u8(matches, vld1q_u8(LANE_BITS.as_ptr()));
+            u16::from(vaddv_u8(vget_low_u8(bits))) | u16::from(vaddv_u8(vget_high_u8(bits))) << 8
+        }
+    }
+}
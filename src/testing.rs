@@ -0,0 +1,128 @@
+//! Worst-case inputs for this crate's own hashers, for testing how a service behaves under hash
+//! flooding.
+//!
+//! Both generators target the unkeyed hashers: [`hash_u64`](crate::hash_u64),
+//! [`hash_bytes`](crate::hash_bytes) and the [`CMHasher`](crate::CMHasher)s behind them, under a
+//! seed the attacker knows. The keys they return are specific to that seed and spread normally
+//! under any other, which is why a service exposed to untrusted keys should pick its seed at
+//! random. Hashers keyed with a secret, such as [`KeyedHasher`](crate::KeyedHasher) with a random
+//! key, are out of scope.
+//!
+//! # Examples
+//!
+//! ```
+//! use cmhash::testing::multicollisions;
+//! use cmhash::{hash_to_bucket, hash_u64};
+//!
+//! let keys = multicollisions(7, 10, 100);
+//! assert!(keys.iter().all(|&k| hash_to_bucket(hash_u64(k, 7).0, 1 << 10) == 0));
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::hasher::DEFAULT_PRIME;
+
+/// Returns `count` distinct keys whose [`hash_u64`](crate::hash_u64) under `seed` all land in
+/// bucket 0 of `2^n_bucket_bits`, as reduced by [`hash_to_bucket`](crate::hash_to_bucket)
+///
+/// The word hasher is a bijection: one multiply by an odd constant under the seed, then
+/// [`Fmix64`](crate::Fmix64), both of which invert. So rather than searching, this inverts it
+/// for the hashes `0..count`, whose top `n_bucket_bits` bits are all zero. Those hashes also
+/// share the top 7 bits that Swiss tables compare first.
+///
+/// # Panics
+///
+/// Panics if `n_bucket_bits` is over 64, or if `count` keys don't fit in one bucket, that is if
+/// `count` is over `2^(64 - n_bucket_bits)`.
+pub fn multicollisions(seed: u64, n_bucket_bits: u32, count: usize) -> Vec<u64> {
+    assert!(n_bucket_bits <= 64, "a hash has only 64 bits to reduce");
+    let free_bits = 64 - n_bucket_bits;
+    assert!(
+        free_bits >= usize::BITS || count <= 1 << free_bits,
+        "{count} distinct hashes don't fit in one of 2^{n_bucket_bits} buckets"
+    );
+    (0..count as u64)
+        .map(|hash| unfmix64(hash).wrapping_mul(PRIME_INVERSE) ^ seed)
+        .collect()
+}
+
+/// Returns `count` distinct keys of `len` bytes whose [`hash_bytes`](crate::hash_bytes) under
+/// `seed` are all equal, so they collide in every table no matter how it reduces the hash
+///
+/// The keys are built from the words fed into the hasher. After each word the hasher keeps the high
+/// half of a widening multiply as its state and folds the low half into its output, and two
+/// words `a` and `b` often have products with equal high halves. Feeding `a` then `b` leaves
+/// the same state and output as feeding `b` then `a`, so each 16-byte block of a key can take
+/// either order independently, and `k` blocks give `2^k` keys that agree in the whole state of
+/// the hasher. The bytes after the last block are zero. Keys are written in native byte order,
+/// matching how [`hash_bytes`](crate::hash_bytes) reads them, so they collide on the machine that
+/// generates them.
+///
+/// These are exact collisions rather than the near collisions a generic search over the last
+/// word would find, and take time linear in `count * len`.
+///
+/// # Panics
+///
+/// Panics if `count` is over `2^(len / 16)`, the number of keys `len` bytes leave room for.
+pub fn near_collisions_bytes(seed: u64, len: usize, count: usize) -> Vec<Vec<u8>> {
+    let blocks = len / 16;
+    assert!(
+        blocks >= usize::BITS as usize || count <= 1 << blocks,
+        "{len} bytes leave room for at most 2^{blocks} colliding keys, not {count}"
+    );
+    let needed = count.next_power_of_two().trailing_zeros() as usize;
+    let (a, b, state) = swappable_pair();
+    (0..count)
+        .map(|i| {
+            let mut key = Vec::with_capacity(len);
+            let mut before = seed;
+            for block in 0..needed {
+                let (first, second) = if (i >> block) & 1 == 0 {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                key.extend_from_slice(&(first ^ before).to_ne_bytes());
+                key.extend_from_slice(&(second ^ state).to_ne_bytes());
+                before = state;
+            }
+            key.resize(len, 0);
+            key
+        })
+        .collect()
+}
+
+/// The inverse of [`DEFAULT_PRIME`] modulo `2^64`
+const PRIME_INVERSE: u64 = inverse(DEFAULT_PRIME);
+
+/// Inverts an odd `x` modulo `2^64` by Newton's iteration, each step doubling the correct bits
+const fn inverse(x: u64) -> u64 {
+    let mut inv = x;
+    let mut i = 0;
+    while i < 5 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(x.wrapping_mul(inv)));
+        i += 1;
+    }
+    inv
+}
+
+/// The inverse of the `fmix64` finalizer
+fn unfmix64(mut h: u64) -> u64 {
+    // Each `h ^= h >> 33` is its own inverse, as the shift is at least half the width
+    h ^= h >> 33;
+    h = h.wrapping_mul(inverse(0xC4CE_B9FE_1A85_EC53));
+    h ^= h >> 33;
+    h = h.wrapping_mul(inverse(0xFF51_AFD7_ED55_8CCD));
+    h ^ (h >> 33)
+}
+
+/// Finds two words whose products with [`DEFAULT_PRIME`] share their high half, returning them
+/// and that high half
+fn swappable_pair() -> (u64, u64, u64) {
+    let high = |x: u64| ((x as u128 * DEFAULT_PRIME as u128) >> 64) as u64;
+    let mut a = 1u64 << 63;
+    while high(a) != high(a + 1) {
+        a += 1;
+    }
+    (a, a + 1, high(a))
+}
@@ -0,0 +1,58 @@
+use core::hash::{Hash, Hasher};
+
+use crate::hasher::{fmix64, for_each_word, DEFAULT_HASHER_STATE};
+use crate::keyed::KeyedHasher;
+use crate::output::hash_combine;
+
+/// Returns a deterministic tag for the type `T`, for mixing into hashes of values from
+/// registries that hold several types, or into digests that should change with a schema
+///
+/// The tag is a hash of [`core::any::type_name`] with all whitespace removed, so that
+/// `(u8, u16)` and `(u8,u16)` tag alike. The name's little-endian words and then its length are
+/// folded in with [`hash_combine`], so unlike [`TypeId`](core::any::TypeId) the tag is the same
+/// in every build and on every platform, as long as the name is.
+///
+/// The name is not guaranteed to stay the same. The tag changes if the type is renamed or moved
+/// to another module, since the name includes its path, and it can change with a compiler
+/// release if `type_name` starts rendering names differently. Pin the tags a persisted format
+/// depends on in a test, as this crate does for some standard types, to notice when that
+/// happens.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::type_tag;
+///
+/// assert_eq!(type_tag::<u32>(), type_tag::<u32>());
+/// assert_ne!(type_tag::<u32>(), type_tag::<i32>());
+/// assert_ne!(type_tag::<Option<u8>>(), type_tag::<Option<u16>>());
+/// ```
+pub fn type_tag<T: ?Sized>() -> u64 {
+    let name = core::any::type_name::<T>();
+    let mut state = fmix64(DEFAULT_HASHER_STATE);
+    let mut len = 0;
+    for_each_word::<8>(
+        name.split_whitespace().map(|part| {
+            len += part.len();
+            part.as_bytes()
+        }),
+        |w| state = hash_combine(state, u64::from_le_bytes(w)),
+    );
+    hash_combine(state, len as u64)
+}
+
+/// Hashes `value` with a [`KeyedHasher`] keyed by [`type_tag`] of `T`, so that values of
+/// different types that write the same bytes hash differently
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::hash_tagged;
+///
+/// assert_ne!(hash_tagged(&7u32), hash_tagged(&7i32));
+/// ```
+pub fn hash_tagged<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut h = KeyedHasher::from_seed(type_tag::<T>());
+    value.hash(&mut h);
+    h.finish()
+}
@@ -0,0 +1,82 @@
+use core::hash::{Hash, Hasher};
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::hasher::Fmix64Hasher;
+use crate::mixer::Fmix64;
+use crate::output::{hash_str, DEFAULT_SEED};
+
+/// Returns `true` if `s` is certainly already in NFC, checking cheaply
+fn is_nfc(s: &str) -> bool {
+    s.is_ascii() || is_nfc_quick(s.chars()) == IsNormalized::Yes
+}
+
+/// Hashes `s` by its NFC normalization, so that canonically equivalent strings, like "café"
+/// written with a precomposed `é` and with `e` followed by a combining acute accent, hash
+/// equal.
+///
+/// The result is [`hash_str`] of the NFC form of `s`. Text that is already NFC, which includes
+/// all ASCII and most text in the wild, is recognized by a quick scan and hashed directly.
+/// Anything else is normalized one code point at a time straight into the hasher, which costs
+/// a table lookup or two per code point but builds no intermediate `String`. A string and its
+/// normalized form are meant to collide: that is the point of hashing this way.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::{hash_str, hash_str_nfc};
+///
+/// assert_eq!(hash_str_nfc("caf\u{e9}"), hash_str_nfc("cafe\u{301}"));
+/// assert_eq!(hash_str_nfc("cafe"), hash_str("cafe"));
+/// ```
+pub fn hash_str_nfc(s: &str) -> u64 {
+    if is_nfc(s) {
+        return hash_str(s);
+    }
+    let hasher = Fmix64Hasher::with_mixer(DEFAULT_SEED, Fmix64);
+    let mut stream = hasher.stream();
+    let mut utf8 = [0; 4];
+    for c in s.nfc() {
+        stream.write(c.encode_utf8(&mut utf8).as_bytes());
+    }
+    stream.finish();
+    hasher.finish()
+}
+
+/// A string map key that hashes and compares by its NFC normalization, so that canonically
+/// equivalent spellings of the same text find the same entry.
+///
+/// Every hash and every comparison of text that isn't already NFC normalizes it again, as
+/// [`hash_str_nfc`] does; normalize once up front instead if the same keys are looked up often.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use cmhash::NfcKey;
+///
+/// let mut users = HashSet::new();
+/// users.insert(NfcKey("Ren\u{e9}e"));
+/// assert!(users.contains(&NfcKey("Rene\u{301}e")));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NfcKey<S>(pub S);
+
+impl<S: AsRef<str>> PartialEq for NfcKey<S> {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.0.as_ref(), other.0.as_ref());
+        if is_nfc(a) && is_nfc(b) {
+            a == b
+        } else {
+            a.nfc().eq(b.nfc())
+        }
+    }
+}
+
+impl<S: AsRef<str>> Eq for NfcKey<S> {}
+
+impl<S: AsRef<str>> Hash for NfcKey<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(hash_str_nfc(self.0.as_ref()));
+    }
+}
@@ -0,0 +1,95 @@
+//! [wasm-bindgen](https://crates.io/crates/wasm-bindgen) bindings for calling this crate from
+//! JavaScript, in browsers and on edge runtimes.
+//!
+//! Every export hashes with a portable [`CMHasher`](crate::CMHasher) finalized with
+//! [`Fmix64`](crate::Fmix64): words are read little-endian, so a hash computed in JavaScript
+//! matches [`hash_bytes`](crate::hash_bytes) on a little-endian server, and a
+//! [`CMHasherBuilder`](crate::CMHasherBuilder) with `.portable(true)` and
+//! [`MixerChoice::Fmix64`](crate::MixerChoice::Fmix64) on any server.
+//!
+//! Seeds and hashes cross into JavaScript as `BigInt`s, since a `number` only holds 53 bits and
+//! would silently round most hashes.
+//!
+//! ```js
+//! import { hashBytes, hashString, StreamingHasher } from "cmhash";
+//!
+//! const whole = hashString("Hello, World!", 7n);
+//! const hasher = new StreamingHasher(7n);
+//! hasher.update(new TextEncoder().encode("Hello, "));
+//! hasher.update(new TextEncoder().encode("World!"));
+//! console.assert(hasher.finish() === whole);
+//! ```
+//!
+//! The bindings compile on every target, so they can be tested natively, but are only exported
+//! to JavaScript on `wasm32`.
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::hasher::{fmix64, WordBuffer};
+use crate::raw::{round_u64, PRIME_U64};
+
+/// Hashes `data` under `seed`
+///
+/// Exported as `hashBytes(data: Uint8Array, seed: bigint): bigint`.
+#[wasm_bindgen(js_name = hashBytes)]
+pub fn hash_bytes(data: &[u8], seed: u64) -> u64 {
+    let mut hasher = StreamingHasher::new(seed);
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Hashes the UTF-8 bytes of `s` under `seed`, as [`hash_bytes`] does
+///
+/// Exported as `hashString(s: string, seed: bigint): bigint`.
+#[wasm_bindgen(js_name = hashString)]
+pub fn hash_string(s: &str, seed: u64) -> u64 {
+    hash_bytes(s.as_bytes(), seed)
+}
+
+/// A hasher fed in parts, whose digest is the [`hash_bytes`] of everything passed to
+/// [`update`](Self::update) so far, however it was split
+///
+/// Exported as the class `StreamingHasher`, constructed with `new StreamingHasher(seed)`.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct StreamingHasher {
+    state: u64,
+    data: u64,
+    words: WordBuffer<8>,
+}
+
+#[wasm_bindgen]
+impl StreamingHasher {
+    /// Creates a hasher under `seed`
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            data: seed,
+            words: WordBuffer::new(),
+        }
+    }
+
+    /// Feeds `data` to the hasher, exactly as if it were appended to everything fed before
+    pub fn update(&mut self, data: &[u8]) {
+        let Self {
+            state,
+            data: acc,
+            words,
+        } = self;
+        words.push(data, |word| *acc ^= absorb(state, word));
+    }
+
+    /// Returns the hash of everything fed so far, leaving the hasher free to be fed more
+    pub fn finish(&self) -> u64 {
+        let mut state = self.state;
+        fmix64(self.data ^ absorb(&mut state, self.words.finish()))
+    }
+}
+
+/// One round of a portable [`CMHasher`](crate::CMHasher) over a little-endian word
+fn absorb(state: &mut u64, word: [u8; 8]) -> u64 {
+    let (hash, next) = round_u64(*state, u64::from_le_bytes(word), PRIME_U64);
+    *state = next;
+    hash
+}
@@ -0,0 +1,91 @@
+use core::ops::BitXor;
+
+/// A word width the core algorithm can be instantiated at
+pub(crate) trait Word: Copy + BitXor<Output = Self> {
+    /// The multiplier at this width: the Mersenne primes `2^13 - 1` and `2^31 - 1` at 16 and 32
+    /// bits, and at 64 bits `2^62 - 1`, which is not prime but is what the 64-bit hashers have
+    /// always multiplied by
+    const PRIME: Self;
+    /// The default state at this width
+    const DEFAULT_STATE: Self;
+
+    /// Multiplies two words, returning the low and high halves of the full product
+    fn wide_mul(self, rhs: Self) -> (Self, Self);
+
+    /// Xors `val` into `state` and multiplies by [`Self::PRIME`], returning the low and high
+    /// halves of the product
+    fn round(state: Self, val: Self) -> (Self, Self);
+}
+
+macro_rules! impl_word {
+    ($($word:ty => $double:ty, $prime:expr, $state:expr;)*) => {
+        $(
+            impl Word for $word {
+                const PRIME: Self = $prime;
+                const DEFAULT_STATE: Self = $state;
+
+                #[inline]
+                fn wide_mul(self, rhs: Self) -> (Self, Self) {
+                    let product = self as $double * rhs as $double;
+                    (product as Self, (product >> <$word>::BITS) as Self)
+                }
+
+                #[inline]
+                fn round(state: Self, val: Self) -> (Self, Self) {
+                    (val ^ state).wide_mul(Self::PRIME)
+                }
+            }
+        )*
+    };
+}
+
+impl_word! {
+    u16 => u32, (1 << 13) - 1, 0xAAAA;
+    u32 => u64, (1 << 31) - 1, 0xAAAA_AAAA;
+}
+
+// The 64-bit and word-sized rounds are the ones `raw` exposes, so they're defined there once
+impl Word for u64 {
+    const PRIME: Self = (2 << 61) - 1;
+    const DEFAULT_STATE: Self = 0xAAAA_AAAA_AAAA_AAAA;
+
+    #[inline]
+    fn wide_mul(self, rhs: Self) -> (Self, Self) {
+        let product = self as u128 * rhs as u128;
+        (product as Self, (product >> u64::BITS) as Self)
+    }
+
+    #[inline]
+    fn round(state: Self, val: Self) -> (Self, Self) {
+        crate::raw::round_u64(state, val, Self::PRIME)
+    }
+}
+
+impl Word for usize {
+    const PRIME: Self = crate::MERSENNE_PRIME;
+    const DEFAULT_STATE: Self = crate::DEFAULT_STATE;
+
+    #[inline]
+    fn wide_mul(self, rhs: Self) -> (Self, Self) {
+        self.widening_mul(rhs)
+    }
+
+    #[inline]
+    fn round(state: Self, val: Self) -> (Self, Self) {
+        crate::raw::round(state, val)
+    }
+}
+
+/// A single round of the algorithm: xors `val` into `state` and multiplies by the prime,
+/// returning the hash and the next state
+#[inline]
+pub(crate) fn round<W: Word>(state: W, val: W) -> (W, W) {
+    W::round(state, val)
+}
+
+/// One round from the default state with both halves of the product xored together
+#[inline]
+pub(crate) fn stateless<W: Word>(val: W) -> W {
+    let (hash, state) = round(W::DEFAULT_STATE, val);
+    hash ^ state
+}
@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+
+use crate::seed::Seed;
+use crate::sequence::HashSequence;
+
+/// A table of random words for Zobrist hashing, one per `(position, state)` pair.
+///
+/// The entries are drawn from a [`HashSequence`] in row-major order, so tables built from the same
+/// seed and dimensions are identical on every run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZobristTable {
+    entries: Vec<u64>,
+    states: usize,
+}
+
+impl ZobristTable {
+    /// Builds the table for `positions` positions that can each be in one of `states` states
+    pub fn new(positions: usize, states: usize, seed: impl Into<Seed>) -> Self {
+        Self {
+            entries: HashSequence::new(seed).take(positions * states).collect(),
+            states,
+        }
+    }
+
+    /// Returns the number of positions in the table
+    pub fn positions(&self) -> usize {
+        self.entries.len().checked_div(self.states).unwrap_or(0)
+    }
+
+    /// Returns the number of states each position can be in
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    /// Returns the word for `state` at `position`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` or `state` is out of range.
+    pub fn entry(&self, position: usize, state: usize) -> u64 {
+        assert!(state < self.states, "state out of range");
+        self.entries[position * self.states + state]
+    }
+
+    /// Starts an empty [`ZobristHash`] over this table
+    pub fn hasher(&self) -> ZobristHash<'_> {
+        ZobristHash {
+            table: self,
+            hash: 0,
+        }
+    }
+}
+
+/// An incrementally maintained Zobrist hash.
+///
+/// # Examples
+///
+/// ```
+/// use cmhash::ZobristTable;
+///
+/// let table = ZobristTable::new(64, 12, 0xC4E55);
+/// let mut board = table.hasher();
+/// board.toggle(12, 0); // place a piece
+/// let before = board.get();
+/// board.toggle(12, 0); // remove it
+/// board.toggle(28, 0); // and place it elsewhere
+/// let incremental = board.get();
+/// assert_ne!(incremental, before);
+/// // Recomputing from the occupied squares agrees with the incremental updates
+/// assert_eq!(board.recompute([(28, 0)]), incremental);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZobristHash<'a> {
+    table: &'a ZobristTable,
+    hash: u64,
+}
+
+impl ZobristHash<'_> {
+    /// Toggles `state` at `position` in or out of the hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` or `state` is out of range.
+    pub fn toggle(&mut self, position: usize, state: usize) {
+        self.hash ^= self.table.entry(position, state);
+    }
+
+    /// Returns the current hash
+    pub fn get(&self) -> u64 {
+        self.hash
+    }
+
+    /// Recomputes the hash from scratch for the given occupied `(position, state)` pairs,
+    /// replacing the incrementally maintained value, and returns it.
+    ///
+    /// Comparing the result with [`Self::get`] beforehand verifies the incremental updates.
+    pub fn recompute(&mut self, occupied: impl IntoIterator<Item = (usize, usize)>) -> u64 {
+        self.hash = occupied.into_iter().fold(0, |hash, (position, state)| {
+            hash ^ self.table.entry(position, state)
+        });
+        self.hash
+    }
+}
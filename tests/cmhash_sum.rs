@@ -0,0 +1,192 @@
+//! Runs the `cmhash-sum` binary on temporary files and standard input.
+
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use cmhash::hash_reader;
+
+/// `"Hello, World!"` under seed 0. Pinned: changing it breaks every stored checksum file.
+const HELLO_GOLDEN: &str = "8a1da9722196ab8a";
+
+/// A file in the temporary directory, removed when dropped
+struct TempFile(PathBuf);
+
+impl Deref for TempFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for TempFile {
+    fn as_ref(&self) -> &OsStr {
+        self.0.as_os_str()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn temp_file(name: &str, contents: &[u8]) -> TempFile {
+    let path = std::env::temp_dir().join(format!("cmhash-sum-{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    TempFile(path)
+}
+
+fn cmhash_sum() -> Command {
+    Command::cargo_bin("cmhash-sum").unwrap()
+}
+
+fn stdout_of(cmd: &mut Command) -> String {
+    String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+}
+
+#[test]
+fn stdin_output_is_pinned() {
+    let out = stdout_of(cmhash_sum().write_stdin("Hello, World!"));
+    assert_eq!(out, format!("{HELLO_GOLDEN}  -\n"));
+    let out = stdout_of(cmhash_sum().arg("-").write_stdin("Hello, World!"));
+    assert_eq!(out, format!("{HELLO_GOLDEN}  -\n"));
+}
+
+#[test]
+fn stdin_matches_hash_reader() {
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+    let out = stdout_of(
+        cmhash_sum()
+            .args(["--seed", "0x5EED"])
+            .write_stdin(data.clone()),
+    );
+    let expected = hash_reader(&data[..], 0x5EED).unwrap();
+    assert_eq!(out, format!("{expected:016x}  -\n"));
+}
+
+#[test]
+fn prints_a_line_per_file() {
+    let a = temp_file("lines-a", b"Hello, World!");
+    let b = temp_file("lines-b", b"");
+    let out = stdout_of(cmhash_sum().arg(&a).arg(&b));
+    let empty = hash_reader(&b""[..], 0).unwrap();
+    assert_eq!(
+        out,
+        format!(
+            "{HELLO_GOLDEN}  {}\n{empty:016x}  {}\n",
+            a.display(),
+            b.display()
+        )
+    );
+}
+
+#[test]
+fn seed_changes_the_digest() {
+    let file = temp_file("seed", b"Hello, World!");
+    let decimal = stdout_of(cmhash_sum().args(["--seed", "24301"]).arg(&file));
+    let hex = stdout_of(cmhash_sum().arg("--seed=0x5eed").arg(&file));
+    let bare = stdout_of(cmhash_sum().args(["-s", "0000000000005EED"]).arg(&file));
+    assert_eq!(decimal, hex);
+    assert_eq!(decimal, bare);
+    assert_ne!(decimal, stdout_of(cmhash_sum().arg(&file)));
+}
+
+#[test]
+fn stable_is_v1() {
+    let file = temp_file("algorithm", b"Hello, World!");
+    let stable = stdout_of(cmhash_sum().args(["--algorithm", "stable"]).arg(&file));
+    let v1 = stdout_of(cmhash_sum().args(["-a", "v1"]).arg(&file));
+    assert_eq!(stable, v1);
+    assert_eq!(stable, stdout_of(cmhash_sum().arg(&file)));
+}
+
+#[test]
+fn check_accepts_its_own_output() {
+    let a = temp_file("check-a", b"first");
+    let b = temp_file("check-b", b"second");
+    let sums = stdout_of(cmhash_sum().args(["--seed", "3"]).arg(&a).arg(&b));
+    let sums_file = temp_file("check-sums", sums.as_bytes());
+    let out = stdout_of(
+        cmhash_sum()
+            .args(["--seed", "3", "--check"])
+            .arg(&sums_file),
+    );
+    assert_eq!(out, format!("{}: OK\n{}: OK\n", a.display(), b.display()));
+    // Read from stdin too
+    stdout_of(cmhash_sum().args(["-s", "3", "-c", "-"]).write_stdin(sums));
+}
+
+#[test]
+fn check_fails_on_a_modified_file() {
+    let a = temp_file("modified-a", b"unchanged");
+    let b = temp_file("modified-b", b"original");
+    let sums = stdout_of(cmhash_sum().arg(&a).arg(&b));
+    let sums_file = temp_file("modified-sums", sums.as_bytes());
+    std::fs::write(&*b, b"tampered").unwrap();
+
+    let assert = cmhash_sum().arg("--check").arg(&sums_file).assert().code(1);
+    let output = assert.get_output();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("{}: OK", a.display())));
+    assert!(stdout.contains(&format!("{}: FAILED", b.display())));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 computed checksum(s) did NOT match"));
+}
+
+#[test]
+fn check_fails_on_a_missing_file_or_bad_lines() {
+    let missing = std::env::temp_dir().join("cmhash-sum-does-not-exist");
+    let sums = temp_file(
+        "missing-sums",
+        format!("0000000000000000  {}\n", missing.display()).as_bytes(),
+    );
+    cmhash_sum().arg("-c").arg(&sums).assert().code(1);
+
+    let garbage = temp_file("garbage-sums", b"not a checksum line\n");
+    cmhash_sum().arg("-c").arg(&garbage).assert().code(1);
+}
+
+#[test]
+fn check_from_stdin_rejects_stdin_entries() {
+    let file = temp_file("stdin-entry", b"Hello, World!");
+    let sums = format!("{HELLO_GOLDEN}  -\n{HELLO_GOLDEN}  {}\n", file.display());
+    let assert = cmhash_sum()
+        .args(["--check", "-"])
+        .write_stdin(sums)
+        .timeout(std::time::Duration::from_secs(10))
+        .assert()
+        .code(1);
+    let output = assert.get_output();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("-: FAILED open or read"), "{stdout}");
+    assert!(
+        stdout.contains(&format!("{}: OK", file.display())),
+        "{stdout}"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("standard input can't be checked"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn unreadable_files_fail() {
+    let missing = std::env::temp_dir().join("cmhash-sum-does-not-exist");
+    cmhash_sum().arg(&missing).assert().code(1);
+}
+
+#[test]
+fn bad_arguments_are_usage_errors() {
+    cmhash_sum().args(["--algorithm", "v2"]).assert().code(2);
+    cmhash_sum().args(["--algorithm", "md5"]).assert().code(2);
+    cmhash_sum().args(["--seed", "seven"]).assert().code(2);
+    cmhash_sum().arg("--seed").assert().code(2);
+    cmhash_sum().arg("--frobnicate").assert().code(2);
+    cmhash_sum().arg("--help").assert().success();
+}
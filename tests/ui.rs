@@ -0,0 +1,12 @@
+//! Compile-pass and compile-fail cases pinning which types can be shared across threads, how they
+//! can be placed and which traits are sealed. Regenerate the expected errors with `TRYBUILD=overwrite cargo test --test ui`
+//! after a deliberate change.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/fail/*.rs");
+    #[cfg(feature = "macros")]
+    cases.compile_fail("tests/ui/memoize/*.rs");
+}
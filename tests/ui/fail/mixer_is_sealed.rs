@@ -0,0 +1,11 @@
+use cmhash::Mixer;
+
+struct Identity;
+
+impl Mixer for Identity {
+    fn mix(&self, h: u64) -> u64 {
+        h
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use std::thread;
+
+use cmhash::TLCoreHasher;
+
+fn main() {
+    let hasher = TLCoreHasher::new();
+    thread::scope(|s| {
+        s.spawn(|| hasher.hash_word(1));
+    });
+}
@@ -0,0 +1,30 @@
+#[cmhash::memoize(capacity = 8)]
+fn generic<T: Clone + 'static>(x: T) -> T {
+    x
+}
+
+#[cmhash::memoize(capacity = 8)]
+fn borrowed(name: &str) -> usize {
+    name.len()
+}
+
+struct Counter;
+
+impl Counter {
+    #[cmhash::memoize(capacity = 8)]
+    fn method(&self, x: u32) -> u32 {
+        x
+    }
+}
+
+#[cmhash::memoize]
+fn unbounded(x: u32) -> u32 {
+    x
+}
+
+#[cmhash::memoize(capacity = 8, ttl = 5)]
+fn unknown_option(x: u32) -> u32 {
+    x
+}
+
+fn main() {}
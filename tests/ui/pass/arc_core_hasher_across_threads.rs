@@ -0,0 +1,17 @@
+use std::sync::Arc;
+use std::thread;
+
+use cmhash::CoreHasher;
+
+fn main() {
+    let hasher = Arc::new(CoreHasher::new());
+    let workers: Vec<_> = (0..4)
+        .map(|i| {
+            let hasher = Arc::clone(&hasher);
+            thread::spawn(move || hasher.hash_word(i))
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
@@ -0,0 +1,16 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use cmhash::{BuildHasherSeeded, CoreHasher, RawCoreState, StatelessBuildHasher};
+
+static SEEDED: BuildHasherSeeded<0xC0FFEE> = BuildHasherSeeded;
+static STATELESS: StatelessBuildHasher = StatelessBuildHasher;
+static STATE: RawCoreState = RawCoreState::INIT;
+
+fn main() {
+    let mut map = HashMap::with_hasher(SEEDED);
+    map.insert("key", 1);
+    assert_eq!(SEEDED.hash_one(7u64), SEEDED.hash_one(7u64));
+    assert_eq!(STATELESS.hash_one(7u64), STATELESS.hash_one(7u64));
+    CoreHasher::from_raw(&STATE).hash_word(7);
+}
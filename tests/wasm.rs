@@ -0,0 +1,37 @@
+//! Checks of the JavaScript bindings on `wasm32`, against golden values computed natively. Run
+//! with `wasm-pack test --node -- --features wasm-bindgen`.
+
+#![cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+
+use cmhash::wasm::{hash_bytes, hash_string, StreamingHasher};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn matches_native_golden_values() {
+    let ramp: Vec<u8> = (0..100).collect();
+    assert_eq!(hash_string("Hello, World!", 7), 0x976c_9c4c_5c9a_0c52);
+    assert_eq!(hash_bytes(&ramp, 0x5EED), 0x921d_407f_36d2_a4f5);
+    assert_eq!(hash_bytes(&[0xFF; 8], u64::MAX), 0x64b5_720b_4b82_5f21);
+
+    let mut hasher = StreamingHasher::new(0x5EED);
+    hasher.update(&ramp[..33]);
+    hasher.update(&ramp[33..]);
+    assert_eq!(hasher.finish(), 0x921d_407f_36d2_a4f5);
+}
+
+#[wasm_bindgen_test]
+fn full_width_values_survive_bigint() {
+    // Both have bits above the 53 a JavaScript number holds exactly
+    for hash in [
+        hash_string("Hello, World!", 7),
+        hash_bytes(&[0xFF; 8], u64::MAX),
+    ] {
+        assert_ne!(hash as f64 as u64, hash);
+        let js = JsValue::from(hash);
+        assert!(js.is_bigint());
+        assert_eq!(u64::try_from(js).unwrap(), hash);
+    }
+    let seed = JsValue::from(u64::MAX);
+    assert_eq!(u64::try_from(seed).unwrap(), u64::MAX);
+}